@@ -1,5 +1,7 @@
 #![doc(html_root_url = "https://docs.rs/rayon-hash/0.4")]
 
+#[cfg(feature = "rand_core")]
+extern crate rand_core;
 extern crate rayon;
 
 #[cfg(test)] extern crate rand;
@@ -13,6 +15,12 @@ mod ptr;
 pub use self::hash_map::HashMap;
 // #[stable(feature = "rust1", since = "1.0.0")]
 pub use self::hash_set::HashSet;
+pub use self::hash_counter::HashCounter;
+pub use self::hash_multimap::MultiMap;
+pub use self::hash_bimap::BiHashMap;
+pub use self::hash_indexed::IndexedHashMap;
+pub use self::hash_ttl::TtlHashMap;
+pub use self::hash_frozen::FrozenHashMap;
 
 mod par;
 mod std_hash;
@@ -22,7 +30,14 @@ pub mod hash_map {
     //! A hash map implemented with linear probing and Robin Hood bucket stealing.
     // #[stable(feature = "rust1", since = "1.0.0")]
     pub use super::std_hash::map::*;
+    pub use super::std_hash::codec::*;
+    pub use super::std_hash::snapshot::{DeterministicState, NoPadding};
+    pub use super::std_hash::fixed::{FixedHasher, FixedState};
+    pub use super::std_hash::cursor::{Cursor, CursorMut};
+    pub use super::std_hash::split::{TableSliceIter, TableSliceIterMut, TableSliceMut};
+    pub use super::std_hash::lru::*;
     pub use super::par::map::*;
+    pub use super::par::lru::*;
 }
 
 // #[stable(feature = "rust1", since = "1.0.0")]
@@ -30,5 +45,42 @@ pub mod hash_set {
     //! A hash set implemented as a `HashMap` where the value is `()`.
     // #[stable(feature = "rust1", since = "1.0.0")]
     pub use super::std_hash::set::*;
+    pub use super::std_hash::interner::*;
     pub use super::par::set::*;
 }
+
+pub mod hash_counter {
+    //! A hash multiset implemented as a `HashMap` from values to their counts.
+    pub use super::std_hash::counter::*;
+}
+
+pub mod hash_multimap {
+    //! A hash multimap implemented as a `HashMap` from keys to groups of values.
+    pub use super::std_hash::multimap::*;
+    pub use super::par::multimap::*;
+}
+
+pub mod hash_bimap {
+    //! A bidirectional hash map implemented with a table for each side.
+    pub use super::std_hash::bimap::*;
+}
+
+pub mod hash_indexed {
+    //! An insertion-ordered hash map, with entries in a `Vec` and a table of
+    //! their indices.
+    pub use super::std_hash::indexed::*;
+    pub use super::par::indexed::*;
+}
+
+pub mod hash_ttl {
+    //! A hash map with expiring entries, implemented as a `HashMap` from
+    //! keys to values with their expiry times.
+    pub use super::std_hash::ttl::*;
+}
+
+pub mod hash_frozen {
+    //! A read-only hash map over a borrowed snapshot of a `HashMap`'s table,
+    //! such as a memory-mapped file.
+    pub use super::std_hash::frozen::*;
+    pub use super::par::frozen::*;
+}
@@ -0,0 +1,112 @@
+/// Rayon extensions for `BiHashMap`
+use rayon::iter::{FromParallelIterator, IntoParallelIterator, ParallelExtend};
+use rayon::iter::IntoParallelRefIterator;
+use std::hash::{BuildHasher, Hash};
+
+use super::map::{ParIter, ParKeys};
+use crate::BiHashMap;
+
+impl<L: Sync, R: Sync, S> BiHashMap<L, R, S> {
+    /// Visits all pairs in parallel, split over the buckets of the left
+    /// side's table.
+    pub fn par_iter_left(&self) -> ParIter<'_, L, R> {
+        self.left.par_iter()
+    }
+
+    /// Visits all pairs in parallel, with the right value first, split over
+    /// the buckets of the right side's table.
+    pub fn par_iter_right(&self) -> ParIter<'_, R, L> {
+        self.right.par_iter()
+    }
+
+    pub fn par_left_values(&self) -> ParKeys<'_, L, R> {
+        self.left.par_keys()
+    }
+
+    pub fn par_right_values(&self) -> ParKeys<'_, R, L> {
+        self.right.par_keys()
+    }
+}
+
+impl<'a, L: Sync, R: Sync, S> IntoParallelIterator for &'a BiHashMap<L, R, S> {
+    type Item = (&'a L, &'a R);
+    type Iter = ParIter<'a, L, R>;
+
+    fn into_par_iter(self) -> Self::Iter {
+        self.par_iter_left()
+    }
+}
+
+/// Collect pairs from a parallel iterator into a bimap.  Pairs produced
+/// later in the parallel iterator overwrite earlier ones, just as with a
+/// sequential iterator.
+impl<L, R, S> FromParallelIterator<(L, R)> for BiHashMap<L, R, S>
+where
+    L: Eq + Hash + Clone + Send,
+    R: Eq + Hash + Clone + Send,
+    S: BuildHasher + Default,
+{
+    fn from_par_iter<P>(par_iter: P) -> Self
+    where
+        P: IntoParallelIterator<Item = (L, R)>,
+    {
+        let mut map = BiHashMap::default();
+        map.par_extend(par_iter);
+        map
+    }
+}
+
+// This is equal to the normal `BiHashMap` -- no custom advantage.
+impl<L, R, S> ParallelExtend<(L, R)> for BiHashMap<L, R, S>
+where
+    L: Eq + Hash + Clone + Send,
+    R: Eq + Hash + Clone + Send,
+    S: BuildHasher,
+{
+    fn par_extend<I>(&mut self, par_iter: I)
+    where
+        I: IntoParallelIterator<Item = (L, R)>,
+    {
+        let (list, len) = super::collect(par_iter);
+
+        // Pairs may overwrite each other, so only reserve half the length
+        // (rounded up) unless the map is empty, like `HashMap`.
+        let reserve = if self.is_empty() { len } else { len - len / 2 };
+        self.reserve(reserve);
+        for vec in list {
+            self.extend(vec);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_par_bimap {
+    use crate::BiHashMap;
+    use rayon::prelude::*;
+
+    #[test]
+    fn test_par_iter() {
+        let map: BiHashMap<u32, String> = (0..10_000u32)
+            .into_par_iter()
+            .map(|i| (i, format!("#{}", i)))
+            .collect();
+        assert_eq!(map.len(), 10_000);
+
+        assert!(map.par_iter_left().all(|(l, r)| *r == format!("#{}", l)));
+        assert!(map.par_iter_right().all(|(r, l)| map.get_by_left(l) == Some(r)));
+        assert_eq!(map.par_left_values().sum::<u32>(), (0..10_000).sum());
+        assert_eq!(map.par_right_values().count(), 10_000);
+        assert_eq!(map.par_iter().count(), 10_000);
+    }
+
+    #[test]
+    fn test_par_collect_overwrites() {
+        // Later pairs win, so every right value ends up with the last left
+        // value that used it.
+        let map: BiHashMap<u32, u32> = (0..1000u32).into_par_iter().map(|i| (i, i % 10)).collect();
+        assert_eq!(map.len(), 10);
+        for r in 0..10 {
+            assert_eq!(map.get_by_right(&r), Some(&(990 + r)));
+        }
+    }
+}
@@ -0,0 +1,381 @@
+/// Rayon extensions for encoding `HashMap` and `HashSet` in binary
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use std::cmp;
+use std::hash::{BuildHasher, Hash};
+use std::io::{self, Read, Write};
+
+use super::map::collect_sharded;
+use crate::std_hash::codec::{checksum, hasher_id, invalid_data, ChunkHeader};
+use crate::std_hash::codec::{Decode, Encode, Header, FORMAT_VERSION};
+use crate::std_hash::table::{make_hash, RawTable};
+use crate::{HashMap, HashSet};
+
+/// The number of buckets in each chunk, unless the table is smaller.  This
+/// is fixed, so the encoding doesn't depend on the number of threads.
+const CHUNK_BUCKETS: usize = 1 << 14;
+
+/// How many chunks to hold in memory at once, per thread.
+const CHUNKS_PER_THREAD: usize = 2;
+
+/// The capacity in a header isn't covered by any checksum, so a decoded map
+/// gets at most this many times its length.
+const MAX_CAPACITY_FACTOR: usize = 8;
+
+fn chunks_per_batch() -> usize {
+    cmp::max(1, rayon::current_num_threads()) * CHUNKS_PER_THREAD
+}
+
+/// Writes the header and then the table in chunks of buckets.  The chunks
+/// of each batch are encoded in parallel, and written in order.
+fn write_table<K, V, W, F>(
+    table: &RawTable<K, V>,
+    hasher_id: u64,
+    capacity: usize,
+    writer: &mut W,
+    encode: F,
+) -> io::Result<()>
+where
+    K: Sync,
+    V: Sync,
+    W: Write,
+    F: Fn(&K, &V, &mut Vec<u8>) + Sync,
+{
+    let buckets = table.capacity();
+    let chunks = if buckets == 0 {
+        0
+    } else {
+        cmp::max(1, buckets / CHUNK_BUCKETS)
+    };
+    let header = Header {
+        version: FORMAT_VERSION,
+        hasher_id,
+        capacity: capacity as u64,
+        len: table.size() as u64,
+        chunks: chunks as u64,
+    };
+    header.write_to(writer)?;
+
+    let chunk_len = buckets.checked_div(chunks).unwrap_or(0);
+    let mut start = 0;
+    while start < chunks {
+        let end = cmp::min(chunks, start + chunks_per_batch());
+        let encoded: Vec<_> = (start..end)
+            .into_par_iter()
+            .map(|i| {
+                let mut bytes = Vec::new();
+                let mut len = 0;
+                for (key, value) in table.bucket_range(i * chunk_len, (i + 1) * chunk_len) {
+                    encode(key, value, &mut bytes);
+                    len += 1;
+                }
+                let header = ChunkHeader {
+                    len,
+                    bytes: bytes.len() as u64,
+                    checksum: checksum(&bytes),
+                };
+                (header, bytes)
+            }).collect();
+        for (header, bytes) in encoded {
+            header.write_to(writer)?;
+            writer.write_all(&bytes)?;
+        }
+        start = end;
+    }
+    Ok(())
+}
+
+/// Reads the header and the chunks of entries, checking and decoding the
+/// chunks of each batch in parallel.
+fn read_chunks<T, R, F>(reader: &mut R, decode: F) -> io::Result<(Header, Vec<Vec<T>>)>
+where
+    T: Send,
+    R: Read,
+    F: Fn(&mut &[u8]) -> io::Result<T> + Sync,
+{
+    let header = Header::read_from(reader)?;
+    let mut chunks = Vec::new();
+    let mut len = 0;
+    let mut left = header.chunks;
+    while left > 0 {
+        let batch = cmp::min(left, chunks_per_batch() as u64);
+        let mut raw = Vec::new();
+        for _ in 0..batch {
+            let chunk = ChunkHeader::read_from(reader)?;
+            // Don't trust the length for the allocation, in case it's corrupt.
+            let mut bytes = Vec::new();
+            Read::take(&mut *reader, chunk.bytes).read_to_end(&mut bytes)?;
+            if bytes.len() as u64 != chunk.bytes {
+                return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "truncated chunk"));
+            }
+            raw.push((chunk, bytes));
+        }
+
+        let decoded: Vec<Vec<T>> = raw
+            .into_par_iter()
+            .map(|(chunk, bytes)| {
+                if checksum(&bytes) != chunk.checksum {
+                    return Err(invalid_data("chunk checksum mismatch"));
+                }
+                let mut input = &bytes[..];
+                let mut items = Vec::with_capacity(cmp::min(chunk.len, bytes.len() as u64) as usize);
+                for _ in 0..chunk.len {
+                    items.push(decode(&mut input)?);
+                }
+                if !input.is_empty() {
+                    return Err(invalid_data("trailing bytes in chunk"));
+                }
+                Ok(items)
+            }).collect::<io::Result<_>>()?;
+
+        len += decoded.iter().map(Vec::len).sum::<usize>() as u64;
+        chunks.extend(decoded);
+        left -= batch;
+    }
+    if len != header.len {
+        return Err(invalid_data("wrong number of entries"));
+    }
+    Ok((header, chunks))
+}
+
+impl<K, V, S> HashMap<K, V, S>
+where
+    K: Eq + Hash + Send,
+    V: Send,
+    S: BuildHasher + Sync,
+{
+    /// Builds the table from decoded entries, hashing them in parallel.  It's
+    /// sized just once, with room for the capacity in the header, as far as
+    /// that's within `MAX_CAPACITY_FACTOR` times the number of entries.
+    fn fill_decoded(&mut self, header: &Header, chunks: Vec<Vec<(K, V)>>) {
+        let len: usize = chunks.iter().map(Vec::len).sum();
+        let max_capacity = len.saturating_mul(MAX_CAPACITY_FACTOR) as u64;
+        let capacity = cmp::min(header.capacity, max_capacity) as usize;
+
+        let hash_builder = self.hasher();
+        let entries = chunks
+            .into_par_iter()
+            .flat_map(|chunk| chunk.into_par_iter())
+            .map(|(key, value)| (make_hash(hash_builder, &key), key, value));
+        self.table = collect_sharded(entries, capacity, |_, value, later| *value = later);
+    }
+
+    /// Reads a map written by [`write_to`], decoding the keys and values
+    /// with `decoder`, and hashing them with `hash_builder`.
+    ///
+    /// Returns an error of kind `InvalidData` if the data is corrupt, which
+    /// includes a chunk's bytes not matching their checksum.
+    ///
+    /// [`write_to`]: #method.write_to
+    pub fn read_from_with_hasher<R, D>(reader: &mut R, decoder: &D, hash_builder: S) -> io::Result<Self>
+    where
+        R: Read,
+        D: Decode<K> + Decode<V> + Sync,
+    {
+        let (header, chunks) = read_chunks(reader, |input| {
+            let key = decoder.decode(input)?;
+            let value = decoder.decode(input)?;
+            Ok((key, value))
+        })?;
+        let mut map = HashMap::with_hasher(hash_builder);
+        map.fill_decoded(&header, chunks);
+        Ok(map)
+    }
+
+    /// Reads a map written by [`write_to`], decoding the keys and values
+    /// with `decoder`.  See [`read_from_with_hasher`].
+    ///
+    /// [`write_to`]: #method.write_to
+    /// [`read_from_with_hasher`]: #method.read_from_with_hasher
+    pub fn read_from<R, D>(reader: &mut R, decoder: &D) -> io::Result<Self>
+    where
+        R: Read,
+        D: Decode<K> + Decode<V> + Sync,
+        S: Default,
+    {
+        Self::read_from_with_hasher(reader, decoder, S::default())
+    }
+}
+
+impl<K, V, S> HashMap<K, V, S>
+where
+    K: Eq + Hash + Sync,
+    V: Sync,
+    S: BuildHasher,
+{
+    /// Writes the map in a compact binary format, encoding the keys and
+    /// values with `encoder`.  See [`Header`] for the layout.
+    ///
+    /// The table's buckets are split into fixed ranges, which are encoded in
+    /// parallel, a few at a time, with a checksum for each.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rayon_hash::hash_map::BinaryCodec;
+    /// use rayon_hash::HashMap;
+    ///
+    /// let map: HashMap<u32, String> = (0..100).map(|i| (i, i.to_string())).collect();
+    /// let mut bytes = Vec::new();
+    /// map.write_to(&mut bytes, &BinaryCodec).unwrap();
+    ///
+    /// let copy: HashMap<u32, String> = HashMap::read_from(&mut &bytes[..], &BinaryCodec).unwrap();
+    /// assert_eq!(copy, map);
+    /// ```
+    ///
+    /// [`Header`]: struct.Header.html
+    pub fn write_to<W, E>(&self, writer: &mut W, encoder: &E) -> io::Result<()>
+    where
+        W: Write,
+        E: Encode<K> + Encode<V> + Sync,
+    {
+        let id = hasher_id(self.hasher());
+        write_table(&self.table, id, self.capacity(), writer, |key, value, out| {
+            encoder.encode(key, out);
+            encoder.encode(value, out);
+        })
+    }
+}
+
+impl<T, S> HashSet<T, S>
+where
+    T: Eq + Hash + Send,
+    S: BuildHasher + Sync,
+{
+    /// Reads a set written by [`write_to`], decoding the values with
+    /// `decoder`, and hashing them with `hash_builder`.
+    ///
+    /// Returns an error of kind `InvalidData` if the data is corrupt, which
+    /// includes a chunk's bytes not matching their checksum.
+    ///
+    /// [`write_to`]: #method.write_to
+    pub fn read_from_with_hasher<R, D>(reader: &mut R, decoder: &D, hash_builder: S) -> io::Result<Self>
+    where
+        R: Read,
+        D: Decode<T> + Sync,
+    {
+        let (header, chunks) = read_chunks(reader, |input| Ok((decoder.decode(input)?, ())))?;
+        let mut set = HashSet::with_hasher(hash_builder);
+        set.map.fill_decoded(&header, chunks);
+        Ok(set)
+    }
+
+    /// Reads a set written by [`write_to`], decoding the values with
+    /// `decoder`.  See [`read_from_with_hasher`].
+    ///
+    /// [`write_to`]: #method.write_to
+    /// [`read_from_with_hasher`]: #method.read_from_with_hasher
+    pub fn read_from<R, D>(reader: &mut R, decoder: &D) -> io::Result<Self>
+    where
+        R: Read,
+        D: Decode<T> + Sync,
+        S: Default,
+    {
+        Self::read_from_with_hasher(reader, decoder, S::default())
+    }
+}
+
+impl<T, S> HashSet<T, S>
+where
+    T: Eq + Hash + Sync,
+    S: BuildHasher,
+{
+    /// Writes the set in a compact binary format, encoding the values with
+    /// `encoder`.  This is the same format as `HashMap::write_to`, with only
+    /// the keys encoded.
+    pub fn write_to<W, E>(&self, writer: &mut W, encoder: &E) -> io::Result<()>
+    where
+        W: Write,
+        E: Encode<T> + Sync,
+    {
+        let map = &self.map;
+        let id = hasher_id(map.hasher());
+        write_table(&map.table, id, map.capacity(), writer, |key, _, out| {
+            encoder.encode(key, out)
+        })
+    }
+}
+
+#[cfg(test)]
+mod test_par_codec {
+    use crate::hash_map::{BinaryCodec, Header};
+    use crate::{HashMap, HashSet};
+    use std::io::ErrorKind;
+
+    #[test]
+    fn test_map_round_trip() {
+        let map: HashMap<u64, String> = (0..100_000).map(|i| (i, format!("v{}", i))).collect();
+        let mut bytes = Vec::new();
+        map.write_to(&mut bytes, &BinaryCodec).unwrap();
+
+        let header = Header::read_from(&mut &bytes[..]).unwrap();
+        assert_eq!(header.len, 100_000);
+        assert_eq!(header.capacity, map.capacity() as u64);
+        assert!(header.chunks > 1);
+
+        let copy: HashMap<u64, String> = HashMap::read_from(&mut &bytes[..], &BinaryCodec).unwrap();
+        assert_eq!(copy, map);
+        assert!(copy.capacity() >= map.capacity());
+
+        // The hasher id only matches when the keys are hashed the same way.
+        let mut again = Vec::new();
+        copy.write_to(&mut again, &BinaryCodec).unwrap();
+        assert_ne!(Header::read_from(&mut &again[..]).unwrap().hasher_id, header.hasher_id);
+
+        let hash_builder = map.hasher().clone();
+        let copy: HashMap<u64, String> =
+            HashMap::read_from_with_hasher(&mut &bytes[..], &BinaryCodec, hash_builder).unwrap();
+        let mut again = Vec::new();
+        copy.write_to(&mut again, &BinaryCodec).unwrap();
+        assert_eq!(Header::read_from(&mut &again[..]).unwrap(), header);
+        assert_eq!(again.len(), bytes.len());
+    }
+
+    #[test]
+    fn test_set_round_trip() {
+        let empty: HashSet<i32> = HashSet::new();
+        let mut bytes = Vec::new();
+        empty.write_to(&mut bytes, &BinaryCodec).unwrap();
+        let copy: HashSet<i32> = HashSet::read_from(&mut &bytes[..], &BinaryCodec).unwrap();
+        assert!(copy.is_empty());
+
+        let set: HashSet<i32> = (-5000..5000).collect();
+        let mut bytes = Vec::new();
+        set.write_to(&mut bytes, &BinaryCodec).unwrap();
+        let copy: HashSet<i32> = HashSet::read_from(&mut &bytes[..], &BinaryCodec).unwrap();
+        assert_eq!(copy, set);
+    }
+
+    #[test]
+    fn test_corruption() {
+        let map: HashMap<u32, u32> = (0..50_000).map(|i| (i, i)).collect();
+        let mut bytes = Vec::new();
+        map.write_to(&mut bytes, &BinaryCodec).unwrap();
+        let read = |bytes: &[u8]| HashMap::<u32, u32>::read_from(&mut &bytes[..], &BinaryCodec);
+
+        let mut flipped = bytes.clone();
+        let last = flipped.len() - 1;
+        flipped[last] ^= 1;
+        assert_eq!(read(&flipped).unwrap_err().kind(), ErrorKind::InvalidData);
+
+        let truncated = &bytes[..bytes.len() - 1];
+        assert_eq!(read(truncated).unwrap_err().kind(), ErrorKind::UnexpectedEof);
+
+        let mut versioned = bytes.clone();
+        versioned[4] = 2;
+        assert_eq!(read(&versioned).unwrap_err().kind(), ErrorKind::InvalidData);
+
+        // The capacity isn't checksummed, so it's only trusted so far.
+        let mut huge = bytes.clone();
+        for byte in &mut huge[16..24] {
+            *byte = 0xff;
+        }
+        assert_eq!(Header::read_from(&mut &huge[..]).unwrap().capacity, !0);
+        let copy = read(&huge).unwrap();
+        assert_eq!(copy, map);
+        assert!(copy.capacity() < 50_000 * 16);
+
+        // Decoding with the wrong types is caught by the chunk lengths.
+        let err = HashMap::<u64, u64>::read_from(&mut &bytes[..], &BinaryCodec).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,187 @@
+/// Rayon extensions for `HashCounter`
+use rayon::iter::{FromParallelIterator, IntoParallelIterator, ParallelExtend, ParallelIterator};
+use rayon::iter::IntoParallelRefIterator;
+use std::hash::{BuildHasher, Hash};
+
+use super::map::{self, fold_sharded, merge_sharded};
+use crate::std_hash::counter::by_count;
+use crate::std_hash::table::make_hash;
+use crate::std_hash::top_k::TopK;
+use crate::HashCounter;
+
+impl<T: Sync, S> HashCounter<T, S> {
+    /// Returns the `k` most common values and their counts, in order from
+    /// the most common, like `most_common` but scanning the table in
+    /// parallel.  Each job keeps its own top `k`, and these are merged.
+    pub fn par_most_common(&self, k: usize) -> Vec<(&T, usize)> {
+        self.map
+            .par_iter()
+            .fold(
+                || TopK::new(k, &by_count),
+                |mut top, (value, &count)| {
+                    top.push((value, count));
+                    top
+                },
+            ).reduce(|| TopK::new(k, &by_count), TopK::merge)
+            .into_vec()
+    }
+}
+
+impl<T: Sync, S> HashCounter<T, S> {
+    /// Returns a parallel iterator over the values and their counts, in no
+    /// particular order.
+    pub fn par_iter(&self) -> map::ParIter<'_, T, usize> {
+        self.map.par_iter()
+    }
+}
+
+impl<T: Send, S> IntoParallelIterator for HashCounter<T, S> {
+    type Item = (T, usize);
+    type Iter = map::ParIntoIter<T, usize>;
+
+    fn into_par_iter(self) -> Self::Iter {
+        self.map.into_par_iter()
+    }
+}
+
+impl<'a, T: Sync, S> IntoParallelIterator for &'a HashCounter<T, S> {
+    type Item = (&'a T, &'a usize);
+    type Iter = map::ParIter<'a, T, usize>;
+
+    fn into_par_iter(self) -> Self::Iter {
+        self.map.par_iter()
+    }
+}
+
+/// Count the values of a parallel iterator.
+impl<T, S> FromParallelIterator<T> for HashCounter<T, S>
+where
+    T: Eq + Hash + Send,
+    S: BuildHasher + Default + Send + Sync,
+{
+    fn from_par_iter<P>(par_iter: P) -> Self
+    where
+        P: IntoParallelIterator<Item = T>,
+    {
+        let mut counter = HashCounter::default();
+        counter.par_extend(par_iter);
+        counter
+    }
+}
+
+/// Add one occurrence of each value of a parallel iterator.
+///
+/// The values are hashed and counted in parallel shards, and then the counts
+/// are added to the existing ones, without hashing anything again.  An
+/// empty counter just takes the new table.
+impl<T, S> ParallelExtend<T> for HashCounter<T, S>
+where
+    T: Eq + Hash + Send,
+    S: BuildHasher + Send + Sync,
+{
+    fn par_extend<I>(&mut self, par_iter: I)
+    where
+        I: IntoParallelIterator<Item = T>,
+    {
+        let hash_builder = self.map.hasher();
+        let values = par_iter
+            .into_par_iter()
+            .map(|value| (make_hash(hash_builder, &value), value, ()));
+        let counts = fold_sharded(
+            values,
+            || 0,
+            |_, count, ()| *count += 1,
+            |_, count, n| *count += n,
+        );
+        merge_sharded(&mut self.map, counts, |entry, n| *entry.or_insert(0) += n);
+    }
+}
+
+/// Add one occurrence of each copied value of a parallel iterator.
+impl<'a, T, S> ParallelExtend<&'a T> for HashCounter<T, S>
+where
+    T: Copy + Eq + Hash + Send + Sync,
+    S: BuildHasher + Send + Sync,
+{
+    fn par_extend<I>(&mut self, par_iter: I)
+    where
+        I: IntoParallelIterator<Item = &'a T>,
+    {
+        self.par_extend(par_iter.into_par_iter().cloned());
+    }
+}
+
+#[cfg(test)]
+mod test_par_counter {
+    use crate::HashCounter;
+    use rayon::prelude::*;
+
+    #[test]
+    fn test_from_par_iter() {
+        let words = ["apple", "banana", "cherry", "banana", "cherry", "cherry"];
+        let counter: HashCounter<&str> = (0..60_000)
+            .into_par_iter()
+            .map(|i| words[i % words.len()])
+            .collect();
+        assert_eq!(counter.len(), 3);
+        assert_eq!(counter.get("apple"), 10_000);
+        assert_eq!(counter.get("banana"), 20_000);
+        assert_eq!(counter.get("cherry"), 30_000);
+        assert_eq!(counter.total(), 60_000);
+    }
+
+    #[test]
+    fn test_par_extend() {
+        let mut counter: HashCounter<u32> = (0..1000).collect();
+        counter.par_extend((0..100_000u32).into_par_iter().map(|i| i % 2000));
+        assert_eq!(counter.len(), 2000);
+        for i in 0..2000 {
+            assert_eq!(counter.get(&i), if i < 1000 { 51 } else { 50 });
+        }
+
+        // The merged table is still a normal counter.
+        assert_eq!(counter.add(7), 52);
+        assert_eq!(counter.remove(&8), 51);
+        assert_eq!(counter.add(5000), 1);
+        assert_eq!(counter.len(), 2000);
+    }
+
+    #[test]
+    fn test_par_extend_panic() {
+        use std::hash::{Hash, Hasher};
+        use std::panic::{self, AssertUnwindSafe};
+
+        #[derive(PartialEq, Eq)]
+        struct Value(u32);
+
+        impl Hash for Value {
+            fn hash<H: Hasher>(&self, state: &mut H) {
+                if self.0 == 500 {
+                    panic!("hash");
+                }
+                self.0.hash(state);
+            }
+        }
+
+        let mut counter: HashCounter<Value> = (0..10).map(Value).collect();
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            counter.par_extend((0..1000).into_par_iter().map(Value));
+        }));
+        assert!(result.is_err());
+        assert_eq!(counter.len(), 10);
+        assert_eq!(counter.get(&Value(3)), 1);
+    }
+
+    #[test]
+    fn test_par_most_common() {
+        let counter: HashCounter<u32> = (0..500u32)
+            .into_par_iter()
+            .flat_map(|i| (0..i).into_par_iter().map(move |_| i))
+            .collect();
+
+        assert_eq!(counter.par_most_common(10), counter.most_common(10));
+        assert_eq!(counter.par_most_common(3), [(&499, 499), (&498, 498), (&497, 497)]);
+        assert!(counter.par_most_common(0).is_empty());
+        assert_eq!(counter.par_most_common(1000).len(), 499);
+    }
+}
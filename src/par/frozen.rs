@@ -0,0 +1,84 @@
+/// Rayon extensions for `FrozenHashMap`
+use rayon::iter::plumbing::*;
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+
+use crate::hash_frozen::{FrozenHashMap, FrozenIter};
+
+/// Parallel iterator over the entries of a `FrozenHashMap`, splitting its
+/// buckets in halves like the iterators of `HashMap`.
+pub struct FrozenParIter<'a, K: 'a, V: 'a> {
+    iter: FrozenIter<'a, K, V>,
+}
+
+impl<'a, K: Sync, V: Sync, S> FrozenHashMap<'a, K, V, S> {
+    /// Returns a parallel iterator over the entries of the map.
+    pub fn par_iter(&self) -> FrozenParIter<'a, K, V> {
+        FrozenParIter { iter: self.iter() }
+    }
+}
+
+impl<'a, K: Sync, V: Sync> ParallelIterator for FrozenParIter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn drive_unindexed<C>(self, consumer: C) -> C::Result
+    where
+        C: UnindexedConsumer<Self::Item>,
+    {
+        bridge_unindexed(FrozenProducer { iter: self.iter }, consumer)
+    }
+}
+
+impl<'a, K: Sync, V: Sync, S> IntoParallelIterator for &FrozenHashMap<'a, K, V, S> {
+    type Item = (&'a K, &'a V);
+    type Iter = FrozenParIter<'a, K, V>;
+
+    fn into_par_iter(self) -> Self::Iter {
+        self.par_iter()
+    }
+}
+
+struct FrozenProducer<'a, K: 'a, V: 'a> {
+    iter: FrozenIter<'a, K, V>,
+}
+
+impl<'a, K: Sync, V: Sync> UnindexedProducer for FrozenProducer<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn split(self) -> (Self, Option<Self>) {
+        let (left, right) = self.iter.split();
+        (
+            FrozenProducer { iter: left },
+            right.map(|iter| FrozenProducer { iter }),
+        )
+    }
+
+    fn fold_with<F>(self, folder: F) -> F
+    where
+        F: Folder<Self::Item>,
+    {
+        folder.consume_iter(self.iter)
+    }
+}
+
+#[cfg(test)]
+mod test_par_frozen {
+    use crate::hash_map::DefaultHasher;
+    use crate::std_hash::frozen::Aligned;
+    use crate::{FrozenHashMap, HashMap};
+    use rayon::prelude::*;
+    use std::hash::BuildHasherDefault;
+
+    type Fixed = BuildHasherDefault<DefaultHasher>;
+
+    #[test]
+    fn test_par_iter() {
+        let map: HashMap<u32, u32, Fixed> = (0..50_000).map(|i| (i, i * 2)).collect();
+        let bytes = Aligned::new(&map.to_raw_bytes(), 0u64);
+        let frozen = unsafe { FrozenHashMap::<u32, u32, _>::from_raw_bytes(&bytes, Fixed::default()) }.unwrap();
+
+        assert_eq!(frozen.par_iter().count(), 50_000);
+        let sum: u64 = (&frozen).into_par_iter().map(|(_, &v)| u64::from(v)).sum();
+        assert_eq!(sum, 50_000 * 49_999);
+        assert!(frozen.par_iter().all(|(k, v)| map.get(k) == Some(v)));
+    }
+}
@@ -0,0 +1,312 @@
+/// Rayon extensions for `IndexedHashMap`
+use rayon::iter::plumbing::{Consumer, ProducerCallback, UnindexedConsumer};
+use rayon::iter::{FromParallelIterator, IndexedParallelIterator, IntoParallelIterator};
+use rayon::iter::{IntoParallelRefIterator, ParallelExtend, ParallelIterator};
+use rayon::slice::ParallelSliceMut;
+use std::cmp::Ordering;
+use std::hash::{BuildHasher, Hash};
+
+use crate::std_hash::table::make_hash;
+use crate::IndexedHashMap;
+
+/// Indexed parallel iterator over the entries of an `IndexedHashMap`, in
+/// order.
+pub struct ParIter<'a, K: Sync + 'a, V: Sync + 'a> {
+    entries: &'a [(K, V)],
+}
+
+/// Indexed parallel iterator over the entries of an `IndexedHashMap`, in
+/// order, with mutable references to the values.
+pub struct ParIterMut<'a, K: Send + Sync + 'a, V: Send + 'a> {
+    entries: &'a mut [(K, V)],
+}
+
+fn entry_refs<K, V>(entry: &(K, V)) -> (&K, &V) {
+    (&entry.0, &entry.1)
+}
+
+fn entry_refs_mut<K, V>(entry: &mut (K, V)) -> (&K, &mut V) {
+    (&entry.0, &mut entry.1)
+}
+
+impl<K: Sync, V: Sync, S> IndexedHashMap<K, V, S> {
+    pub fn par_iter(&self) -> ParIter<'_, K, V> {
+        ParIter {
+            entries: &self.entries,
+        }
+    }
+}
+
+impl<K: Send + Sync, V: Send, S> IndexedHashMap<K, V, S> {
+    pub fn par_iter_mut(&mut self) -> ParIterMut<'_, K, V> {
+        ParIterMut {
+            entries: &mut self.entries,
+        }
+    }
+}
+
+impl<K, V, S> IndexedHashMap<K, V, S>
+where
+    K: Eq + Hash + Send + Sync,
+    V: Send + Sync,
+    S: BuildHasher + Sync,
+{
+    /// Sorts the entries in parallel with a comparison function of the keys
+    /// and values.  The sort is stable.
+    pub fn par_sort_by<F>(&mut self, compare: F)
+    where
+        F: Fn(&K, &V, &K, &V) -> Ordering + Sync,
+    {
+        // Like `sort_by`, sort the positions, so a panicking comparison
+        // leaves the map as it was.
+        let hash_builder = self.hasher();
+        let hashes = self
+            .entries
+            .par_iter()
+            .map(|entry| make_hash(hash_builder, &entry.0))
+            .collect();
+        let mut order: Vec<usize> = (0..self.entries.len()).collect();
+        {
+            let entries = &self.entries;
+            order.par_sort_by(|&a, &b| {
+                let (a, b) = (&entries[a], &entries[b]);
+                compare(&a.0, &a.1, &b.0, &b.1)
+            });
+        }
+        self.reorder(order, hashes);
+    }
+}
+
+impl<'a, K: Sync, V: Sync> ParallelIterator for ParIter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn drive_unindexed<C>(self, consumer: C) -> C::Result
+    where
+        C: UnindexedConsumer<Self::Item>,
+    {
+        self.drive(consumer)
+    }
+
+    fn opt_len(&self) -> Option<usize> {
+        Some(self.entries.len())
+    }
+}
+
+impl<'a, K: Sync, V: Sync> IndexedParallelIterator for ParIter<'a, K, V> {
+    fn drive<C>(self, consumer: C) -> C::Result
+    where
+        C: Consumer<Self::Item>,
+    {
+        self.entries.into_par_iter().map(entry_refs).drive(consumer)
+    }
+
+    fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    fn with_producer<CB>(self, callback: CB) -> CB::Output
+    where
+        CB: ProducerCallback<Self::Item>,
+    {
+        self.entries
+            .into_par_iter()
+            .map(entry_refs)
+            .with_producer(callback)
+    }
+}
+
+impl<'a, K: Send + Sync, V: Send> ParallelIterator for ParIterMut<'a, K, V> {
+    type Item = (&'a K, &'a mut V);
+
+    fn drive_unindexed<C>(self, consumer: C) -> C::Result
+    where
+        C: UnindexedConsumer<Self::Item>,
+    {
+        self.drive(consumer)
+    }
+
+    fn opt_len(&self) -> Option<usize> {
+        Some(self.entries.len())
+    }
+}
+
+impl<'a, K: Send + Sync, V: Send> IndexedParallelIterator for ParIterMut<'a, K, V> {
+    fn drive<C>(self, consumer: C) -> C::Result
+    where
+        C: Consumer<Self::Item>,
+    {
+        self.entries
+            .into_par_iter()
+            .map(entry_refs_mut)
+            .drive(consumer)
+    }
+
+    fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    fn with_producer<CB>(self, callback: CB) -> CB::Output
+    where
+        CB: ProducerCallback<Self::Item>,
+    {
+        self.entries
+            .into_par_iter()
+            .map(entry_refs_mut)
+            .with_producer(callback)
+    }
+}
+
+impl<K: Send, V: Send, S> IntoParallelIterator for IndexedHashMap<K, V, S> {
+    type Item = (K, V);
+    type Iter = rayon::vec::IntoIter<(K, V)>;
+
+    fn into_par_iter(self) -> Self::Iter {
+        self.entries.into_par_iter()
+    }
+}
+
+impl<'a, K: Sync, V: Sync, S> IntoParallelIterator for &'a IndexedHashMap<K, V, S> {
+    type Item = (&'a K, &'a V);
+    type Iter = ParIter<'a, K, V>;
+
+    fn into_par_iter(self) -> Self::Iter {
+        self.par_iter()
+    }
+}
+
+impl<'a, K: Send + Sync, V: Send, S> IntoParallelIterator for &'a mut IndexedHashMap<K, V, S> {
+    type Item = (&'a K, &'a mut V);
+    type Iter = ParIterMut<'a, K, V>;
+
+    fn into_par_iter(self) -> Self::Iter {
+        self.par_iter_mut()
+    }
+}
+
+/// Collect (key, value) pairs from a parallel iterator into an indexed
+/// hashmap, in the parallel iterator's order.  If multiple pairs correspond
+/// to the same key, then the first one keeps its position, and later ones
+/// overwrite its value, just as with a sequential iterator.
+impl<K, V, S> FromParallelIterator<(K, V)> for IndexedHashMap<K, V, S>
+where
+    K: Eq + Hash + Send,
+    V: Send,
+    S: BuildHasher + Default + Sync,
+{
+    fn from_par_iter<P>(par_iter: P) -> Self
+    where
+        P: IntoParallelIterator<Item = (K, V)>,
+    {
+        let mut map = IndexedHashMap::default();
+        map.par_extend(par_iter);
+        map
+    }
+}
+
+/// Extend an indexed hash map with items from a parallel iterator, in the
+/// parallel iterator's order.
+///
+/// The keys are hashed in parallel, but inserted in order.
+impl<K, V, S> ParallelExtend<(K, V)> for IndexedHashMap<K, V, S>
+where
+    K: Eq + Hash + Send,
+    V: Send,
+    S: BuildHasher + Sync,
+{
+    fn par_extend<I>(&mut self, par_iter: I)
+    where
+        I: IntoParallelIterator<Item = (K, V)>,
+    {
+        let hash_builder = self.hasher();
+        let hashed: Vec<_> = par_iter
+            .into_par_iter()
+            .map(|(key, value)| (make_hash(hash_builder, &key), key, value))
+            .collect();
+
+        let reserve = if self.is_empty() {
+            hashed.len()
+        } else {
+            hashed.len() - hashed.len() / 2
+        };
+        self.reserve(reserve);
+        for (hash, key, value) in hashed {
+            self.insert_hashed(hash, key, value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_par_indexed {
+    use crate::IndexedHashMap;
+    use rayon::prelude::*;
+
+    #[test]
+    fn test_par_iter_order() {
+        let map: IndexedHashMap<u32, u32> = (0..10_000u32)
+            .into_par_iter()
+            .map(|i| (i.wrapping_mul(2_654_435_761), i))
+            .collect();
+        assert_eq!(map.len(), 10_000);
+
+        let values: Vec<u32> = map.par_iter().map(|(_, &v)| v).collect();
+        assert_eq!(values, (0..10_000).collect::<Vec<_>>());
+        let serial: Vec<_> = map.iter().collect();
+        let parallel: Vec<_> = map.par_iter().collect();
+        assert_eq!(serial, parallel);
+        assert_eq!(map.par_iter().len(), 10_000);
+        let reversed: Vec<u32> = map.par_iter().rev().map(|(_, &v)| v).collect();
+        assert_eq!(reversed, (0..10_000).rev().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_par_collect_duplicates() {
+        let map: IndexedHashMap<u32, u32> = (0..1000u32)
+            .into_par_iter()
+            .map(|i| (i % 10, i))
+            .collect();
+        assert_eq!(map.len(), 10);
+        for (i, (&key, &value)) in map.iter().enumerate() {
+            assert_eq!(key as usize, i);
+            assert_eq!(value, 990 + key);
+        }
+    }
+
+    #[test]
+    fn test_par_iter_mut() {
+        let mut map: IndexedHashMap<u32, u32> = (0..1000).map(|i| (i, i)).collect();
+        map.par_iter_mut()
+            .enumerate()
+            .for_each(|(i, (&k, v))| *v = k + i as u32);
+        assert!(map.iter().all(|(&k, &v)| v == 2 * k));
+    }
+
+    #[test]
+    fn test_par_sort_by() {
+        let mut map: IndexedHashMap<u32, u32> = (0..10_000).map(|i| (i, (i * 7919) % 10_000)).collect();
+        map.par_sort_by(|_, v1, _, v2| v1.cmp(v2));
+        for (i, (key, &value)) in map.iter().enumerate() {
+            assert_eq!(value as usize, i);
+            assert_eq!(map.get_index_of(key), Some(i));
+        }
+    }
+
+    #[test]
+    fn test_par_sort_by_panic() {
+        use std::panic::{self, AssertUnwindSafe};
+
+        let mut map: IndexedHashMap<u32, u32> = (0..10_000).map(|i| (i, i)).collect();
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            map.par_sort_by(|&k1, _, &k2, _| {
+                if k1 == 5000 || k2 == 5000 {
+                    panic!("par_sort_by");
+                }
+                k2.cmp(&k1)
+            })
+        }));
+        assert!(result.is_err());
+        for (i, (&key, _)) in map.iter().enumerate() {
+            assert_eq!(key as usize, i);
+            assert_eq!(map.get_index_of(&key), Some(i));
+        }
+    }
+}
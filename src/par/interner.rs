@@ -0,0 +1,74 @@
+/// Rayon extensions for `Interner`
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+use std::borrow::Borrow;
+use std::hash::BuildHasher;
+
+use crate::hash_set::{Interner, Symbol};
+
+impl<S: BuildHasher + Sync> Interner<S> {
+    /// Interns all of the strings, returning their symbols in the same
+    /// order.
+    ///
+    /// The strings are hashed and looked up in parallel, and then the new
+    /// ones are added in order, so the symbols are numbered just as if the
+    /// strings were interned one by one.
+    pub fn par_intern_all<T>(&mut self, strings: &[T]) -> Vec<Symbol>
+    where
+        T: Borrow<str> + Sync,
+    {
+        let found: Vec<_> = {
+            let this = &*self;
+            strings
+                .par_iter()
+                .map(|string| {
+                    let bytes = string.borrow().as_bytes();
+                    let hash = this.make_hash(bytes);
+                    (hash, this.get_hashed(hash, bytes))
+                }).collect()
+        };
+
+        strings
+            .iter()
+            .zip(found)
+            .map(|(string, (hash, symbol))| match symbol {
+                Some(symbol) => symbol,
+                None => self.intern_hashed(hash, string.borrow().as_bytes(), Some(true)),
+            }).collect()
+    }
+}
+
+#[cfg(test)]
+mod test_par_interner {
+    use crate::hash_set::Interner;
+
+    #[test]
+    fn test_par_intern_all() {
+        let mut interner = Interner::new();
+        let zero = interner.intern("0");
+        let three = interner.intern("3");
+
+        let words: Vec<String> = (0..20_000).map(|i| (i % 5000).to_string()).collect();
+        let symbols = interner.par_intern_all(&words);
+        assert_eq!(interner.len(), 5000);
+        assert_eq!(symbols[0], zero);
+        assert_eq!(symbols[3], three);
+        assert_eq!(symbols[1].as_u32(), 2);
+        assert_eq!(symbols[4].as_u32(), 4);
+
+        for (word, &symbol) in words.iter().zip(&symbols) {
+            assert_eq!(interner.resolve(symbol), Some(&word[..]));
+        }
+
+        // The same numbering as interning one by one.
+        let mut serial = Interner::new();
+        serial.intern("0");
+        serial.intern("3");
+        let expected: Vec<_> = words.iter().map(|w| serial.intern(w)).collect();
+        assert_eq!(symbols, expected);
+
+        let strs = ["a", "b", "0"];
+        let more = interner.par_intern_all(&strs);
+        assert_eq!(more[2], zero);
+        assert_eq!(more[0].as_u32(), 5000);
+    }
+}
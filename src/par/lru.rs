@@ -0,0 +1,97 @@
+/// Rayon extensions for `LruCache`
+use rayon::iter::plumbing::UnindexedConsumer;
+use rayon::iter::{IntoParallelIterator, IntoParallelRefMutIterator, ParallelIterator};
+
+use crate::hash_map::LruCache;
+use crate::std_hash::lru::Node;
+
+/// Parallel iterator over the entries of an `LruCache`, in no particular
+/// order.
+pub struct LruParIter<'a, K: Sync + 'a, V: Sync + 'a> {
+    nodes: &'a [Node<K, V>],
+}
+
+impl<K: Sync, V: Sync, S> LruCache<K, V, S> {
+    /// Returns a parallel iterator over the entries of the cache.  This
+    /// doesn't mark them as used.
+    pub fn par_iter(&self) -> LruParIter<'_, K, V> {
+        LruParIter { nodes: &self.nodes }
+    }
+}
+
+impl<K: Send + Sync, V: Send, S> LruCache<K, V, S> {
+    /// Retains only the entries specified by the predicate, keeping their
+    /// order of use.  The predicate is called in parallel, which suits bulk
+    /// expiry of many entries at once.
+    pub fn par_retain<F>(&mut self, f: F)
+    where
+        F: Fn(&K, &mut V) -> bool + Sync,
+    {
+        let keep: Vec<bool> = self
+            .nodes
+            .par_iter_mut()
+            .map(|node| f(&node.key, &mut node.value))
+            .collect();
+        self.retain_nodes(&keep);
+    }
+}
+
+impl<'a, K: Sync, V: Sync> ParallelIterator for LruParIter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn drive_unindexed<C>(self, consumer: C) -> C::Result
+    where
+        C: UnindexedConsumer<Self::Item>,
+    {
+        self.nodes
+            .into_par_iter()
+            .map(|node| (&node.key, &node.value))
+            .drive_unindexed(consumer)
+    }
+
+    fn opt_len(&self) -> Option<usize> {
+        Some(self.nodes.len())
+    }
+}
+
+impl<'a, K: Sync, V: Sync, S> IntoParallelIterator for &'a LruCache<K, V, S> {
+    type Item = (&'a K, &'a V);
+    type Iter = LruParIter<'a, K, V>;
+
+    fn into_par_iter(self) -> Self::Iter {
+        self.par_iter()
+    }
+}
+
+#[cfg(test)]
+mod test_par_lru {
+    use crate::hash_map::LruCache;
+    use rayon::prelude::*;
+
+    #[test]
+    fn test_par_iter() {
+        let mut cache = LruCache::new(1000);
+        cache.extend((0..5000u32).map(|i| (i, i * 2)));
+        assert_eq!(cache.par_iter().count(), 1000);
+        let sum: u32 = cache.par_iter().map(|(_, &v)| v).sum();
+        assert_eq!(sum, (4000..5000).map(|i| i * 2).sum());
+    }
+
+    #[test]
+    fn test_par_retain() {
+        let mut cache = LruCache::new(10_000);
+        cache.extend((0..10_000u32).map(|i| (i, i)));
+        cache.get(&1);
+        cache.par_retain(|&k, v| {
+            *v += 1;
+            k % 3 != 0
+        });
+        assert_eq!(cache.len(), 6666);
+        assert!(cache.par_iter().all(|(&k, &v)| k % 3 != 0 && v == k + 1));
+
+        let recent: Vec<u32> = cache.iter().take(3).map(|(&k, _)| k).collect();
+        assert_eq!(recent, [1, 9998, 9997]);
+        assert_eq!(cache.pop_lru(), Some((2, 3)));
+        assert_eq!(cache.get(&4), Some(&5));
+    }
+}
@@ -1,8 +1,20 @@
 /// Rayon extensions to `HashMap`
 use rayon::iter::{FromParallelIterator, IntoParallelIterator, ParallelExtend, ParallelIterator};
-use std::hash::{BuildHasher, Hash};
+use rayon::iter::{IndexedParallelIterator, IntoParallelRefMutIterator};
+use rayon::slice::ParallelSliceMut;
+use rayon::vec;
+use std::any::Any;
+use std::cmp::{self, Ordering};
+use std::hash::{BuildHasher, BuildHasherDefault, Hash, Hasher};
+use std::mem;
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
 
-use super::table;
+use super::table::{self, BucketWriter};
+use crate::hash_map::{DefaultHasher, Entry};
+use crate::std_hash::map::entry_hash;
+use crate::std_hash::table::{make_hash, RawTable, SafeHash};
+use crate::std_hash::top_k::TopK;
 use crate::HashMap;
 
 pub use self::table::{ParIntoIter, ParIter, ParIterMut};
@@ -26,6 +38,22 @@ impl<K, V: Send, S> HashMap<K, V, S> {
     }
 }
 
+impl<K: Send, V: Send, S> HashMap<K, V, S> {
+    /// Consumes the map and transforms its values in parallel.
+    ///
+    /// Since the table layout only depends on the keys' hashes and the
+    /// capacity, every key stays in its bucket with its stored hash, and
+    /// nothing is rehashed.
+    pub fn par_map_values<W, F>(self, f: F) -> HashMap<K, W, S>
+    where
+        W: Send,
+        F: Fn(&K, V) -> W + Sync,
+    {
+        let (hash_builder, table) = self.into_raw_parts();
+        HashMap::from_raw_parts(hash_builder, table.par_map_values(f))
+    }
+}
+
 impl<K, V, S> HashMap<K, V, S>
 where
     K: Eq + Hash + Sync,
@@ -39,6 +67,148 @@ where
     }
 }
 
+impl<K, V, S> HashMap<K, V, S>
+where
+    K: Hash + Sync,
+    V: Hash + Sync,
+{
+    /// Computes a digest of the map's entries that is independent of their
+    /// order in the table, so equal maps have equal digests regardless of
+    /// capacity or the map's own `BuildHasher`.
+    ///
+    /// Each `(K, V)` is hashed separately with a fresh `H::default()`, and
+    /// those hashes are combined with a commutative wrapping sum, which is
+    /// then hashed along with the length.
+    pub fn par_content_hash<H: Hasher + Default>(&self) -> u64 {
+        let sum = self
+            .into_par_iter()
+            .map(|entry| {
+                let mut hasher = H::default();
+                entry.hash(&mut hasher);
+                hasher.finish()
+            }).reduce(|| 0, u64::wrapping_add);
+
+        let mut hasher = H::default();
+        hasher.write_usize(self.len());
+        hasher.write_u64(sum);
+        hasher.finish()
+    }
+}
+
+impl<K, V, S> HashMap<K, V, S>
+where
+    K: Hash + Sync,
+    V: Hash + Sync,
+{
+    /// Feeds the map into `state` exactly like its `Hash` implementation,
+    /// but hashing the entries in parallel.
+    pub fn par_hash<H: Hasher>(&self, state: &mut H) {
+        let sum = self
+            .into_par_iter()
+            .map(entry_hash)
+            .reduce(|| 0, u64::wrapping_add);
+        state.write_usize(self.len());
+        state.write_u64(sum);
+    }
+}
+
+impl<K: Ord + Sync, V: Sync, S> HashMap<K, V, S> {
+    /// Returns a parallel iterator over the keys in ascending order.
+    ///
+    /// The references are collected and sorted in parallel first, so the
+    /// result is indexed and can be zipped, enumerated or collected in
+    /// order.
+    pub fn par_sorted_keys(&self) -> vec::IntoIter<&K> {
+        let mut keys: Vec<&K> = self.par_keys().collect();
+        keys.par_sort_unstable();
+        keys.into_par_iter()
+    }
+}
+
+impl<K: Sync, V: Sync, S> HashMap<K, V, S> {
+    /// Returns a parallel iterator over the entries, sorted by `cmp`.
+    ///
+    /// The references are collected and sorted in parallel first, so the
+    /// result is indexed.  Entries that compare equal are kept in table
+    /// order, which only depends on the hash builder and the history of the
+    /// map.
+    pub fn par_sorted_iter_by<F>(&self, cmp: F) -> vec::IntoIter<(&K, &V)>
+    where
+        F: Fn(&(&K, &V), &(&K, &V)) -> Ordering + Sync,
+    {
+        let mut entries: Vec<(&K, &V)> = self.into_par_iter().collect();
+        entries.par_sort_by(cmp);
+        entries.into_par_iter()
+    }
+}
+
+impl<K: Send, V: Send, S> HashMap<K, V, S> {
+    /// Consumes the map and returns its entries in a vector sorted by key,
+    /// moving them out and sorting in parallel.
+    pub fn into_sorted_vec(self) -> Vec<(K, V)>
+    where
+        K: Ord,
+    {
+        let mut entries: Vec<(K, V)> = self.into_par_iter().collect();
+        entries.par_sort_unstable_by(|a, b| a.0.cmp(&b.0));
+        entries
+    }
+
+    /// Consumes the map and returns its entries in a vector sorted by
+    /// `cmp`, moving them out and sorting in parallel.  Entries that compare
+    /// equal are kept in table order.
+    pub fn into_sorted_vec_by<F>(self, cmp: F) -> Vec<(K, V)>
+    where
+        F: Fn(&(K, V), &(K, V)) -> Ordering + Sync,
+    {
+        let mut entries: Vec<(K, V)> = self.into_par_iter().collect();
+        entries.par_sort_by(cmp);
+        entries
+    }
+}
+
+impl<K: Sync, V: Sync, S> HashMap<K, V, S> {
+    /// Returns the `k` greatest entries according to `cmp`, from the
+    /// greatest, e.g. the entries with the highest values.  Entries that
+    /// compare equal are chosen arbitrarily.
+    ///
+    /// Each job keeps its own heap of at most `k` entries, and these are
+    /// merged, so only `O(k)` entries are held per job, however large the
+    /// map.
+    pub fn par_top_k_by<F>(&self, k: usize, cmp: F) -> Vec<(&K, &V)>
+    where
+        F: Fn(&(&K, &V), &(&K, &V)) -> Ordering + Sync,
+    {
+        self.into_par_iter()
+            .fold(
+                || TopK::new(k, &cmp),
+                |mut top, entry| {
+                    top.push(entry);
+                    top
+                },
+            ).reduce(|| TopK::new(k, &cmp), TopK::merge)
+            .into_vec()
+    }
+
+    /// Returns an entry with the smallest value, or `None` if the map is
+    /// empty.
+    pub fn par_min_by_value(&self) -> Option<(&K, &V)>
+    where
+        V: Ord,
+    {
+        self.into_par_iter().min_by(|a, b| a.1.cmp(b.1))
+    }
+
+    /// Returns an entry with the greatest value, or `None` if the map is
+    /// empty.
+    pub fn par_max_by_value(&self) -> Option<(&K, &V)>
+    where
+        V: Ord,
+    {
+        self.into_par_iter().max_by(|a, b| a.1.cmp(b.1))
+    }
+}
+
 impl<K: Send, V: Send, S> IntoParallelIterator for HashMap<K, V, S> {
     type Item = (K, V);
     type Iter = ParIntoIter<K, V>;
@@ -116,6 +286,373 @@ where
     }
 }
 
+impl<K, V, S> HashMap<K, V, S>
+where
+    K: Eq + Hash + Send + Sync,
+    V: Send + Sync,
+    S: BuildHasher + Send + Sync,
+{
+    /// Moves all entries of `other` into this map.  For keys present in
+    /// both, the values are combined with `resolve(key, ours, theirs)`.
+    ///
+    /// `other`'s keys are hashed with our hash builder and looked up in
+    /// parallel.  The values of keys we already have are then resolved in
+    /// place, in parallel over ranges of our buckets, and only the new
+    /// entries are inserted one by one, without hashing them again.
+    ///
+    /// If `resolve` panics, the entry it was resolving is removed from this
+    /// map, and some of `other`'s entries may not have been merged, but
+    /// the rest of this map is left as it was.
+    pub fn par_merge<R>(&mut self, other: Self, resolve: R)
+    where
+        R: Fn(&K, V, V) -> V + Sync,
+    {
+        self.par_merge_maps(vec![other], resolve);
+    }
+
+    fn par_merge_maps<R>(&mut self, others: Vec<Self>, resolve: R)
+    where
+        R: Fn(&K, V, V) -> V + Sync,
+    {
+        let num_regions = (rayon::current_num_threads() * 4).next_power_of_two();
+        let region_len = cmp::max(1, self.table.capacity() / num_regions);
+
+        // Split each map's entries into the values of keys we have, by the
+        // region of our buckets they're in, and the new entries.
+        let parts: Vec<MergeParts<K, V>> = {
+            let map = &*self;
+            let new_parts = || MergeParts::new(num_regions);
+            others
+                .into_par_iter()
+                .map(|other| {
+                    other
+                        .into_par_iter()
+                        .fold(new_parts, |mut parts, (key, value)| {
+                            let hash = make_hash(map.hasher(), &key);
+                            match map.bucket_index_hashed(hash, &key) {
+                                Some(i) => parts.updates[i / region_len].push((i, value)),
+                                None => parts.inserts.push((hash, key, value)),
+                            }
+                            parts
+                        }).reduce(new_parts, MergeParts::append)
+                }).collect()
+        };
+
+        let mut regions: Vec<Vec<_>> = (0..num_regions).map(|_| Vec::new()).collect();
+        let mut inserts = Vec::new();
+        for part in parts {
+            for (region, updates) in regions.iter_mut().zip(part.updates) {
+                region.push(updates);
+            }
+            inserts.push(part.inserts);
+        }
+
+        // Each region's values are resolved in the maps' order by a single
+        // job.  A job that panics leaves the value it was resolving moved
+        // out, so that entry is removed before the panic carries on.
+        let panics: Vec<(Option<usize>, Box<dyn Any + Send>)> = {
+            let writer = BucketWriter::new(&mut self.table);
+            let resolve = &resolve;
+            regions
+                .into_par_iter()
+                .filter_map(|updates| {
+                    let mut current = None;
+                    panic::catch_unwind(AssertUnwindSafe(|| {
+                        for (index, theirs) in updates.into_iter().flatten() {
+                            current = Some(index);
+                            unsafe {
+                                let pair = writer.pair(index);
+                                let ours = ptr::read(&(*pair).1);
+                                ptr::write(&mut (*pair).1, resolve(&(*pair).0, ours, theirs));
+                            }
+                        }
+                    })).err()
+                    .map(|payload| (current, payload))
+                }).collect()
+        };
+        if !panics.is_empty() {
+            let indices: Vec<usize> = panics.iter().filter_map(|&(index, _)| index).collect();
+            unsafe { self.remove_moved_out(&indices) };
+            let (_, payload) = panics.into_iter().next().unwrap();
+            panic::resume_unwind(payload);
+        }
+
+        // Keys new to this map may still be in more than one of the others.
+        self.reserve(inserts.iter().map(Vec::len).sum());
+        for (hash, key, value) in inserts.into_iter().flatten() {
+            match self.entry_hashed(hash, key) {
+                Entry::Occupied(entry) => {
+                    let (key, ours) = entry.remove_entry();
+                    let value = resolve(&key, ours, value);
+                    self.get_or_insert_hashed_with(hash, key, || value);
+                }
+                Entry::Vacant(entry) => {
+                    entry.insert(value);
+                }
+            }
+        }
+    }
+}
+
+/// The entries of a map to merge into another, split into the values of
+/// keys it has, with their bucket indices, and the new entries with their
+/// hashes.
+struct MergeParts<K, V> {
+    updates: Vec<Vec<(usize, V)>>,
+    inserts: Vec<(SafeHash, K, V)>,
+}
+
+impl<K, V> MergeParts<K, V> {
+    fn new(num_regions: usize) -> Self {
+        MergeParts {
+            updates: (0..num_regions).map(|_| Vec::new()).collect(),
+            inserts: Vec::new(),
+        }
+    }
+
+    fn append(mut self, other: Self) -> Self {
+        for (updates, other) in self.updates.iter_mut().zip(other.updates) {
+            updates.extend(other);
+        }
+        self.inserts.extend(other.inserts);
+        self
+    }
+}
+
+/// Merges all of the maps into one, using the first map's hash builder.
+/// For keys present in several maps, the values are combined in order with
+/// `resolve(key, earlier, later)`.  See `HashMap::par_merge`.
+pub fn par_merge_all<K, V, S, R>(maps: Vec<HashMap<K, V, S>>, resolve: R) -> HashMap<K, V, S>
+where
+    K: Eq + Hash + Send + Sync,
+    V: Send + Sync,
+    S: BuildHasher + Default + Send + Sync,
+    R: Fn(&K, V, V) -> V + Sync,
+{
+    let mut maps = maps.into_iter();
+    match maps.next() {
+        Some(mut map) => {
+            map.par_merge_maps(maps.collect(), resolve);
+            map
+        }
+        None => HashMap::default(),
+    }
+}
+
+/// Groups the items of a parallel iterator by key, keeping the items of
+/// each group in the iterator's order.
+pub fn par_group_by<I, K, S, F>(iter: I, key_fn: F) -> HashMap<K, Vec<I::Item>, S>
+where
+    I: IntoParallelIterator,
+    I::Item: Send,
+    K: Eq + Hash + Send,
+    S: BuildHasher + Default + Send + Sync,
+    F: Fn(&I::Item) -> K + Sync,
+{
+    par_fold_by_key(
+        iter,
+        key_fn,
+        Vec::new,
+        |group, item| group.push(item),
+        |group, other| group.extend(other),
+    )
+}
+
+/// Folds the items of a parallel iterator into one accumulator per key.
+///
+/// Each key's accumulator starts from `init()`, and items are added to it
+/// with `fold`.  Accumulators built separately for the same key are merged
+/// with `combine`, the later one into the earlier one.
+///
+/// Keys are hashed once and split into shards by their hash bits, and the
+/// shards are built and merged in parallel.  The final table is then filled
+/// straight from the shards' entries, without hashing or comparing any key
+/// again.
+pub fn par_fold_by_key<I, K, A, S, KF, ID, F, C>(
+    iter: I,
+    key_fn: KF,
+    init: ID,
+    fold: F,
+    combine: C,
+) -> HashMap<K, A, S>
+where
+    I: IntoParallelIterator,
+    I::Item: Send,
+    K: Eq + Hash + Send,
+    A: Send,
+    S: BuildHasher + Default + Send + Sync,
+    KF: Fn(&I::Item) -> K + Sync,
+    ID: Fn() -> A + Sync,
+    F: Fn(&mut A, I::Item) + Sync,
+    C: Fn(&mut A, A) + Sync,
+{
+    let hash_builder = S::default();
+    let items = iter.into_par_iter().map(|item| {
+        let key = key_fn(&item);
+        (make_hash(&hash_builder, &key), key, item)
+    });
+    let table = fold_sharded(
+        items,
+        init,
+        |_, acc, item| fold(acc, item),
+        |_, acc, other| combine(acc, other),
+    );
+    HashMap::from_raw_parts(hash_builder, table)
+}
+
+/// The shard maps only ever see pre-computed hashes, never their own hash
+/// builder, so its type doesn't matter.
+type Shard<K, A> = HashMap<K, A, BuildHasherDefault<DefaultHasher>>;
+
+/// Folds pre-hashed items into one accumulator per key, and returns a table
+/// of the results with the same hashes.
+///
+/// The items are split into shards by the highest hash bits, below the one
+/// that `SafeHash` always sets, as the lowest bits select the buckets.  Each
+/// worker folds into its own set of shard maps, and these are merged shard
+/// by shard in parallel.  Finally, the table is filled from all the shards.
+pub(crate) fn fold_sharded<I, K, X, A, ID, F, C>(
+    items: I,
+    init: ID,
+    fold: F,
+    combine: C,
+) -> RawTable<K, A>
+where
+    I: ParallelIterator<Item = (SafeHash, K, X)>,
+    K: Eq + Hash + Send,
+    A: Send,
+    ID: Fn() -> A + Sync,
+    F: Fn(&K, &mut A, X) + Sync,
+    C: Fn(&K, &mut A, A) + Sync,
+{
+    let shards = build_shards(
+        items,
+        |shard, hash, key, item| {
+            let (key, acc) = shard.get_or_insert_hashed_with(hash, key, &init);
+            fold(key, acc, item);
+        },
+        &combine,
+    );
+    fill_from_shards(shards, 0)
+}
+
+/// Collects pre-hashed entries into a table with room for at least
+/// `capacity` entries, keeping their hashes.  The values of entries with the
+/// same key are merged with `combine`, the later one into the earlier one.
+///
+/// This works like `fold_sharded`, but the first value for each key is
+/// stored as it is, so the table is built just once.
+pub(crate) fn collect_sharded<I, K, V, C>(items: I, capacity: usize, combine: C) -> RawTable<K, V>
+where
+    I: ParallelIterator<Item = (SafeHash, K, V)>,
+    K: Eq + Hash + Send,
+    V: Send,
+    C: Fn(&K, &mut V, V) + Sync,
+{
+    let shards = build_shards(
+        items,
+        |shard, hash, key, value| insert_or_combine(shard, hash, key, value, &combine),
+        &combine,
+    );
+    fill_from_shards(shards, capacity)
+}
+
+fn build_shards<I, K, X, A, F, C>(items: I, fold: F, combine: &C) -> Vec<Shard<K, A>>
+where
+    I: ParallelIterator<Item = (SafeHash, K, X)>,
+    K: Eq + Hash + Send,
+    A: Send,
+    F: Fn(&mut Shard<K, A>, SafeHash, K, X) + Sync,
+    C: Fn(&K, &mut A, A) + Sync,
+{
+    let num_shards = (rayon::current_num_threads() * 4).next_power_of_two();
+    let shard_bits = num_shards.trailing_zeros() as usize;
+    let hash_bits = mem::size_of::<usize>() * 8;
+    let shard_of = |hash: SafeHash| (hash.inspect() << 1) >> (hash_bits - shard_bits);
+    let new_shards = || -> Vec<Shard<K, A>> { (0..num_shards).map(|_| HashMap::default()).collect() };
+
+    items
+        .fold(new_shards, |mut shards, (hash, key, item)| {
+            fold(&mut shards[shard_of(hash)], hash, key, item);
+            shards
+        }).reduce(new_shards, |mut shards, others| {
+            shards
+                .par_iter_mut()
+                .zip(others)
+                .for_each(|(shard, other)| merge_shard(shard, other, combine));
+            shards
+        })
+}
+
+/// Fills a table with room for at least `capacity` entries from all the
+/// shards.
+fn fill_from_shards<K, A>(shards: Vec<Shard<K, A>>, capacity: usize) -> RawTable<K, A>
+where
+    K: Eq + Hash + Send,
+    A: Send,
+{
+    let len = shards.iter().map(HashMap::len).sum();
+    let capacity = cmp::max(len, capacity);
+    let (_, mut table) = Shard::with_capacity_and_hasher(capacity, Default::default()).into_raw_parts();
+    let entries = shards
+        .into_par_iter()
+        .flat_map(|shard| shard.into_raw_parts().1.into_par_buckets())
+        .map(|(_, hash, key, acc)| (hash, key, acc));
+    table.par_fill(entries);
+    table
+}
+
+/// Merges a table from `fold_sharded` into a map whose hash builder made
+/// its hashes, passing `merge` the map's entry and the accumulator of each
+/// key in turn.  If the map is empty, it just takes the table instead.
+pub(crate) fn merge_sharded<K, A, S, F>(map: &mut HashMap<K, A, S>, table: RawTable<K, A>, mut merge: F)
+where
+    K: Eq + Hash,
+    S: BuildHasher,
+    F: FnMut(Entry<'_, K, A>, A),
+{
+    if map.is_empty() {
+        let capacity = map.capacity();
+        map.table = table;
+        map.reserve(capacity.saturating_sub(map.len()));
+        return;
+    }
+    map.reserve(table.size());
+    for (hash, key, acc) in table.into_iter() {
+        merge(map.entry_hashed(hash, key), acc);
+    }
+}
+
+fn merge_shard<K, A, C>(shard: &mut Shard<K, A>, other: Shard<K, A>, combine: &C)
+where
+    K: Eq + Hash,
+    C: Fn(&K, &mut A, A),
+{
+    if shard.is_empty() {
+        *shard = other;
+        return;
+    }
+    for (hash, key, acc) in other.into_raw_parts().1.into_iter() {
+        insert_or_combine(shard, hash, key, acc, combine);
+    }
+}
+
+fn insert_or_combine<K, A, C>(shard: &mut Shard<K, A>, hash: SafeHash, key: K, acc: A, combine: &C)
+where
+    K: Eq + Hash,
+    C: Fn(&K, &mut A, A),
+{
+    match shard.entry_hashed(hash, key) {
+        Entry::Occupied(entry) => {
+            let (key, value) = entry.into_refs_mut();
+            combine(key, value, acc);
+        }
+        Entry::Vacant(entry) => {
+            entry.insert(acc);
+        }
+    }
+}
+
 // This is equal to the normal `HashMap` -- no custom advantage.
 fn extend<K, V, S, I>(map: &mut HashMap<K, V, S>, par_iter: I)
 where
@@ -140,8 +677,10 @@ where
 #[cfg(test)]
 mod test_par_map {
     use super::HashMap;
+    use crate::hash_map::DefaultHasher;
     use rayon::prelude::*;
     use std::hash::{Hash, Hasher};
+    use std::panic::{self, AssertUnwindSafe};
     use std::sync::atomic::{AtomicUsize, Ordering};
 
     struct Dropable<'a> {
@@ -316,6 +855,263 @@ mod test_par_map {
         assert!(m1.par_eq(&m2));
     }
 
+    #[test]
+    fn test_content_hash() {
+        let mut m1 = HashMap::new();
+        for i in 0..1000 {
+            m1.insert(i, i.to_string());
+        }
+
+        // Different capacity, hasher seed, and insertion order.
+        let mut m2 = HashMap::with_capacity(10_000);
+        for i in (0..1000).rev() {
+            m2.insert(i, i.to_string());
+        }
+
+        let h1 = m1.par_content_hash::<DefaultHasher>();
+        assert_eq!(h1, m2.par_content_hash::<DefaultHasher>());
+
+        m2.insert(0, "zero".to_string());
+        assert_ne!(h1, m2.par_content_hash::<DefaultHasher>());
+
+        m2.remove(&0);
+        assert_ne!(h1, m2.par_content_hash::<DefaultHasher>());
+
+        let empty: HashMap<i32, String> = HashMap::new();
+        assert_ne!(h1, empty.par_content_hash::<DefaultHasher>());
+    }
+
+    #[test]
+    fn test_par_hash() {
+        let map: HashMap<_, _> = (0..1000).map(|i| (i, i.to_string())).collect();
+
+        let mut serial = DefaultHasher::new();
+        map.hash(&mut serial);
+        let mut parallel = DefaultHasher::new();
+        map.par_hash(&mut parallel);
+        assert_eq!(serial.finish(), parallel.finish());
+    }
+
+    #[test]
+    fn test_map_values() {
+        let map: HashMap<_, _> = (0..1000).map(|i| (i, i * 2)).collect();
+        let capacity = map.capacity();
+        let keys: Vec<_> = map.keys().cloned().collect();
+
+        let map = map.par_map_values(|&k, v| format!("{}:{}", k, v));
+        assert_eq!(map.len(), 1000);
+        assert_eq!(map.capacity(), capacity);
+        assert_eq!(map.keys().cloned().collect::<Vec<_>>(), keys);
+        for i in 0..1000 {
+            assert_eq!(map[&i], format!("{}:{}", i, i * 2));
+        }
+
+        let mut map = map.par_map_values(|_, v| v.len());
+        assert_eq!(map.remove(&999), Some(8));
+        map.insert(1000, 0);
+        assert_eq!(map.len(), 1000);
+
+        let empty: HashMap<i32, i32> = HashMap::new();
+        assert!(empty.par_map_values(|_, v| v + 1).is_empty());
+    }
+
+    #[test]
+    fn test_map_values_drops() {
+        let key = AtomicUsize::new(0);
+        let value = AtomicUsize::new(0);
+
+        let mut hm = HashMap::new();
+        for i in 0..100 {
+            hm.insert(Dropable::new(i, &key), Dropable::new(i, &value));
+        }
+
+        let hm = hm.par_map_values(|_, v| v.k);
+        assert_eq!(key.load(Ordering::Relaxed), 100);
+        assert_eq!(value.load(Ordering::Relaxed), 0);
+
+        drop(hm);
+        assert_eq!(key.load(Ordering::Relaxed), 0);
+    }
+
+    #[test]
+    fn test_group_by() {
+        let groups: HashMap<_, _> = super::par_group_by(0..10_000, |&i| i % 100);
+        assert_eq!(groups.len(), 100);
+        for (&key, group) in &groups {
+            let expected: Vec<_> = (0..100).map(|i| i * 100 + key).collect();
+            assert_eq!(*group, expected);
+        }
+
+        // The filled table must behave like any other.
+        let mut groups = groups;
+        for key in 0..100 {
+            assert!(groups.contains_key(&key));
+        }
+        assert!(!groups.contains_key(&100));
+        assert_eq!(groups.remove(&7).map(|g| g.len()), Some(100));
+        groups.insert(1000, vec![]);
+        assert_eq!(groups.len(), 100);
+
+        let empty: HashMap<i32, Vec<i32>> = super::par_group_by(Vec::new(), |&i| i);
+        assert!(empty.is_empty());
+    }
+
+    #[test]
+    fn test_fold_by_key() {
+        let words = ["apple", "banana", "cherry", "avocado", "blueberry", "apricot"];
+        let counts: HashMap<_, _> = super::par_fold_by_key(
+            (0..6000).into_par_iter().map(|i| words[i % words.len()]),
+            |word| word.chars().next().unwrap(),
+            || 0,
+            |count, _| *count += 1,
+            |count, other| *count += other,
+        );
+        assert_eq!(counts.len(), 3);
+        assert_eq!(counts[&'a'], 3000);
+        assert_eq!(counts[&'b'], 2000);
+        assert_eq!(counts[&'c'], 1000);
+
+        // Many distinct keys, so the spill between regions gets exercised.
+        let squares: HashMap<_, _> = super::par_fold_by_key(
+            0..100_000u64,
+            |&i| i,
+            || 0,
+            |acc, i| *acc += i * i,
+            |acc, other| *acc += other,
+        );
+        assert_eq!(squares.len(), 100_000);
+        for i in 0..100_000 {
+            assert_eq!(squares[&i], i * i);
+        }
+    }
+
+    #[test]
+    fn test_merge() {
+        let mut a: HashMap<u32, u32> = (0..20_000).map(|i| (i, i)).collect();
+        let b: HashMap<u32, u32> = (10_000..30_000).map(|i| (i, 1)).collect();
+        a.par_merge(b, |_, ours, theirs| ours + theirs);
+
+        assert_eq!(a.len(), 30_000);
+        for i in 0..30_000 {
+            let expected = if i < 10_000 {
+                i
+            } else if i < 20_000 {
+                i + 1
+            } else {
+                1
+            };
+            assert_eq!(a[&i], expected);
+        }
+
+        // The merged table is still a normal map.
+        assert_eq!(a.remove(&5), Some(5));
+        assert_eq!(a.insert(40_000, 7), None);
+        assert_eq!(a.get(&40_000), Some(&7));
+        assert_eq!(a.len(), 30_000);
+    }
+
+    #[test]
+    fn test_merge_panic() {
+        let mut a: HashMap<u32, Vec<u32>> = (0..10_000).map(|i| (i, vec![i])).collect();
+        let b: HashMap<u32, Vec<u32>> = (5_000..15_000).map(|i| (i, vec![0])).collect();
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            a.par_merge(b, |&k, mut ours, theirs| {
+                if k % 1000 == 0 {
+                    panic!("resolve");
+                }
+                ours.extend(theirs);
+                ours
+            })
+        }));
+        assert!(result.is_err());
+
+        // Only the entries being resolved are lost.
+        for i in 0..10_000 {
+            match a.get(&i) {
+                None => assert!(i >= 5_000 && i % 1000 == 0),
+                Some(v) => assert!(*v == vec![i] || *v == vec![i, 0]),
+            }
+        }
+        assert!(a.keys().all(|k| a.contains_key(k)));
+    }
+
+    #[test]
+    fn test_merge_all() {
+        let maps = || -> Vec<HashMap<u32, Vec<usize>>> {
+            (0..4)
+                .map(|n| (n..1000).map(|i| (i, vec![n as usize])).collect())
+                .collect()
+        };
+        let concat = |_: &u32, mut ours: Vec<usize>, theirs| {
+            ours.extend(theirs);
+            ours
+        };
+        let merged = super::par_merge_all(maps(), concat);
+
+        assert_eq!(merged.len(), 1000);
+        for i in 0..1000u32 {
+            let expected: Vec<usize> = (0..4).filter(|&n| n <= i as usize).collect();
+            assert_eq!(merged[&i], expected);
+        }
+
+        // All keys are new to the first map.
+        let mut maps = maps();
+        maps.insert(0, HashMap::new());
+        assert_eq!(super::par_merge_all(maps, concat), merged);
+
+        let empty: HashMap<u32, u32> = super::par_merge_all(vec![], |_, a, b| a + b);
+        assert!(empty.is_empty());
+    }
+
+    #[test]
+    fn test_sorted() {
+        let map: HashMap<u32, u32> = (0..10_000).map(|i| (i, i % 7)).collect();
+
+        let keys: Vec<u32> = map.par_sorted_keys().cloned().collect();
+        assert_eq!(keys, (0..10_000).collect::<Vec<_>>());
+        assert!(map.par_sorted_keys().enumerate().all(|(i, &k)| i as u32 == k));
+
+        // Sorting by value, ties broken by key.
+        let entries: Vec<_> = map
+            .par_sorted_iter_by(|a, b| (a.1, a.0).cmp(&(b.1, b.0)))
+            .map(|(&k, &v)| (k, v))
+            .collect();
+        assert_eq!(entries.len(), 10_000);
+        assert_eq!(entries[0], (0, 0));
+        assert_eq!(entries[1], (7, 0));
+        assert!(entries.windows(2).all(|w| (w[0].1, w[0].0) < (w[1].1, w[1].0)));
+
+        let by_value = map.clone().into_sorted_vec_by(|a, b| b.1.cmp(&a.1));
+        assert!(by_value.windows(2).all(|w| w[0].1 >= w[1].1));
+        assert_eq!(by_value[0].1, 6);
+        let sorted = map.into_sorted_vec();
+        assert_eq!(sorted, (0..10_000).map(|i| (i, i % 7)).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_top_k() {
+        let map: HashMap<u32, u32> = (0..100_000).map(|i| (i, i * 7919 % 100_003)).collect();
+        let mut expected: Vec<_> = map.iter().collect();
+        expected.sort_by(|a, b| b.1.cmp(a.1));
+        expected.truncate(10);
+        assert_eq!(map.par_top_k_by(10, |a, b| a.1.cmp(b.1)), expected);
+
+        // The smallest keys, by reversing the order.
+        let smallest: Vec<u32> = map
+            .par_top_k_by(3, |a, b| b.0.cmp(a.0))
+            .into_iter()
+            .map(|(&k, _)| k)
+            .collect();
+        assert_eq!(smallest, [0, 1, 2]);
+        assert!(map.par_top_k_by(0, |a, b| a.cmp(b)).is_empty());
+        assert_eq!(map.par_top_k_by(200_000, |a, b| a.cmp(b)).len(), 100_000);
+
+        assert_eq!(map.par_max_by_value(), Some(expected[0]));
+        let min = map.par_min_by_value().unwrap();
+        assert!(map.values().all(|v| v >= min.1));
+        assert_eq!(HashMap::<u32, u32>::new().par_max_by_value(), None);
+    }
+
     #[test]
     fn test_from_iter() {
         let xs = [(1, 1), (2, 2), (3, 3), (4, 4), (5, 5), (6, 6)];
@@ -1,8 +1,18 @@
 use rayon::prelude::*;
 use std::collections::LinkedList;
 
+pub mod bimap;
+pub mod codec;
+pub mod counter;
+pub mod frozen;
+pub mod indexed;
+pub mod interner;
+pub mod lru;
 pub mod map;
+pub mod multimap;
+pub mod sample;
 pub mod set;
+pub mod ttl;
 mod table;
 
 /// Helper for collecting parallel iterators to an intermediary
@@ -0,0 +1,170 @@
+/// Rayon extensions for `MultiMap`
+use rayon::iter::plumbing::UnindexedConsumer;
+use rayon::iter::{FromParallelIterator, IntoParallelIterator, ParallelExtend, ParallelIterator};
+use rayon::iter::IntoParallelRefIterator;
+use std::hash::{BuildHasher, Hash};
+
+use super::map::{self, fold_sharded, merge_sharded};
+use crate::hash_map::Entry;
+use crate::std_hash::multimap::Group;
+use crate::std_hash::table::make_hash;
+use crate::MultiMap;
+
+/// Parallel iterator over the key-value pairs of a `MultiMap`, flattening
+/// the values of each key.
+pub struct ParIter<'a, K: Sync + 'a, V: Sync + 'a> {
+    inner: map::ParIter<'a, K, Group<V>>,
+}
+
+impl<K: Sync, V: Sync, S> MultiMap<K, V, S> {
+    pub fn par_iter(&self) -> ParIter<'_, K, V> {
+        ParIter {
+            inner: self.map.par_iter(),
+        }
+    }
+
+    /// Visits each key with the group of its values, splitting the work by
+    /// keys rather than values.
+    pub fn par_iter_groups(&self) -> map::ParIter<'_, K, Group<V>> {
+        self.map.par_iter()
+    }
+}
+
+impl<'a, K: Sync, V: Sync> ParallelIterator for ParIter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn drive_unindexed<C>(self, consumer: C) -> C::Result
+    where
+        C: UnindexedConsumer<Self::Item>,
+    {
+        self.inner
+            .flat_map(|(key, group)| group.as_slice().par_iter().map(move |value| (key, value)))
+            .drive_unindexed(consumer)
+    }
+}
+
+impl<'a, K: Sync, V: Sync, S> IntoParallelIterator for &'a MultiMap<K, V, S> {
+    type Item = (&'a K, &'a V);
+    type Iter = ParIter<'a, K, V>;
+
+    fn into_par_iter(self) -> Self::Iter {
+        self.par_iter()
+    }
+}
+
+/// Collect (key, value) pairs from a parallel iterator into a multimap,
+/// grouping the values of each key in the parallel iterator's order.
+impl<K, V, S> FromParallelIterator<(K, V)> for MultiMap<K, V, S>
+where
+    K: Eq + Hash + Send,
+    V: Send,
+    S: BuildHasher + Default + Send + Sync,
+{
+    fn from_par_iter<P>(par_iter: P) -> Self
+    where
+        P: IntoParallelIterator<Item = (K, V)>,
+    {
+        let mut map = MultiMap::default();
+        map.par_extend(par_iter);
+        map
+    }
+}
+
+/// Extend a multimap with items from a parallel iterator, appending the
+/// values of each key in the parallel iterator's order.
+///
+/// The pairs are hashed and grouped in parallel shards, and then the groups
+/// are appended to the existing ones, without hashing anything again.  An
+/// empty multimap just takes the new table.
+impl<K, V, S> ParallelExtend<(K, V)> for MultiMap<K, V, S>
+where
+    K: Eq + Hash + Send,
+    V: Send,
+    S: BuildHasher + Send + Sync,
+{
+    fn par_extend<I>(&mut self, par_iter: I)
+    where
+        I: IntoParallelIterator<Item = (K, V)>,
+    {
+        let hash_builder = self.map.hasher();
+        let pairs = par_iter
+            .into_par_iter()
+            .map(|(key, value)| (make_hash(hash_builder, &key), key, Group::one(value)));
+        let groups = fold_sharded(
+            pairs,
+            Group::new,
+            |_, group, other| group.append(other),
+            |_, group, other| group.append(other),
+        );
+
+        // Count the values as they are merged, so the length stays right
+        // if hashing or comparing a key panics.
+        let len = &mut self.len;
+        if self.map.is_empty() {
+            *len = groups.iter().map(|(_, group)| group.len()).sum();
+        }
+        merge_sharded(&mut self.map, groups, |entry, group| {
+            *len += group.len();
+            match entry {
+                Entry::Occupied(entry) => entry.into_mut().append(group),
+                Entry::Vacant(entry) => {
+                    entry.insert(group);
+                }
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod test_par_multimap {
+    use crate::MultiMap;
+    use rayon::prelude::*;
+
+    #[test]
+    fn test_from_par_iter() {
+        let map: MultiMap<u32, u32> = (0..100_000u32)
+            .into_par_iter()
+            .map(|i| (i % 1000, i))
+            .collect();
+        assert_eq!(map.len(), 100_000);
+        assert_eq!(map.keys_len(), 1000);
+        for key in 0..1000 {
+            let values = map.get_all(&key);
+            assert_eq!(values.len(), 100);
+            // Values are grouped in the iterator's order.
+            assert!(values.iter().enumerate().all(|(i, &v)| v == key + 1000 * i as u32));
+        }
+    }
+
+    #[test]
+    fn test_par_extend() {
+        let mut map: MultiMap<u32, u32> = (0..10).map(|i| (i, i)).collect();
+        map.par_extend((10..40u32).into_par_iter().map(|i| (i % 20, i)));
+        assert_eq!(map.len(), 40);
+        assert_eq!(map.keys_len(), 20);
+        assert_eq!(map.get_all(&3), [3, 23]);
+        assert_eq!(map.get_all(&13), [13, 33]);
+
+        // The merged table is still a normal multimap.
+        map.insert(3, 43);
+        assert_eq!(map.get_all(&3), [3, 23, 43]);
+        assert_eq!(map.remove_all(&13), [13, 33]);
+        assert_eq!(map.len(), 39);
+    }
+
+    #[test]
+    fn test_par_iter() {
+        let map: MultiMap<u32, u32> = (0..10_000u32).map(|i| (i % 7, i)).collect();
+        assert_eq!(map.par_iter().count(), 10_000);
+        assert!(map.par_iter().all(|(&k, &v)| k == v % 7));
+        let sum: u32 = map.par_iter().map(|(_, &v)| v).sum();
+        assert_eq!(sum, (0..10_000).sum());
+
+        let sizes: Vec<_> = map
+            .par_iter_groups()
+            .map(|(_, group)| group.len())
+            .collect();
+        assert_eq!(sizes.len(), 7);
+        assert_eq!(sizes.iter().sum::<usize>(), 10_000);
+    }
+}
@@ -0,0 +1,121 @@
+/// Rayon extensions for sampling a `HashMap`
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use std::cmp;
+
+use crate::std_hash::sample::{below, SplitMix64};
+use crate::std_hash::table::RawTable;
+use crate::HashMap;
+
+/// The number of samples drawn by each job, with its own generator.  The
+/// sample only depends on the seed, not on how the jobs are run.
+const CHUNK_SAMPLES: usize = 1 << 10;
+
+/// The number of buckets counted together for sampling sparse tables.
+const BLOCK_BUCKETS: usize = 1 << 10;
+
+impl<K: Sync, V: Sync, S> HashMap<K, V, S> {
+    /// Returns `n` entries chosen uniformly at random with replacement,
+    /// like `sample`, but drawing them in parallel.  The same seed always
+    /// gives the same sample of the same map.
+    ///
+    /// If the table is mostly empty, its full buckets are first counted in
+    /// blocks in parallel, and then each sample picks a random rank, and
+    /// looks through a single block for it.
+    pub fn par_sample(&self, n: usize, seed: u64) -> Vec<(&K, &V)> {
+        if self.is_empty() {
+            return Vec::new();
+        }
+        let table = &self.table;
+        let chunks = (0..n / CHUNK_SAMPLES + 1).into_par_iter();
+        let chunk_len = |c: usize| cmp::min(n, (c + 1) * CHUNK_SAMPLES) - c * CHUNK_SAMPLES;
+        let samples: Vec<Vec<_>> = if table.is_dense() {
+            chunks
+                .map(|c| {
+                    let mut rng = SplitMix64::stream(seed, c as u64);
+                    (0..chunk_len(c))
+                        .map(|_| table.sample_bucket(&mut || rng.next_u64()))
+                        .collect()
+                }).collect()
+        } else {
+            let blocks = Blocks::count(table);
+            chunks
+                .map(|c| {
+                    let mut rng = SplitMix64::stream(seed, c as u64);
+                    (0..chunk_len(c))
+                        .map(|_| blocks.find(table, below(&mut || rng.next_u64(), table.size())))
+                        .collect()
+                }).collect()
+        };
+        samples.concat()
+    }
+}
+
+/// The number of entries before the end of each block of buckets.
+struct Blocks {
+    len: usize,
+    ends: Vec<usize>,
+}
+
+impl Blocks {
+    fn count<K: Sync, V: Sync>(table: &RawTable<K, V>) -> Blocks {
+        let len = cmp::min(BLOCK_BUCKETS, table.capacity());
+        let mut ends: Vec<usize> = (0..table.capacity() / len)
+            .into_par_iter()
+            .map(|b| table.bucket_range(b * len, (b + 1) * len).count())
+            .collect();
+        let mut total = 0;
+        for end in &mut ends {
+            total += *end;
+            *end = total;
+        }
+        Blocks { len, ends }
+    }
+
+    /// Returns the entry of the given rank.
+    fn find<'a, K, V>(&self, table: &'a RawTable<K, V>, rank: usize) -> (&'a K, &'a V) {
+        // The first block that ends past the rank.
+        let (mut lo, mut hi) = (0, self.ends.len());
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            if self.ends[mid] <= rank {
+                lo = mid + 1;
+            } else {
+                hi = mid;
+            }
+        }
+        let start = if lo == 0 { 0 } else { self.ends[lo - 1] };
+        table
+            .bucket_range(lo * self.len, (lo + 1) * self.len)
+            .nth(rank - start)
+            .expect("rank out of range")
+    }
+}
+
+#[cfg(test)]
+mod test_par_sample {
+    use crate::HashMap;
+
+    #[test]
+    fn test_par_sample() {
+        let empty: HashMap<u32, u32> = HashMap::new();
+        assert!(empty.par_sample(10, 1).is_empty());
+
+        let mut map: HashMap<u32, u32> = (0..10_000).map(|i| (i, i)).collect();
+        let sample = map.par_sample(50_000, 1);
+        assert_eq!(sample.len(), 50_000);
+        assert_eq!(sample, map.par_sample(50_000, 1));
+        assert_ne!(sample, map.par_sample(50_000, 2));
+        assert!(sample.iter().all(|&(k, v)| k == v));
+        assert_eq!(map.par_sample(5, 1), sample[..5]);
+
+        // A sparse table.
+        map.retain(|&k, _| k % 1000 == 0);
+        let sample = map.par_sample(5000, 3);
+        let mut hits = vec![0; 10];
+        for (&k, _) in sample {
+            assert_eq!(k % 1000, 0);
+            hits[k as usize / 1000] += 1;
+        }
+        assert!(hits.iter().all(|&h| h > 300), "{:?}", hits);
+    }
+}
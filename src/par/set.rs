@@ -1,10 +1,15 @@
 use rayon::iter::plumbing::UnindexedConsumer;
 /// Rayon extensions for `HashSet`
 use rayon::iter::{FromParallelIterator, IntoParallelIterator, ParallelExtend, ParallelIterator};
-use std::hash::{BuildHasher, Hash};
+use rayon::iter::IntoParallelRefIterator;
+use rayon::slice::ParallelSliceMut;
+use rayon::vec;
+use std::hash::{BuildHasher, Hash, Hasher};
+use std::ptr;
 
-use super::map;
-use crate::HashSet;
+use super::map::{self, fold_sharded};
+use crate::std_hash::table::make_hash;
+use crate::{HashMap, HashSet};
 
 pub struct ParIntoIter<T: Send> {
     inner: map::ParIntoIter<T, ()>,
@@ -34,6 +39,93 @@ pub struct ParUnion<'a, T: Sync + 'a, S: Sync + 'a> {
     b: &'a HashSet<T, S>,
 }
 
+pub struct ParUnionAll<'a, T: Sync + 'a, S: Sync + 'a> {
+    sets: &'a [&'a HashSet<T, S>],
+}
+
+pub struct ParIntersectionAll<'a, T: Sync + 'a, S: Sync + 'a> {
+    sets: &'a [&'a HashSet<T, S>],
+}
+
+/// Visits the values in any of the given sets, without duplicates.
+///
+/// The values are first gathered into a single set in parallel, so each
+/// one is hashed and probed once, however many sets there are.
+pub fn par_union_all<'a, T, S>(sets: &'a [&'a HashSet<T, S>]) -> ParUnionAll<'a, T, S>
+where
+    T: Eq + Hash + Sync,
+    S: BuildHasher + Sync,
+{
+    ParUnionAll { sets }
+}
+
+/// Visits the values in all of the given sets, iterating the smallest set
+/// and probing the others.
+pub fn par_intersection_all<'a, T, S>(
+    sets: &'a [&'a HashSet<T, S>],
+) -> ParIntersectionAll<'a, T, S>
+where
+    T: Eq + Hash + Sync,
+    S: BuildHasher + Sync,
+{
+    ParIntersectionAll { sets }
+}
+
+/// Consumes the given sets and returns their union, using the largest
+/// set's hash builder.
+///
+/// The largest set's values keep their stored hashes, while the others are
+/// hashed in parallel, and the new table is built in parallel too.
+pub fn par_union_all_into<T, S>(mut sets: Vec<HashSet<T, S>>) -> HashSet<T, S>
+where
+    T: Eq + Hash + Send,
+    S: BuildHasher + Default + Send + Sync,
+{
+    let largest = (0..sets.len()).max_by_key(|&i| sets[i].len());
+    let (hash_builder, table) = match largest {
+        Some(i) => sets.swap_remove(i).map.into_raw_parts(),
+        None => return HashSet::default(),
+    };
+    let table = {
+        let ours = table
+            .into_par_buckets()
+            .map(|(_, hash, value, ())| (hash, value, ()));
+        let theirs = sets
+            .into_par_iter()
+            .flatten()
+            .map(|value| (make_hash(&hash_builder, &value), value, ()));
+        fold_sharded(ours.chain(theirs), || (), |_, _, ()| (), |_, _, ()| ())
+    };
+    HashSet {
+        map: HashMap::from_raw_parts(hash_builder, table),
+    }
+}
+
+/// Consumes the given sets and returns their intersection, moving the
+/// common values out of the smallest set.
+///
+/// The values keep the smallest set's stored hashes and hash builder, and
+/// the new table is built in parallel.
+pub fn par_intersection_all_into<T, S>(mut sets: Vec<HashSet<T, S>>) -> HashSet<T, S>
+where
+    T: Eq + Hash + Send + Sync,
+    S: BuildHasher + Default + Send + Sync,
+{
+    let smallest = (0..sets.len()).min_by_key(|&i| sets[i].len());
+    let (hash_builder, table) = match smallest {
+        Some(i) => sets.swap_remove(i).map.into_raw_parts(),
+        None => return HashSet::default(),
+    };
+    let common = table
+        .into_par_buckets()
+        .filter(|&(_, _, ref value, ())| sets.iter().all(|set| set.contains(value)))
+        .map(|(_, hash, value, ())| (hash, value, ()));
+    let table = fold_sharded(common, || (), |_, _, ()| (), |_, _, ()| ());
+    HashSet {
+        map: HashMap::from_raw_parts(hash_builder, table),
+    }
+}
+
 impl<T, S> HashSet<T, S>
 where
     T: Eq + Hash + Sync,
@@ -59,15 +151,22 @@ where
     }
 
     pub fn par_is_disjoint(&self, other: &Self) -> bool {
-        self.into_par_iter().all(|x| !other.contains(x))
+        // Drive the smaller table, probing the larger one.  `all` stops
+        // every worker as soon as one of them finds a common element.
+        if self.len() <= other.len() {
+            self.into_par_iter().all(|x| !other.contains(x))
+        } else {
+            other.into_par_iter().all(|x| !self.contains(x))
+        }
     }
 
     pub fn par_is_subset(&self, other: &Self) -> bool {
-        self.into_par_iter().all(|x| other.contains(x))
+        // A larger set can never be a subset, so don't bother iterating.
+        self.len() <= other.len() && self.into_par_iter().all(|x| other.contains(x))
     }
 
     pub fn par_is_superset(&self, other: &Self) -> bool {
-        other.is_subset(self)
+        other.par_is_subset(self)
     }
 
     pub fn par_eq(&self, other: &Self) -> bool {
@@ -75,6 +174,82 @@ where
     }
 }
 
+impl<T: Hash + Sync, S> HashSet<T, S> {
+    /// Computes a digest of the set's values that is independent of their
+    /// order in the table.  See `HashMap::par_content_hash`.
+    pub fn par_content_hash<H: Hasher + Default>(&self) -> u64 {
+        self.map.par_content_hash::<H>()
+    }
+
+    /// Feeds the set into `state` exactly like its `Hash` implementation,
+    /// but hashing the values in parallel.
+    pub fn par_hash<H: Hasher>(&self, state: &mut H) {
+        self.map.par_hash(state)
+    }
+}
+
+impl<T: Ord + Sync, S> HashSet<T, S> {
+    /// Returns a parallel iterator over the values in ascending order.  See
+    /// `HashMap::par_sorted_keys`.
+    pub fn par_sorted_iter(&self) -> vec::IntoIter<&T> {
+        self.map.par_sorted_keys()
+    }
+}
+
+impl<T: Ord + Send, S> HashSet<T, S> {
+    /// Consumes the set and returns its values in a sorted vector, moving
+    /// them out and sorting in parallel.
+    pub fn into_sorted_vec(self) -> Vec<T> {
+        let mut values: Vec<T> = self.into_par_iter().collect();
+        values.par_sort_unstable();
+        values
+    }
+}
+
+impl<T, S> HashSet<T, S>
+where
+    T: Eq + Hash + Clone + Send + Sync,
+    S: BuildHasher + Default + Send + Sync,
+{
+    /// Returns the union of `self` and `other` as a new set, like `&a | &b`.
+    pub fn par_bitor(&self, other: &Self) -> Self {
+        self.par_union(other).cloned().collect()
+    }
+
+    /// Returns the intersection of `self` and `other` as a new set, like `&a & &b`.
+    pub fn par_bitand(&self, other: &Self) -> Self {
+        self.par_intersection(other).cloned().collect()
+    }
+
+    /// Returns the symmetric difference of `self` and `other` as a new set, like `&a ^ &b`.
+    pub fn par_bitxor(&self, other: &Self) -> Self {
+        self.par_symmetric_difference(other).cloned().collect()
+    }
+
+    /// Returns the difference of `self` and `other` as a new set, like `&a - &b`.
+    pub fn par_sub(&self, other: &Self) -> Self {
+        self.par_difference(other).cloned().collect()
+    }
+}
+
+impl<T, S> HashSet<T, S>
+where
+    T: Eq + Hash + Send,
+    S: BuildHasher + Send,
+{
+    /// Consumes both sets and returns their union, moving the elements of
+    /// the smaller set into the larger one without cloning.
+    pub fn par_union_into(self, other: Self) -> Self {
+        let (mut large, small) = if self.len() >= other.len() {
+            (self, other)
+        } else {
+            (other, self)
+        };
+        large.par_extend(small);
+        large
+    }
+}
+
 impl<T: Send, S> IntoParallelIterator for HashSet<T, S> {
     type Item = T;
     type Iter = ParIntoIter<T>;
@@ -256,10 +431,68 @@ where
     }
 }
 
+impl<'a, T, S> ParallelIterator for ParUnionAll<'a, T, S>
+where
+    T: Eq + Hash + Sync,
+    S: BuildHasher + Sync,
+{
+    type Item = &'a T;
+
+    fn drive_unindexed<C>(self, consumer: C) -> C::Result
+    where
+        C: UnindexedConsumer<Self::Item>,
+    {
+        // The values of all sets are gathered into a single set of
+        // references, hashed with the first set's hash builder, which then
+        // yields each value once.
+        let sets = self.sets;
+        let hash_builder = match sets.first() {
+            Some(set) => set.hasher(),
+            None => return rayon::iter::empty().drive_unindexed(consumer),
+        };
+        let values = sets
+            .par_iter()
+            .flat_map(|&set| set.into_par_iter())
+            .map(|x| (make_hash(hash_builder, x), x, ()));
+        fold_sharded(values, || (), |_, _, ()| (), |_, _, ()| ())
+            .into_par_buckets()
+            .map(|(_, _, x, ())| x)
+            .drive_unindexed(consumer)
+    }
+}
+
+impl<'a, T, S> ParallelIterator for ParIntersectionAll<'a, T, S>
+where
+    T: Eq + Hash + Sync,
+    S: BuildHasher + Sync,
+{
+    type Item = &'a T;
+
+    fn drive_unindexed<C>(self, consumer: C) -> C::Result
+    where
+        C: UnindexedConsumer<Self::Item>,
+    {
+        let sets = self.sets;
+        match sets.iter().min_by_key(|set| set.len()) {
+            Some(&smallest) => smallest
+                .into_par_iter()
+                .filter(|&x| {
+                    sets.iter()
+                        .all(|&set| ptr::eq(set, smallest) || set.contains(x))
+                }).drive_unindexed(consumer),
+            None => rayon::iter::empty().drive_unindexed(consumer),
+        }
+    }
+}
+
 #[cfg(test)]
 mod test_par_set {
     use super::HashSet;
+    use super::{par_intersection_all, par_intersection_all_into};
+    use super::{par_union_all, par_union_all_into};
+    use crate::hash_map::DefaultHasher;
     use rayon::prelude::*;
+    use std::hash::{Hash, Hasher};
     use std::sync::atomic::{AtomicUsize, Ordering};
 
     #[test]
@@ -313,6 +546,29 @@ mod test_par_set {
         assert!(b.par_is_superset(&a));
     }
 
+    #[test]
+    fn test_subset_short_circuit() {
+        let small: HashSet<_> = (0..10).collect();
+        let large: HashSet<_> = (0..10_000).collect();
+
+        // The cardinality check alone decides this, without any probing.
+        assert!(!large.par_is_subset(&small));
+        assert!(large.par_is_superset(&small));
+        assert!(small.par_is_subset(&large));
+
+        // Only the smaller table is iterated when checking disjointness.
+        let odd: HashSet<_> = (0..10_000).map(|i| i * 2 + 1).collect();
+        let even: HashSet<_> = (0..10).map(|i| i * 2).collect();
+        assert!(odd.par_is_disjoint(&even));
+        assert!(even.par_is_disjoint(&odd));
+        assert!(!large.par_is_disjoint(&even));
+
+        // Equal sizes still need a full comparison.
+        let shifted: HashSet<_> = (1..10_001).collect();
+        assert!(!shifted.par_is_subset(&large));
+        assert!(!large.par_is_superset(&shifted));
+    }
+
     #[test]
     fn test_iterate() {
         let mut a = HashSet::new();
@@ -439,6 +695,94 @@ mod test_par_set {
         assert_eq!(i, expected.len());
     }
 
+    #[test]
+    fn test_owned_ops() {
+        let a: HashSet<_> = (0..10).collect();
+        let b: HashSet<_> = (5..15).collect();
+
+        assert_eq!(a.par_bitor(&b), &a | &b);
+        assert_eq!(a.par_bitand(&b), &a & &b);
+        assert_eq!(a.par_bitxor(&b), &a ^ &b);
+        assert_eq!(a.par_sub(&b), &a - &b);
+        assert_eq!(b.par_sub(&a), &b - &a);
+    }
+
+    #[test]
+    fn test_union_into() {
+        let a: HashSet<_> = (0..100).map(|i| i.to_string()).collect();
+        let b: HashSet<_> = (50..60).map(|i| i.to_string()).collect();
+        let expected = &a | &b;
+
+        assert_eq!(a.clone().par_union_into(b.clone()), expected);
+        assert_eq!(b.par_union_into(a), expected);
+
+        let empty = HashSet::new();
+        assert_eq!(expected.clone().par_union_into(empty), expected);
+    }
+
+    #[test]
+    fn test_union_all() {
+        let a: HashSet<_> = (0..10).collect();
+        let b: HashSet<_> = (5..20).collect();
+        let c: HashSet<_> = (15..30).collect();
+
+        let mut union: Vec<_> = par_union_all(&[&a, &b, &c]).cloned().collect();
+        union.sort();
+        assert_eq!(union, (0..30).collect::<Vec<_>>());
+
+        let none: &[&HashSet<i32>] = &[];
+        assert_eq!(par_union_all(none).count(), 0);
+
+        let set = par_union_all_into(vec![a, b, c]);
+        assert_eq!(set, (0..30).collect());
+    }
+
+    #[test]
+    fn test_intersection_all() {
+        let a: HashSet<_> = (0..100).collect();
+        let b: HashSet<_> = (50..150).collect();
+        let c: HashSet<_> = (0..100).filter(|x| x % 3 == 0).collect();
+
+        let mut inter: Vec<_> = par_intersection_all(&[&a, &b, &c])
+            .cloned()
+            .collect();
+        inter.sort();
+        let expected: Vec<_> = (50..100).filter(|x| x % 3 == 0).collect();
+        assert_eq!(inter, expected);
+
+        let empty = HashSet::new();
+        assert_eq!(par_intersection_all(&[&a, &empty]).count(), 0);
+        let none: &[&HashSet<i32>] = &[];
+        assert_eq!(par_intersection_all(none).count(), 0);
+
+        let set = par_intersection_all_into(vec![a, b, c]);
+        assert_eq!(set, expected.into_iter().collect());
+    }
+
+    #[test]
+    fn test_content_hash() {
+        let a: HashSet<_> = (0..1000).collect();
+        let mut b = HashSet::with_capacity(5000);
+        b.extend((0..1000).rev());
+
+        let hash = a.par_content_hash::<DefaultHasher>();
+        assert_eq!(hash, b.par_content_hash::<DefaultHasher>());
+
+        b.remove(&500);
+        assert_ne!(hash, b.par_content_hash::<DefaultHasher>());
+    }
+
+    #[test]
+    fn test_par_hash() {
+        let set: HashSet<_> = (0..1000).collect();
+
+        let mut serial = DefaultHasher::new();
+        set.hash(&mut serial);
+        let mut parallel = DefaultHasher::new();
+        set.par_hash(&mut parallel);
+        assert_eq!(serial.finish(), parallel.finish());
+    }
+
     #[test]
     fn test_from_iter() {
         let xs = [1, 2, 3, 4, 5, 6, 7, 8, 9];
@@ -487,6 +831,15 @@ mod test_par_set {
         assert!(s1.par_eq(&s2));
     }
 
+    #[test]
+    fn test_sorted() {
+        let set: HashSet<i32> = (-500..500).rev().collect();
+        let values: Vec<i32> = set.par_sorted_iter().cloned().collect();
+        assert_eq!(values, (-500..500).collect::<Vec<_>>());
+        assert_eq!(set.par_sorted_iter().len(), 1000);
+        assert_eq!(set.into_sorted_vec(), values);
+    }
+
     #[test]
     fn test_extend_ref() {
         let mut a = HashSet::new();
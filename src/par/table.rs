@@ -1,11 +1,12 @@
 /// Rayon extensions to `RawTable`
+use std::cmp;
 use std::marker;
 use std::ptr;
 
 use rayon::iter::plumbing::*;
 use rayon::prelude::*;
 
-use crate::std_hash::table::{RawBucket, RawTable};
+use crate::std_hash::table::{RawBucket, RawTable, SafeHash};
 
 struct SplitBuckets<'a, K, V> {
     bucket: RawBucket<K, V>,
@@ -54,6 +55,34 @@ impl<'a, K, V> Iterator for SplitBuckets<'a, K, V> {
     }
 }
 
+/// Iterator over the entries in a fixed range of a table's buckets, so work
+/// can be divided the same way no matter how many threads there are.
+pub(crate) struct BucketRange<'a, K: 'a, V: 'a> {
+    iter: SplitBuckets<'a, K, V>,
+}
+
+impl<K, V> RawTable<K, V> {
+    /// Returns an iterator over the entries in buckets `start..end`.
+    pub(crate) fn bucket_range(&self, start: usize, end: usize) -> BucketRange<'_, K, V> {
+        assert!(start <= end && end <= self.capacity());
+        let mut iter = SplitBuckets::new(self);
+        iter.bucket.index_add(start);
+        iter.end = end;
+        BucketRange { iter }
+    }
+}
+
+impl<'a, K, V> Iterator for BucketRange<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.next().map(|bucket| unsafe {
+            let pair_ptr = bucket.pair();
+            (&(*pair_ptr).0, &(*pair_ptr).1)
+        })
+    }
+}
+
 /// Parallel iterator over shared references to entries in a map.
 pub struct ParIter<'a, K: 'a, V: 'a> {
     table: &'a RawTable<K, V>,
@@ -430,3 +459,248 @@ impl<'a, K: 'a, V: 'a> Drop for ParIntoIterProducer<'a, K, V> {
         }
     }
 }
+
+/// Parallel iterator over the entries in a table with their bucket index and
+/// stored hash, consuming it.  This lets a new table be filled with the very
+/// same layout, without hashing anything again.
+pub(crate) struct ParIntoBuckets<K, V> {
+    table: RawTable<K, V>,
+}
+
+impl<K: Send, V: Send> RawTable<K, V> {
+    pub(crate) fn into_par_buckets(self) -> ParIntoBuckets<K, V> {
+        ParIntoBuckets { table: self }
+    }
+}
+
+impl<K: Send, V: Send> ParallelIterator for ParIntoBuckets<K, V> {
+    type Item = (usize, SafeHash, K, V);
+
+    fn drive_unindexed<C>(self, consumer: C) -> C::Result
+    where
+        C: UnindexedConsumer<Self::Item>,
+    {
+        // Pre-set the map size to zero, indicating all items drained.
+        let mut table = self.table;
+        unsafe {
+            table.set_size(0);
+        }
+
+        let buckets = SplitBuckets::new(&table);
+        let producer = ParIntoBucketsProducer::from(buckets);
+        bridge_unindexed(producer, consumer)
+    }
+}
+
+struct ParIntoBucketsProducer<'a, K: 'a, V: 'a> {
+    iter: SplitBuckets<'a, K, V>,
+}
+
+impl<'a, K, V> From<SplitBuckets<'a, K, V>> for ParIntoBucketsProducer<'a, K, V> {
+    fn from(iter: SplitBuckets<'a, K, V>) -> Self {
+        Self { iter }
+    }
+}
+
+unsafe impl<'a, K: Send, V: Send> Send for ParIntoBucketsProducer<'a, K, V> {}
+
+impl<'a, K: Send, V: Send> UnindexedProducer for ParIntoBucketsProducer<'a, K, V> {
+    type Item = (usize, SafeHash, K, V);
+
+    fn split(mut self) -> (Self, Option<Self>) {
+        // We must not drop self yet!
+        let (left, right) = self.iter.split();
+        self.iter = left;
+        (self, right.map(Self::from))
+    }
+
+    fn fold_with<F>(mut self, folder: F) -> F
+    where
+        F: Folder<Self::Item>,
+    {
+        let iter = self.iter.by_ref().map(|bucket| unsafe {
+            let hash = bucket.safe_hash();
+            bucket.set_empty();
+            let (k, v) = ptr::read(bucket.pair());
+            (bucket.index(), hash, k, v)
+        });
+        folder.consume_iter(iter)
+    }
+}
+
+impl<'a, K: 'a, V: 'a> Drop for ParIntoBucketsProducer<'a, K, V> {
+    fn drop(&mut self) {
+        for bucket in self.iter.by_ref() {
+            unsafe {
+                bucket.set_empty();
+                ptr::drop_in_place(bucket.pair());
+            }
+        }
+    }
+}
+
+/// Writes entries into distinct buckets of a table from multiple threads.
+///
+/// The table's size is not updated, so the caller must set it once all
+/// writes are done.  If that never happens, e.g. on a panic, the written
+/// entries are leaked rather than dropped.
+///
+/// Full buckets can be accessed too, again from one thread each.
+pub(crate) struct BucketWriter<'a, K: 'a, V: 'a> {
+    start: RawBucket<K, V>,
+    marker: marker::PhantomData<&'a mut RawTable<K, V>>,
+}
+
+unsafe impl<'a, K: Send, V: Send> Send for BucketWriter<'a, K, V> {}
+unsafe impl<'a, K: Send, V: Send> Sync for BucketWriter<'a, K, V> {}
+
+impl<'a, K, V> BucketWriter<'a, K, V> {
+    pub(crate) fn new(table: &'a mut RawTable<K, V>) -> Self {
+        BucketWriter {
+            start: table.raw_bucket_at(0),
+            marker: marker::PhantomData,
+        }
+    }
+
+    /// The bucket at `index` must be empty and within the table's capacity,
+    /// and must not be written by any other thread.
+    pub(crate) unsafe fn write(&self, index: usize, hash: SafeHash, key: K, value: V) {
+        let mut bucket = self.start;
+        bucket.index_add(index);
+        debug_assert!(bucket.is_empty());
+        bucket.write(hash, (key, value));
+    }
+
+    /// The bucket at `index` must be full and within the table's capacity,
+    /// and must not be accessed by any other thread.
+    pub(crate) unsafe fn pair(&self, index: usize) -> *mut (K, V) {
+        let mut bucket = self.start;
+        bucket.index_add(index);
+        debug_assert!(!bucket.is_empty());
+        bucket.pair()
+    }
+}
+
+impl<K: Send, V: Send> RawTable<K, V> {
+    /// Maps every value in parallel, keeping each key in the same bucket.
+    pub(crate) fn par_map_values<W, F>(self, f: F) -> RawTable<K, W>
+    where
+        W: Send,
+        F: Fn(&K, V) -> W + Sync,
+    {
+        let size = self.size();
+        let mut table = RawTable::new(self.capacity());
+        table.set_tag(self.tag());
+        {
+            let writer = BucketWriter::new(&mut table);
+            self.into_par_buckets().for_each(|(index, hash, key, value)| {
+                let value = f(&key, value);
+                unsafe { writer.write(index, hash, key, value) };
+            });
+        }
+        unsafe {
+            table.set_size(size);
+        }
+        table
+    }
+}
+
+/// Entries moved out of a table along with their stored hashes.
+type Entries<K, V> = Vec<(SafeHash, K, V)>;
+
+impl<K: Send, V: Send> RawTable<K, V> {
+    /// Fills an empty table in parallel with entries and their stored
+    /// hashes.  The keys must all be distinct, and the hashes must come from
+    /// the same hash builder.
+    ///
+    /// The buckets are divided into contiguous regions, and the entries of
+    /// each region are laid out in order of their ideal bucket, exactly as
+    /// `resize` would insert them.  Only the number of entries spilling over
+    /// from one region into the next has to be worked out serially.
+    pub(crate) fn par_fill<I>(&mut self, entries: I)
+    where
+        I: ParallelIterator<Item = (SafeHash, K, V)>,
+    {
+        assert_eq!(self.size(), 0);
+        let capacity = self.capacity();
+        if capacity == 0 {
+            assert_eq!(entries.count(), 0, "not enough room for the entries");
+            return;
+        }
+        let mask = capacity - 1;
+        let num_regions = cmp::min(capacity, (rayon::current_num_threads() * 4).next_power_of_two());
+        let region_len = capacity / num_regions;
+
+        // Sort each worker's entries into regions by their ideal bucket.
+        let new_parts = || -> Vec<Entries<K, V>> { (0..num_regions).map(|_| Vec::new()).collect() };
+        let parts: Vec<Vec<Entries<K, V>>> = entries
+            .fold(new_parts, |mut parts, (hash, key, value)| {
+                let region = (hash.inspect() & mask) / region_len;
+                parts[region].push((hash, key, value));
+                parts
+            }).collect();
+
+        let size: usize = parts.iter().flatten().map(Vec::len).sum();
+        if size == 0 {
+            return;
+        }
+        assert!(size < capacity, "not enough room for the entries");
+
+        let mut regions: Vec<Vec<_>> = (0..num_regions).map(|_| Vec::new()).collect();
+        for parts in parts {
+            for (region, part) in regions.iter_mut().zip(parts) {
+                region.push(part);
+            }
+        }
+
+        // Order each region by ideal bucket, and find where its layout would
+        // end if nothing spilled into it from the previous region.
+        let regions: Vec<(Entries<K, V>, usize)> = regions
+            .into_par_iter()
+            .enumerate()
+            .map(|(i, parts)| {
+                let mut entries: Vec<_> = parts.into_iter().flatten().collect();
+                entries.sort_unstable_by_key(|&(hash, _, _)| hash.inspect() & mask);
+                let mut next = i * region_len;
+                for &(hash, _, _) in &entries {
+                    next = cmp::max(hash.inspect() & mask, next) + 1;
+                }
+                (entries, next)
+            }).collect();
+
+        // With entries spilling in up to position `start`, a region's layout
+        // ends at `max(end, start + len)`.  The last region may also wrap
+        // around into the first, so repeat until that spill is stable.
+        let mut starts = vec![0; num_regions];
+        let mut wrapped = 0;
+        loop {
+            let mut next = wrapped;
+            for (start, &(ref entries, end)) in starts.iter_mut().zip(&regions) {
+                *start = next;
+                next = cmp::max(end, next + entries.len());
+            }
+            let spill = next.saturating_sub(capacity);
+            if spill <= wrapped {
+                break;
+            }
+            wrapped = spill;
+        }
+
+        {
+            let writer = BucketWriter::new(self);
+            regions
+                .into_par_iter()
+                .zip(starts)
+                .for_each(|((entries, _), mut next)| {
+                    for (hash, key, value) in entries {
+                        let index = cmp::max(hash.inspect() & mask, next);
+                        next = index + 1;
+                        unsafe { writer.write(index & mask, hash, key, value) };
+                    }
+                });
+        }
+        unsafe {
+            self.set_size(size);
+        }
+    }
+}
@@ -0,0 +1,77 @@
+/// Rayon extensions for `TtlHashMap`
+use rayon::iter::ParallelIterator;
+use std::hash::{BuildHasher, Hash};
+use std::mem;
+
+use crate::hash_ttl::{Clock, TtlHashMap};
+use crate::std_hash::table::RawTable;
+
+impl<K, V, C, S> TtlHashMap<K, V, C, S>
+where
+    K: Eq + Hash + Send + Sync,
+    V: Send + Sync,
+    C: Clock,
+    C::Instant: Send + Sync,
+    S: BuildHasher,
+{
+    /// Removes the entries that have expired by `now` in parallel, and
+    /// returns how many were removed.
+    ///
+    /// The buckets are first scanned in parallel for expired entries.  If
+    /// there are any, the live entries are moved in parallel straight into a
+    /// new table of the same capacity, keeping their stored hashes.  If that
+    /// panics, the map is left empty.
+    pub fn par_sweep(&mut self, now: C::Instant) -> usize {
+        let expired = self
+            .map
+            .par_values()
+            .filter(|timed| !timed.is_live(now))
+            .count();
+        if expired == 0 {
+            return 0;
+        }
+
+        let old = mem::replace(&mut self.map.table, RawTable::new(0));
+        let mut table = RawTable::new(old.capacity());
+        table.par_fill(
+            old.into_par_buckets()
+                .filter(|(_, _, _, timed)| timed.is_live(now))
+                .map(|(_, hash, key, timed)| (hash, key, timed)),
+        );
+        self.map.table = table;
+        expired
+    }
+}
+
+#[cfg(test)]
+mod test_par_ttl {
+    use crate::hash_ttl::{Clock, ManualClock, TtlHashMap};
+
+    #[test]
+    fn test_par_sweep() {
+        let clock = ManualClock::new();
+        let mut map = TtlHashMap::with_clock(clock.clone());
+        for i in 0..100_000 {
+            map.insert(i, i, i % 1000);
+        }
+        assert_eq!(map.par_sweep(clock.now()), 100);
+        assert_eq!(map.par_sweep(clock.now()), 0);
+
+        clock.set(500);
+        assert_eq!(map.iter().count(), 49_900);
+        assert_eq!(map.par_sweep(clock.now()), 50_000);
+        assert_eq!(map.len(), 49_900);
+        for i in 0..100_000 {
+            let expected = if i % 1000 > 500 { Some(&i) } else { None };
+            assert_eq!(map.get(&i), expected);
+        }
+
+        // Entries expired since the last sweep are still hidden and kept.
+        clock.set(999);
+        assert_eq!(map.get(&998), None);
+        assert_eq!(map.len(), 49_900);
+        map.insert(1, 1, 5);
+        assert_eq!(map.par_sweep(clock.now()), 49_900);
+        assert_eq!(map.iter().collect::<Vec<_>>(), [(&1, &1)]);
+    }
+}
@@ -0,0 +1,554 @@
+use std::borrow::Borrow;
+use std::fmt;
+use std::hash::{Hash, BuildHasher, BuildHasherDefault};
+use std::iter::FromIterator;
+
+use super::map::{self, DefaultHasher, Entry, HashMap, RandomState};
+use super::table::{make_hash, SafeHash};
+
+/// A bidirectional hash map, where every left value maps to exactly one
+/// right value and the other way around.
+///
+/// It is backed by two tables, one keyed by the left values and one keyed
+/// by the right values, which are both hashed by the same `BuildHasher`.
+/// Every pair is stored in both tables, so both sides must be `Clone` to
+/// insert.
+///
+/// # Examples
+///
+/// ```
+/// use rayon_hash::BiHashMap;
+/// use rayon_hash::hash_bimap::Overwritten;
+///
+/// let mut ids = BiHashMap::new();
+/// ids.insert(1, "one");
+/// ids.insert(2, "two");
+///
+/// assert_eq!(ids.get_by_left(&1), Some(&"one"));
+/// assert_eq!(ids.get_by_right(&"two"), Some(&2));
+///
+/// // Both existing pairs are replaced by the new one.
+/// assert_eq!(ids.insert(1, "two"), Overwritten::Both((1, "one"), (2, "two")));
+/// assert_eq!(ids.len(), 1);
+/// ```
+#[derive(Clone)]
+pub struct BiHashMap<L, R, S = RandomState> {
+    hash_builder: S,
+    pub(crate) left: Side<L, R>,
+    pub(crate) right: Side<R, L>,
+}
+
+/// The tables are only ever used with hashes from the bimap's hash builder,
+/// never their own, so its type doesn't matter.
+pub(crate) type Side<K, V> = HashMap<K, V, BuildHasherDefault<DefaultHasher>>;
+
+/// The pairs that were removed by [`BiHashMap::insert`] to keep the map
+/// one-to-one.
+///
+/// [`BiHashMap::insert`]: struct.BiHashMap.html#method.insert
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Overwritten<L, R> {
+    /// Neither value was present.
+    Neither,
+    /// The left value was paired with another right value.
+    Left(L, R),
+    /// The right value was paired with another left value.
+    Right(L, R),
+    /// The exact same pair was present.
+    Pair(L, R),
+    /// Both values were present in two different pairs, given as the pair
+    /// of the left value, then the pair of the right value.
+    Both((L, R), (L, R)),
+}
+
+impl<L, R> BiHashMap<L, R, RandomState>
+    where L: Eq + Hash,
+          R: Eq + Hash
+{
+    /// Creates an empty `BiHashMap`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rayon_hash::BiHashMap;
+    /// let map: BiHashMap<u32, String> = BiHashMap::new();
+    /// ```
+    #[inline]
+    pub fn new() -> BiHashMap<L, R, RandomState> {
+        Default::default()
+    }
+
+    /// Creates an empty `BiHashMap` with room for at least `capacity` pairs.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rayon_hash::BiHashMap;
+    /// let map: BiHashMap<u32, String> = BiHashMap::with_capacity(10);
+    /// assert!(map.capacity() >= 10);
+    /// ```
+    #[inline]
+    pub fn with_capacity(capacity: usize) -> BiHashMap<L, R, RandomState> {
+        BiHashMap::with_capacity_and_hasher(capacity, Default::default())
+    }
+}
+
+impl<L, R, S> BiHashMap<L, R, S> {
+    /// Returns the number of pairs the map can hold without reallocating.
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.left.capacity().min(self.right.capacity())
+    }
+
+    /// Returns the number of pairs in the map.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.left.len()
+    }
+
+    /// Returns `true` if the map contains no pairs.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.left.is_empty()
+    }
+
+    /// Clears the map, removing all pairs.  Keeps the allocated memory for
+    /// reuse.
+    #[inline]
+    pub fn clear(&mut self) {
+        self.left.clear();
+        self.right.clear();
+    }
+
+    /// Returns a reference to the map's `BuildHasher`.
+    #[inline]
+    pub fn hasher(&self) -> &S {
+        &self.hash_builder
+    }
+
+    /// An iterator visiting all pairs in arbitrary order.
+    #[inline]
+    pub fn iter(&self) -> map::Iter<'_, L, R> {
+        self.left.iter()
+    }
+
+    /// An iterator visiting all left values in arbitrary order.
+    #[inline]
+    pub fn left_values(&self) -> map::Keys<'_, L, R> {
+        self.left.keys()
+    }
+
+    /// An iterator visiting all right values in arbitrary order.
+    #[inline]
+    pub fn right_values(&self) -> map::Keys<'_, R, L> {
+        self.right.keys()
+    }
+}
+
+impl<L, R, S> BiHashMap<L, R, S>
+    where L: Eq + Hash,
+          R: Eq + Hash,
+          S: BuildHasher
+{
+    /// Creates an empty `BiHashMap` which will use the given hash builder to
+    /// hash both sides.
+    #[inline]
+    pub fn with_hasher(hash_builder: S) -> BiHashMap<L, R, S> {
+        BiHashMap::with_capacity_and_hasher(0, hash_builder)
+    }
+
+    /// Creates an empty `BiHashMap` with room for at least `capacity` pairs,
+    /// using `hash_builder` to hash both sides.
+    #[inline]
+    pub fn with_capacity_and_hasher(capacity: usize, hash_builder: S) -> BiHashMap<L, R, S> {
+        BiHashMap {
+            hash_builder,
+            left: Side::with_capacity_and_hasher(capacity, Default::default()),
+            right: Side::with_capacity_and_hasher(capacity, Default::default()),
+        }
+    }
+
+    fn make_hash<X: ?Sized + Hash>(&self, x: &X) -> SafeHash {
+        make_hash(&self.hash_builder, x)
+    }
+
+    /// Reserves capacity for at least `additional` more pairs.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the new allocation size overflows `usize`.
+    #[inline]
+    pub fn reserve(&mut self, additional: usize) {
+        self.left.reserve(additional);
+        self.right.reserve(additional);
+    }
+
+    /// Shrinks the capacity of the map as much as possible.
+    #[inline]
+    pub fn shrink_to_fit(&mut self) {
+        self.left.shrink_to_fit();
+        self.right.shrink_to_fit();
+    }
+
+    /// Returns the right value paired with a left value.
+    ///
+    /// The value may be any borrowed form of the left type, but
+    /// [`Hash`] and [`Eq`] on the borrowed form *must* match those for
+    /// the left type.
+    ///
+    /// [`Eq`]: ../../std/cmp/trait.Eq.html
+    /// [`Hash`]: ../../std/hash/trait.Hash.html
+    #[inline]
+    pub fn get_by_left<Q>(&self, left: &Q) -> Option<&R>
+        where L: Borrow<Q>,
+              Q: ?Sized + Hash + Eq
+    {
+        self.left.get_hashed(self.make_hash(left), left).map(|(_, right)| right)
+    }
+
+    /// Returns the left value paired with a right value.
+    ///
+    /// The value may be any borrowed form of the right type, but
+    /// [`Hash`] and [`Eq`] on the borrowed form *must* match those for
+    /// the right type.
+    ///
+    /// [`Eq`]: ../../std/cmp/trait.Eq.html
+    /// [`Hash`]: ../../std/hash/trait.Hash.html
+    #[inline]
+    pub fn get_by_right<Q>(&self, right: &Q) -> Option<&L>
+        where R: Borrow<Q>,
+              Q: ?Sized + Hash + Eq
+    {
+        self.right.get_hashed(self.make_hash(right), right).map(|(_, left)| left)
+    }
+
+    /// Returns `true` if the map contains a pair with the left value.
+    #[inline]
+    pub fn contains_left<Q>(&self, left: &Q) -> bool
+        where L: Borrow<Q>,
+              Q: ?Sized + Hash + Eq
+    {
+        self.get_by_left(left).is_some()
+    }
+
+    /// Returns `true` if the map contains a pair with the right value.
+    #[inline]
+    pub fn contains_right<Q>(&self, right: &Q) -> bool
+        where R: Borrow<Q>,
+              Q: ?Sized + Hash + Eq
+    {
+        self.get_by_right(right).is_some()
+    }
+
+    /// Removes the pair with the left value, and returns it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rayon_hash::BiHashMap;
+    ///
+    /// let mut map = BiHashMap::new();
+    /// map.insert(1, 'a');
+    /// assert_eq!(map.remove_by_left(&1), Some((1, 'a')));
+    /// assert_eq!(map.remove_by_left(&1), None);
+    /// assert!(!map.contains_right(&'a'));
+    /// ```
+    pub fn remove_by_left<Q>(&mut self, left: &Q) -> Option<(L, R)>
+        where L: Borrow<Q>,
+              Q: ?Sized + Hash + Eq
+    {
+        let (left, right) = self.left.remove_hashed(self.make_hash(left), left)?;
+        self.right.remove_hashed(self.make_hash(&right), &right);
+        Some((left, right))
+    }
+
+    /// Removes the pair with the right value, and returns it.
+    pub fn remove_by_right<Q>(&mut self, right: &Q) -> Option<(L, R)>
+        where R: Borrow<Q>,
+              Q: ?Sized + Hash + Eq
+    {
+        let (right, left) = self.right.remove_hashed(self.make_hash(right), right)?;
+        self.left.remove_hashed(self.make_hash(&left), &left);
+        Some((left, right))
+    }
+
+    /// Retains only the pairs specified by the predicate.
+    pub fn retain<F>(&mut self, mut f: F)
+        where F: FnMut(&L, &R) -> bool
+    {
+        let hash_builder = &self.hash_builder;
+        let right = &mut self.right;
+        self.left.retain(|l, r| {
+            let keep = f(l, r);
+            if !keep {
+                right.remove_hashed(make_hash(hash_builder, r), r);
+            }
+            keep
+        });
+    }
+}
+
+impl<L, R, S> BiHashMap<L, R, S>
+    where L: Eq + Hash + Clone,
+          R: Eq + Hash + Clone,
+          S: BuildHasher
+{
+    /// Inserts a pair, removing any pairs that contain either value, and
+    /// reports which pairs were removed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rayon_hash::BiHashMap;
+    /// use rayon_hash::hash_bimap::Overwritten;
+    ///
+    /// let mut map = BiHashMap::new();
+    /// assert_eq!(map.insert(1, 'a'), Overwritten::Neither);
+    /// assert_eq!(map.insert(1, 'a'), Overwritten::Pair(1, 'a'));
+    /// assert_eq!(map.insert(1, 'b'), Overwritten::Left(1, 'a'));
+    /// assert_eq!(map.insert(2, 'b'), Overwritten::Right(1, 'b'));
+    /// assert_eq!(map.get_by_right(&'b'), Some(&2));
+    /// ```
+    pub fn insert(&mut self, left: L, right: R) -> Overwritten<L, R> {
+        let left_hash = self.make_hash(&left);
+        let right_hash = self.make_hash(&right);
+        let overwritten = match self.left.remove_hashed(left_hash, &left) {
+            Some((l, r)) => {
+                if r == right {
+                    self.right.remove_hashed(right_hash, &right);
+                    Overwritten::Pair(l, r)
+                } else {
+                    self.right.remove_hashed(self.make_hash(&r), &r);
+                    match self.remove_by_right_hashed(right_hash, &right) {
+                        Some(pair) => Overwritten::Both((l, r), pair),
+                        None => Overwritten::Left(l, r),
+                    }
+                }
+            }
+            None => match self.remove_by_right_hashed(right_hash, &right) {
+                Some((l, r)) => Overwritten::Right(l, r),
+                None => Overwritten::Neither,
+            },
+        };
+        self.insert_hashed_unique(left_hash, left, right_hash, right);
+        overwritten
+    }
+
+    /// Inserts a pair only if neither value is present yet, or returns the
+    /// pair back otherwise.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rayon_hash::BiHashMap;
+    ///
+    /// let mut map = BiHashMap::new();
+    /// assert_eq!(map.insert_no_overwrite(1, 'a'), Ok(()));
+    /// assert_eq!(map.insert_no_overwrite(2, 'a'), Err((2, 'a')));
+    /// assert_eq!(map.get_by_right(&'a'), Some(&1));
+    /// ```
+    pub fn insert_no_overwrite(&mut self, left: L, right: R) -> Result<(), (L, R)> {
+        let left_hash = self.make_hash(&left);
+        let right_hash = self.make_hash(&right);
+        if self.left.get_hashed(left_hash, &left).is_some() ||
+           self.right.get_hashed(right_hash, &right).is_some() {
+            return Err((left, right));
+        }
+        self.insert_hashed_unique(left_hash, left, right_hash, right);
+        Ok(())
+    }
+
+    fn remove_by_right_hashed(&mut self, right_hash: SafeHash, right: &R) -> Option<(L, R)> {
+        let (r, l) = self.right.remove_hashed(right_hash, right)?;
+        self.left.remove_hashed(self.make_hash(&l), &l);
+        Some((l, r))
+    }
+
+    // The caller should ensure that neither value is present.
+    fn insert_hashed_unique(&mut self, left_hash: SafeHash, left: L,
+                            right_hash: SafeHash, right: R) {
+        // Clone and look up both sides before inserting into either, so a
+        // panic in `Clone` or `Eq` can't leave a pair with one side.
+        let (left_key, right_key) = (left.clone(), right.clone());
+        let right_entry = self.right.entry_hashed(right_hash, right_key);
+        let left_entry = self.left.entry_hashed(left_hash, left_key);
+        if let (Entry::Vacant(left_entry), Entry::Vacant(right_entry)) = (left_entry, right_entry) {
+            left_entry.insert(right);
+            right_entry.insert(left);
+        }
+    }
+}
+
+impl<L, R, S> PartialEq for BiHashMap<L, R, S>
+    where L: Eq + Hash,
+          R: Eq + Hash,
+          S: BuildHasher
+{
+    fn eq(&self, other: &BiHashMap<L, R, S>) -> bool {
+        self.len() == other.len() &&
+            self.iter().all(|(l, r)| other.get_by_left(l) == Some(r))
+    }
+}
+
+impl<L, R, S> Eq for BiHashMap<L, R, S>
+    where L: Eq + Hash,
+          R: Eq + Hash,
+          S: BuildHasher
+{
+}
+
+impl<L, R, S> fmt::Debug for BiHashMap<L, R, S>
+    where L: fmt::Debug,
+          R: fmt::Debug
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<L, R, S> Default for BiHashMap<L, R, S>
+    where L: Eq + Hash,
+          R: Eq + Hash,
+          S: BuildHasher + Default
+{
+    /// Creates an empty `BiHashMap<L, R, S>`, with the `Default` value for the hasher.
+    fn default() -> BiHashMap<L, R, S> {
+        BiHashMap::with_hasher(Default::default())
+    }
+}
+
+/// Inserts all pairs of an iterator in order, so later pairs overwrite
+/// earlier ones as in `insert`.
+impl<L, R, S> FromIterator<(L, R)> for BiHashMap<L, R, S>
+    where L: Eq + Hash + Clone,
+          R: Eq + Hash + Clone,
+          S: BuildHasher + Default
+{
+    fn from_iter<T: IntoIterator<Item = (L, R)>>(iter: T) -> BiHashMap<L, R, S> {
+        let mut map = BiHashMap::default();
+        map.extend(iter);
+        map
+    }
+}
+
+impl<L, R, S> Extend<(L, R)> for BiHashMap<L, R, S>
+    where L: Eq + Hash + Clone,
+          R: Eq + Hash + Clone,
+          S: BuildHasher
+{
+    fn extend<T: IntoIterator<Item = (L, R)>>(&mut self, iter: T) {
+        for (left, right) in iter {
+            self.insert(left, right);
+        }
+    }
+}
+
+impl<'a, L, R, S> IntoIterator for &'a BiHashMap<L, R, S> {
+    type Item = (&'a L, &'a R);
+    type IntoIter = map::Iter<'a, L, R>;
+
+    fn into_iter(self) -> map::Iter<'a, L, R> {
+        self.iter()
+    }
+}
+
+#[cfg(test)]
+mod test_bimap {
+    use super::{BiHashMap, Overwritten};
+
+    #[test]
+    fn test_insert_overwrites() {
+        let mut map = BiHashMap::new();
+        assert_eq!(map.insert(1, "a"), Overwritten::Neither);
+        assert_eq!(map.insert(2, "b"), Overwritten::Neither);
+        assert_eq!(map.insert(3, "c"), Overwritten::Neither);
+
+        assert_eq!(map.insert(2, "b"), Overwritten::Pair(2, "b"));
+        assert_eq!(map.insert(1, "x"), Overwritten::Left(1, "a"));
+        assert_eq!(map.insert(4, "x"), Overwritten::Right(1, "x"));
+        assert_eq!(map.insert(2, "c"), Overwritten::Both((2, "b"), (3, "c")));
+
+        assert_eq!(map.len(), 2);
+        assert_eq!(map.get_by_left(&2), Some(&"c"));
+        assert_eq!(map.get_by_left(&4), Some(&"x"));
+        assert_eq!(map.get_by_right("c"), Some(&2));
+        assert_eq!(map.get_by_right("x"), Some(&4));
+        for &gone in &[1, 3] {
+            assert!(!map.contains_left(&gone));
+        }
+        for &gone in &["a", "b"] {
+            assert!(!map.contains_right(gone));
+        }
+        assert_eq!(map.right_values().count(), 2);
+    }
+
+    #[test]
+    fn test_remove() {
+        let mut map: BiHashMap<u32, String> = (0..100).map(|i| (i, i.to_string())).collect();
+        assert_eq!(map.remove_by_left(&7), Some((7, "7".to_string())));
+        assert_eq!(map.remove_by_right("8"), Some((8, "8".to_string())));
+        assert_eq!(map.remove_by_right("8"), None);
+        assert!(map.get_by_right("7").is_none());
+        assert!(map.get_by_left(&8).is_none());
+        assert_eq!(map.len(), 98);
+
+        map.retain(|&l, _| l % 2 == 0);
+        assert_eq!(map.len(), 49);
+        assert_eq!(map.right_values().count(), 49);
+        assert!(map.get_by_right("9").is_none());
+        assert_eq!(map.get_by_right("10"), Some(&10));
+    }
+
+    #[test]
+    fn test_insert_no_overwrite() {
+        let mut map = BiHashMap::new();
+        assert_eq!(map.insert_no_overwrite('a', 1), Ok(()));
+        assert_eq!(map.insert_no_overwrite('a', 2), Err(('a', 2)));
+        assert_eq!(map.insert_no_overwrite('b', 1), Err(('b', 1)));
+        assert_eq!(map.insert_no_overwrite('b', 2), Ok(()));
+        assert_eq!(map.len(), 2);
+    }
+
+    #[test]
+    fn test_insert_panic() {
+        use std::hash::{Hash, Hasher};
+        use std::panic::{self, AssertUnwindSafe};
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        static EQ_BUDGET: AtomicUsize = AtomicUsize::new(!0);
+
+        // All values collide, and comparing them panics once the budget
+        // runs out.
+        #[derive(Clone, Debug)]
+        struct Collide(u32);
+
+        impl Hash for Collide {
+            fn hash<H: Hasher>(&self, _: &mut H) {}
+        }
+
+        impl PartialEq for Collide {
+            fn eq(&self, other: &Collide) -> bool {
+                if EQ_BUDGET.load(Ordering::SeqCst) == 0 {
+                    panic!("eq");
+                }
+                EQ_BUDGET.fetch_sub(1, Ordering::SeqCst);
+                self.0 == other.0
+            }
+        }
+
+        impl Eq for Collide {}
+
+        let mut map = BiHashMap::new();
+        map.insert(1, Collide(1));
+        // The first comparison checks that the value is new, and the next
+        // one looks for its place.
+        EQ_BUDGET.store(1, Ordering::SeqCst);
+        let result = panic::catch_unwind(AssertUnwindSafe(|| map.insert_no_overwrite(2, Collide(2))));
+        EQ_BUDGET.store(!0, Ordering::SeqCst);
+        assert!(result.is_err());
+        assert_eq!(map.len(), 1);
+        assert_eq!(map.right_values().count(), 1);
+        assert!(!map.contains_left(&2));
+        assert_eq!(map.get_by_right(&Collide(1)), Some(&1));
+    }
+}
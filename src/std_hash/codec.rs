@@ -0,0 +1,336 @@
+use std::hash::{BuildHasher, Hasher};
+use std::io::{self, Read, Write};
+use std::mem;
+
+use super::map::DefaultHasher;
+
+/// Encodes values of type `T` into bytes, for [`HashMap::write_to`] and
+/// [`HashSet::write_to`].
+///
+/// An encoder can implement `Encode` for several types, such as both the key
+/// and value types of a map.  The encoding only has to be understood by the
+/// matching [`Decode`].
+///
+/// [`HashMap::write_to`]: struct.HashMap.html#method.write_to
+/// [`HashSet::write_to`]: ../hash_set/struct.HashSet.html#method.write_to
+/// [`Decode`]: trait.Decode.html
+pub trait Encode<T: ?Sized> {
+    /// Appends the encoding of `value` to `out`.
+    fn encode(&self, value: &T, out: &mut Vec<u8>);
+}
+
+/// Decodes values of type `T` from bytes, for [`HashMap::read_from`] and
+/// [`HashSet::read_from`].
+///
+/// [`HashMap::read_from`]: struct.HashMap.html#method.read_from
+/// [`HashSet::read_from`]: ../hash_set/struct.HashSet.html#method.read_from
+pub trait Decode<T> {
+    /// Decodes a value from the front of `input`, and advances `input` past
+    /// it.  Returns an error of kind `InvalidData` if the bytes are not a
+    /// valid encoding.
+    fn decode(&self, input: &mut &[u8]) -> io::Result<T>;
+}
+
+/// A simple codec for primitive integers, `bool`, `String` and `Vec<u8>`.
+///
+/// Integers are encoded as little-endian bytes of their full width, with
+/// `usize` and `isize` always taking eight bytes.  Strings and byte vectors
+/// are encoded as their length, like a `u64`, followed by their bytes.
+///
+/// # Examples
+///
+/// ```
+/// use rayon_hash::hash_map::{BinaryCodec, Decode, Encode};
+///
+/// let mut bytes = Vec::new();
+/// BinaryCodec.encode(&String::from("hi"), &mut bytes);
+/// BinaryCodec.encode(&7u16, &mut bytes);
+///
+/// let mut input = &bytes[..];
+/// let s: String = BinaryCodec.decode(&mut input).unwrap();
+/// let n: u16 = BinaryCodec.decode(&mut input).unwrap();
+/// assert_eq!((&*s, n), ("hi", 7));
+/// assert!(input.is_empty());
+/// ```
+#[derive(Clone, Copy, Debug, Default)]
+pub struct BinaryCodec;
+
+pub(crate) fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+/// Splits `len` bytes off the front of `input`.
+fn take<'a>(input: &mut &'a [u8], len: usize) -> io::Result<&'a [u8]> {
+    if input.len() < len {
+        return Err(invalid_data("unexpected end of data"));
+    }
+    let (head, tail) = input.split_at(len);
+    *input = tail;
+    Ok(head)
+}
+
+macro_rules! int_codec {
+    ($($int:ty as $repr:ty),*) => {$(
+        impl Encode<$int> for BinaryCodec {
+            #[inline]
+            fn encode(&self, value: &$int, out: &mut Vec<u8>) {
+                let value = *value as $repr;
+                for i in 0..mem::size_of::<$repr>() {
+                    out.push((value >> (8 * i)) as u8);
+                }
+            }
+        }
+
+        impl Decode<$int> for BinaryCodec {
+            #[inline]
+            fn decode(&self, input: &mut &[u8]) -> io::Result<$int> {
+                let bytes = take(input, mem::size_of::<$repr>())?;
+                let mut value: $repr = 0;
+                for (i, &byte) in bytes.iter().enumerate() {
+                    value |= (byte as $repr) << (8 * i);
+                }
+                if value as $int as $repr != value {
+                    return Err(invalid_data("integer out of range"));
+                }
+                Ok(value as $int)
+            }
+        }
+    )*}
+}
+
+int_codec! {
+    u8 as u8, u16 as u16, u32 as u32, u64 as u64, usize as u64,
+    i8 as u8, i16 as u16, i32 as u32, i64 as u64, isize as u64
+}
+
+impl Encode<bool> for BinaryCodec {
+    #[inline]
+    fn encode(&self, value: &bool, out: &mut Vec<u8>) {
+        out.push(*value as u8);
+    }
+}
+
+impl Decode<bool> for BinaryCodec {
+    #[inline]
+    fn decode(&self, input: &mut &[u8]) -> io::Result<bool> {
+        match take(input, 1)?[0] {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(invalid_data("invalid bool")),
+        }
+    }
+}
+
+impl Encode<[u8]> for BinaryCodec {
+    #[inline]
+    fn encode(&self, value: &[u8], out: &mut Vec<u8>) {
+        self.encode(&value.len(), out);
+        out.extend_from_slice(value);
+    }
+}
+
+impl Encode<Vec<u8>> for BinaryCodec {
+    #[inline]
+    fn encode(&self, value: &Vec<u8>, out: &mut Vec<u8>) {
+        self.encode(&value[..], out);
+    }
+}
+
+impl Decode<Vec<u8>> for BinaryCodec {
+    #[inline]
+    fn decode(&self, input: &mut &[u8]) -> io::Result<Vec<u8>> {
+        let len: usize = self.decode(input)?;
+        Ok(take(input, len)?.to_vec())
+    }
+}
+
+impl Encode<str> for BinaryCodec {
+    #[inline]
+    fn encode(&self, value: &str, out: &mut Vec<u8>) {
+        self.encode(value.as_bytes(), out);
+    }
+}
+
+impl Encode<String> for BinaryCodec {
+    #[inline]
+    fn encode(&self, value: &String, out: &mut Vec<u8>) {
+        self.encode(value.as_bytes(), out);
+    }
+}
+
+impl Decode<String> for BinaryCodec {
+    #[inline]
+    fn decode(&self, input: &mut &[u8]) -> io::Result<String> {
+        let bytes: Vec<u8> = self.decode(input)?;
+        String::from_utf8(bytes).map_err(|_| invalid_data("invalid UTF-8"))
+    }
+}
+
+/// The start of every encoded map or set.
+const MAGIC: [u8; 4] = *b"RHSH";
+
+/// The current version of the format.
+pub const FORMAT_VERSION: u32 = 1;
+
+/// The header of an encoded map or set.
+///
+/// The format, with all integers in little-endian order, is:
+///
+/// - the magic bytes `RHSH`, then the header fields in order, as a `u32`
+///   version and four `u64`s;
+/// - for each chunk, its number of entries and byte length as `u64`s, a
+///   `u64` checksum of its bytes, and then the bytes, which are the encoded
+///   key and value of each entry in turn.
+///
+/// The chunks are ranges of the table's buckets, which are encoded and
+/// decoded in parallel.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Header {
+    /// The format version, which is [`FORMAT_VERSION`] when written.
+    ///
+    /// [`FORMAT_VERSION`]: constant.FORMAT_VERSION.html
+    pub version: u32,
+    /// Identifies how the writer hashed its keys.  This is the hash of a
+    /// fixed string with the map's hash builder, so maps whose builders hash
+    /// the same way have the same id.
+    pub hasher_id: u64,
+    /// The capacity of the map that was written.
+    pub capacity: u64,
+    /// The number of entries.
+    pub len: u64,
+    /// The number of chunks.
+    pub chunks: u64,
+}
+
+impl Header {
+    /// Reads a header from the front of an encoded map or set.  Returns an
+    /// error of kind `InvalidData` if it doesn't start with the magic bytes,
+    /// or has an unknown version.
+    pub fn read_from<R: Read>(reader: &mut R) -> io::Result<Header> {
+        let mut bytes = [0; 40];
+        reader.read_exact(&mut bytes)?;
+        let mut input = &bytes[..];
+        if take(&mut input, 4)? != MAGIC {
+            return Err(invalid_data("not an encoded map"));
+        }
+        let header = Header {
+            version: BinaryCodec.decode(&mut input)?,
+            hasher_id: BinaryCodec.decode(&mut input)?,
+            capacity: BinaryCodec.decode(&mut input)?,
+            len: BinaryCodec.decode(&mut input)?,
+            chunks: BinaryCodec.decode(&mut input)?,
+        };
+        if header.version != FORMAT_VERSION {
+            return Err(invalid_data("unknown format version"));
+        }
+        Ok(header)
+    }
+
+    /// Writes the header.
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        let mut bytes = Vec::with_capacity(40);
+        bytes.extend_from_slice(&MAGIC);
+        BinaryCodec.encode(&self.version, &mut bytes);
+        BinaryCodec.encode(&self.hasher_id, &mut bytes);
+        BinaryCodec.encode(&self.capacity, &mut bytes);
+        BinaryCodec.encode(&self.len, &mut bytes);
+        BinaryCodec.encode(&self.chunks, &mut bytes);
+        writer.write_all(&bytes)
+    }
+}
+
+/// Returns the hasher id of a hash builder, for the header.
+pub(crate) fn hasher_id<S: BuildHasher>(hash_builder: &S) -> u64 {
+    let mut hasher = hash_builder.build_hasher();
+    hasher.write(b"rayon-hash hasher id");
+    hasher.finish()
+}
+
+/// Returns the checksum of a chunk's bytes.
+pub(crate) fn checksum(bytes: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    hasher.write(bytes);
+    hasher.finish()
+}
+
+/// The header of a chunk: its number of entries, byte length and checksum.
+pub(crate) struct ChunkHeader {
+    pub(crate) len: u64,
+    pub(crate) bytes: u64,
+    pub(crate) checksum: u64,
+}
+
+impl ChunkHeader {
+    pub(crate) fn read_from<R: Read>(reader: &mut R) -> io::Result<ChunkHeader> {
+        let mut bytes = [0; 24];
+        reader.read_exact(&mut bytes)?;
+        let mut input = &bytes[..];
+        Ok(ChunkHeader {
+            len: BinaryCodec.decode(&mut input)?,
+            bytes: BinaryCodec.decode(&mut input)?,
+            checksum: BinaryCodec.decode(&mut input)?,
+        })
+    }
+
+    pub(crate) fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        let mut bytes = Vec::with_capacity(24);
+        BinaryCodec.encode(&self.len, &mut bytes);
+        BinaryCodec.encode(&self.bytes, &mut bytes);
+        BinaryCodec.encode(&self.checksum, &mut bytes);
+        writer.write_all(&bytes)
+    }
+}
+
+#[cfg(test)]
+mod test_codec {
+    use super::{BinaryCodec, Decode, Encode, Header, FORMAT_VERSION};
+    use std::io::ErrorKind;
+
+    fn round_trip<T>(value: T) -> T
+        where BinaryCodec: Encode<T> + Decode<T>
+    {
+        let mut bytes = Vec::new();
+        BinaryCodec.encode(&value, &mut bytes);
+        let mut input = &bytes[..];
+        let decoded = BinaryCodec.decode(&mut input).unwrap();
+        assert!(input.is_empty());
+        decoded
+    }
+
+    #[test]
+    fn test_primitives() {
+        assert_eq!(round_trip(0xfeu8), 0xfe);
+        assert_eq!(round_trip(-12345i16), -12345);
+        assert_eq!(round_trip(!0u64), !0);
+        assert_eq!(round_trip(-1i64 << 63), -1 << 63);
+        assert_eq!(round_trip(!0usize), !0);
+        assert_eq!(round_trip(-1isize), -1);
+        assert!(round_trip(true));
+        assert_eq!(round_trip(String::from("späť")), "späť");
+        assert_eq!(round_trip(vec![1u8, 2, 3]), [1, 2, 3]);
+
+        let mut input = &[0u8, 1][..];
+        let err = Decode::<u32>::decode(&BinaryCodec, &mut input).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        let mut input = &[2u8][..];
+        assert!(Decode::<bool>::decode(&BinaryCodec, &mut input).is_err());
+    }
+
+    #[test]
+    fn test_header() {
+        let header = Header {
+            version: FORMAT_VERSION,
+            hasher_id: 1,
+            capacity: 2,
+            len: 3,
+            chunks: 4,
+        };
+        let mut bytes = Vec::new();
+        header.write_to(&mut bytes).unwrap();
+        assert_eq!(Header::read_from(&mut &bytes[..]).unwrap(), header);
+
+        bytes[0] = b'X';
+        let err = Header::read_from(&mut &bytes[..]).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+}
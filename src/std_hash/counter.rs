@@ -0,0 +1,575 @@
+use std::borrow::Borrow;
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::{Hash, BuildHasher};
+use std::iter::FromIterator;
+use std::ops::{Add, BitOr, BitAnd, Sub};
+
+use super::map::{self, HashMap, RandomState};
+use super::top_k::TopK;
+
+/// A hash multiset, counting how many times each value was added, implemented
+/// as a `HashMap` from the values to their counts.
+///
+/// Only values with a nonzero count are stored, so [`len`] is the number of
+/// distinct values, while [`total`] is the sum of all counts.
+///
+/// # Examples
+///
+/// ```
+/// use rayon_hash::HashCounter;
+///
+/// let text = "the quick brown fox jumps over the lazy dog the end";
+/// let words: HashCounter<&str> = text.split_whitespace().collect();
+///
+/// assert_eq!(words.get("the"), 3);
+/// assert_eq!(words.get("fox"), 1);
+/// assert_eq!(words.get("cat"), 0);
+/// assert_eq!(words.most_common(1), [(&"the", 3)]);
+/// ```
+///
+/// [`len`]: #method.len
+/// [`total`]: #method.total
+#[derive(Clone)]
+pub struct HashCounter<T, S = RandomState> {
+    pub(crate) map: HashMap<T, usize, S>,
+}
+
+impl<T: Hash + Eq> HashCounter<T, RandomState> {
+    /// Creates an empty `HashCounter`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rayon_hash::HashCounter;
+    /// let counter: HashCounter<i32> = HashCounter::new();
+    /// ```
+    #[inline]
+    pub fn new() -> HashCounter<T, RandomState> {
+        HashCounter { map: HashMap::new() }
+    }
+
+    /// Creates an empty `HashCounter` with room for at least `capacity`
+    /// distinct values.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rayon_hash::HashCounter;
+    /// let counter: HashCounter<i32> = HashCounter::with_capacity(10);
+    /// assert!(counter.capacity() >= 10);
+    /// ```
+    #[inline]
+    pub fn with_capacity(capacity: usize) -> HashCounter<T, RandomState> {
+        HashCounter { map: HashMap::with_capacity(capacity) }
+    }
+}
+
+impl<T, S> HashCounter<T, S> {
+    /// Returns the number of distinct values the counter can hold without
+    /// reallocating.
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.map.capacity()
+    }
+
+    /// An iterator visiting all distinct values and their counts in
+    /// arbitrary order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rayon_hash::HashCounter;
+    ///
+    /// let counter: HashCounter<_> = vec!['a', 'b', 'a'].into_iter().collect();
+    /// for (value, count) in counter.iter() {
+    ///     println!("{}: {}", value, count);
+    /// }
+    /// ```
+    #[inline]
+    pub fn iter(&self) -> map::Iter<'_, T, usize> {
+        self.map.iter()
+    }
+
+    /// Returns the number of distinct values in the counter.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Returns `true` if nothing has been counted.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Returns the sum of all counts.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rayon_hash::HashCounter;
+    ///
+    /// let counter: HashCounter<_> = vec!['a', 'b', 'a'].into_iter().collect();
+    /// assert_eq!(counter.len(), 2);
+    /// assert_eq!(counter.total(), 3);
+    /// ```
+    pub fn total(&self) -> usize {
+        self.map.values().sum()
+    }
+
+    /// Clears the counter, removing all values.  Keeps the allocated memory
+    /// for reuse.
+    #[inline]
+    pub fn clear(&mut self) {
+        self.map.clear()
+    }
+
+    /// Returns a reference to the underlying map of counts.
+    #[inline]
+    pub fn as_map(&self) -> &HashMap<T, usize, S> {
+        &self.map
+    }
+
+    /// Consumes the counter, returning the underlying map of counts.
+    #[inline]
+    pub fn into_map(self) -> HashMap<T, usize, S> {
+        self.map
+    }
+
+    /// Returns the `k` most common values and their counts, in order from
+    /// the most common.  Values with equal counts are ordered arbitrarily.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rayon_hash::HashCounter;
+    ///
+    /// let counter: HashCounter<_> = "abracadabra".chars().collect();
+    /// assert_eq!(counter.most_common(1), [(&'a', 5)]);
+    ///
+    /// // 'b' and 'r' are tied for second place.
+    /// let top = counter.most_common(3);
+    /// assert_eq!(top[1].1, 2);
+    /// assert_eq!(top[2].1, 2);
+    /// assert_eq!(counter.most_common(10).len(), 5);
+    /// ```
+    pub fn most_common(&self, k: usize) -> Vec<(&T, usize)> {
+        let mut top = TopK::new(k, &by_count);
+        for (value, &count) in self.iter() {
+            top.push((value, count));
+        }
+        top.into_vec()
+    }
+}
+
+impl<T, S> HashCounter<T, S>
+    where T: Eq + Hash,
+          S: BuildHasher
+{
+    /// Creates an empty `HashCounter` which will use the given hash builder
+    /// to hash values.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rayon_hash::HashCounter;
+    /// use rayon_hash::hash_map::RandomState;
+    ///
+    /// let s = RandomState::new();
+    /// let mut counter = HashCounter::with_hasher(s);
+    /// counter.add(2);
+    /// ```
+    #[inline]
+    pub fn with_hasher(hasher: S) -> HashCounter<T, S> {
+        HashCounter { map: HashMap::with_hasher(hasher) }
+    }
+
+    /// Creates an empty `HashCounter` with room for at least `capacity`
+    /// distinct values, using `hasher` to hash them.
+    #[inline]
+    pub fn with_capacity_and_hasher(capacity: usize, hasher: S) -> HashCounter<T, S> {
+        HashCounter { map: HashMap::with_capacity_and_hasher(capacity, hasher) }
+    }
+
+    /// Returns a reference to the counter's `BuildHasher`.
+    #[inline]
+    pub fn hasher(&self) -> &S {
+        self.map.hasher()
+    }
+
+    /// Reserves capacity for at least `additional` more distinct values.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the new allocation size overflows `usize`.
+    #[inline]
+    pub fn reserve(&mut self, additional: usize) {
+        self.map.reserve(additional)
+    }
+
+    /// Shrinks the capacity of the counter as much as possible.
+    #[inline]
+    pub fn shrink_to_fit(&mut self) {
+        self.map.shrink_to_fit()
+    }
+
+    /// Returns the count of a value, which is zero if it was never added.
+    ///
+    /// The value may be any borrowed form of the counter's value type, but
+    /// [`Hash`] and [`Eq`] on the borrowed form *must* match those for
+    /// the value type.
+    ///
+    /// [`Eq`]: ../../std/cmp/trait.Eq.html
+    /// [`Hash`]: ../../std/hash/trait.Hash.html
+    #[inline]
+    pub fn get<Q>(&self, value: &Q) -> usize
+        where T: Borrow<Q>,
+              Q: ?Sized + Hash + Eq
+    {
+        self.map.get(value).cloned().unwrap_or(0)
+    }
+
+    /// Adds one occurrence of `value`, and returns its new count.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rayon_hash::HashCounter;
+    ///
+    /// let mut counter = HashCounter::new();
+    /// assert_eq!(counter.add("x"), 1);
+    /// assert_eq!(counter.add("x"), 2);
+    /// ```
+    #[inline]
+    pub fn add(&mut self, value: T) -> usize {
+        self.add_n(value, 1)
+    }
+
+    /// Adds `n` occurrences of `value`, and returns its new count.  Adding
+    /// zero occurrences of a value that isn't counted yet doesn't store it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rayon_hash::HashCounter;
+    ///
+    /// let mut counter = HashCounter::new();
+    /// assert_eq!(counter.add_n("x", 3), 3);
+    /// assert_eq!(counter.add_n("y", 0), 0);
+    /// assert_eq!(counter.len(), 1);
+    /// ```
+    pub fn add_n(&mut self, value: T, n: usize) -> usize {
+        if n == 0 {
+            return self.get(&value);
+        }
+        let count = self.map.entry(value).or_insert(0);
+        *count += n;
+        *count
+    }
+
+    /// Removes a value entirely, returning the count it had.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rayon_hash::HashCounter;
+    ///
+    /// let mut counter: HashCounter<_> = vec![1, 1, 2].into_iter().collect();
+    /// assert_eq!(counter.remove(&1), 2);
+    /// assert_eq!(counter.remove(&1), 0);
+    /// ```
+    pub fn remove<Q>(&mut self, value: &Q) -> usize
+        where T: Borrow<Q>,
+              Q: ?Sized + Hash + Eq
+    {
+        self.map.remove(value).unwrap_or(0)
+    }
+}
+
+/// Orders values by their counts, for `TopK`.
+pub(crate) fn by_count<T>(a: &(&T, usize), b: &(&T, usize)) -> Ordering {
+    a.1.cmp(&b.1)
+}
+
+impl<T, S> PartialEq for HashCounter<T, S>
+    where T: Eq + Hash,
+          S: BuildHasher
+{
+    fn eq(&self, other: &HashCounter<T, S>) -> bool {
+        self.map == other.map
+    }
+}
+
+impl<T, S> Eq for HashCounter<T, S>
+    where T: Eq + Hash,
+          S: BuildHasher
+{
+}
+
+impl<T, S> fmt::Debug for HashCounter<T, S>
+    where T: Eq + Hash + fmt::Debug,
+          S: BuildHasher
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<T, S> Default for HashCounter<T, S>
+    where T: Eq + Hash,
+          S: BuildHasher + Default
+{
+    /// Creates an empty `HashCounter<T, S>` with the `Default` value for the hasher.
+    fn default() -> HashCounter<T, S> {
+        HashCounter { map: HashMap::default() }
+    }
+}
+
+/// Counts the values of an iterator.
+impl<T, S> FromIterator<T> for HashCounter<T, S>
+    where T: Eq + Hash,
+          S: BuildHasher + Default
+{
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> HashCounter<T, S> {
+        let mut counter = HashCounter::default();
+        counter.extend(iter);
+        counter
+    }
+}
+
+/// Adds one occurrence of each value of an iterator.
+impl<T, S> Extend<T> for HashCounter<T, S>
+    where T: Eq + Hash,
+          S: BuildHasher
+{
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for value in iter {
+            self.add(value);
+        }
+    }
+}
+
+/// Adds one occurrence of each copied value of an iterator.
+impl<'a, T, S> Extend<&'a T> for HashCounter<T, S>
+    where T: 'a + Eq + Hash + Copy,
+          S: BuildHasher
+{
+    fn extend<I: IntoIterator<Item = &'a T>>(&mut self, iter: I) {
+        self.extend(iter.into_iter().cloned());
+    }
+}
+
+impl<'a, T, S> IntoIterator for &'a HashCounter<T, S> {
+    type Item = (&'a T, &'a usize);
+    type IntoIter = map::Iter<'a, T, usize>;
+
+    fn into_iter(self) -> map::Iter<'a, T, usize> {
+        self.iter()
+    }
+}
+
+impl<T, S> IntoIterator for HashCounter<T, S> {
+    type Item = (T, usize);
+    type IntoIter = map::IntoIter<T, usize>;
+
+    fn into_iter(self) -> map::IntoIter<T, usize> {
+        self.map.into_iter()
+    }
+}
+
+impl<T, S> Add<&HashCounter<T, S>> for &HashCounter<T, S>
+    where T: Eq + Hash + Clone,
+          S: BuildHasher + Default
+{
+    type Output = HashCounter<T, S>;
+
+    /// Returns a new `HashCounter` with the sums of both counts.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rayon_hash::HashCounter;
+    ///
+    /// let a: HashCounter<_> = vec![1, 1, 2].into_iter().collect();
+    /// let b: HashCounter<_> = vec![1, 3].into_iter().collect();
+    ///
+    /// let c = &a + &b;
+    /// assert_eq!(c.get(&1), 3);
+    /// assert_eq!(c.get(&2), 1);
+    /// assert_eq!(c.get(&3), 1);
+    /// ```
+    fn add(self, rhs: &HashCounter<T, S>) -> HashCounter<T, S> {
+        let mut counter = self.clone_with_default_hasher();
+        for (value, &count) in rhs {
+            counter.add_n(value.clone(), count);
+        }
+        counter
+    }
+}
+
+impl<T, S> Sub<&HashCounter<T, S>> for &HashCounter<T, S>
+    where T: Eq + Hash + Clone,
+          S: BuildHasher + Default
+{
+    type Output = HashCounter<T, S>;
+
+    /// Returns a new `HashCounter` with the counts of `rhs` subtracted from
+    /// those of `self`, keeping only the values whose count stays positive.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rayon_hash::HashCounter;
+    ///
+    /// let a: HashCounter<_> = vec![1, 1, 2].into_iter().collect();
+    /// let b: HashCounter<_> = vec![1, 2, 2, 3].into_iter().collect();
+    ///
+    /// let c = &a - &b;
+    /// assert_eq!(c.get(&1), 1);
+    /// assert_eq!(c.get(&2), 0);
+    /// assert_eq!(c.len(), 1);
+    /// ```
+    fn sub(self, rhs: &HashCounter<T, S>) -> HashCounter<T, S> {
+        self.filter_counts(|value, count| count.saturating_sub(rhs.get(value)))
+    }
+}
+
+impl<T, S> BitAnd<&HashCounter<T, S>> for &HashCounter<T, S>
+    where T: Eq + Hash + Clone,
+          S: BuildHasher + Default
+{
+    type Output = HashCounter<T, S>;
+
+    /// Returns the intersection of `self` and `rhs` as a new `HashCounter`,
+    /// with the smaller of both counts.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rayon_hash::HashCounter;
+    ///
+    /// let a: HashCounter<_> = vec![1, 1, 2].into_iter().collect();
+    /// let b: HashCounter<_> = vec![1, 2, 2, 3].into_iter().collect();
+    ///
+    /// let c = &a & &b;
+    /// assert_eq!(c.get(&1), 1);
+    /// assert_eq!(c.get(&2), 1);
+    /// assert_eq!(c.len(), 2);
+    /// ```
+    fn bitand(self, rhs: &HashCounter<T, S>) -> HashCounter<T, S> {
+        self.filter_counts(|value, count| count.min(rhs.get(value)))
+    }
+}
+
+impl<T, S> BitOr<&HashCounter<T, S>> for &HashCounter<T, S>
+    where T: Eq + Hash + Clone,
+          S: BuildHasher + Default
+{
+    type Output = HashCounter<T, S>;
+
+    /// Returns the union of `self` and `rhs` as a new `HashCounter`, with
+    /// the larger of both counts.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rayon_hash::HashCounter;
+    ///
+    /// let a: HashCounter<_> = vec![1, 1, 2].into_iter().collect();
+    /// let b: HashCounter<_> = vec![1, 2, 2, 3].into_iter().collect();
+    ///
+    /// let c = &a | &b;
+    /// assert_eq!(c.get(&1), 2);
+    /// assert_eq!(c.get(&2), 2);
+    /// assert_eq!(c.get(&3), 1);
+    /// ```
+    fn bitor(self, rhs: &HashCounter<T, S>) -> HashCounter<T, S> {
+        let mut counter = self.clone_with_default_hasher();
+        for (value, &count) in rhs {
+            let entry = counter.map.entry(value.clone()).or_insert(0);
+            *entry = (*entry).max(count);
+        }
+        counter
+    }
+}
+
+impl<T, S> HashCounter<T, S>
+    where T: Eq + Hash + Clone,
+          S: BuildHasher + Default
+{
+    fn clone_with_default_hasher(&self) -> HashCounter<T, S> {
+        self.filter_counts(|_, count| count)
+    }
+
+    /// Copies the values into a new counter with the counts given by `f`,
+    /// leaving out the values that `f` gives a zero count.
+    fn filter_counts<F>(&self, f: F) -> HashCounter<T, S>
+        where F: Fn(&T, usize) -> usize
+    {
+        let mut counter = HashCounter::with_capacity_and_hasher(self.len(), S::default());
+        for (value, &count) in self {
+            let count = f(value, count);
+            if count > 0 {
+                counter.map.insert(value.clone(), count);
+            }
+        }
+        counter
+    }
+}
+
+#[cfg(test)]
+mod test_counter {
+    use super::HashCounter;
+
+    #[test]
+    fn test_add() {
+        let mut counter = HashCounter::new();
+        assert!(counter.is_empty());
+        assert_eq!(counter.add('a'), 1);
+        assert_eq!(counter.add_n('a', 4), 5);
+        assert_eq!(counter.add_n('b', 2), 2);
+        assert_eq!(counter.add_n('c', 0), 0);
+        assert_eq!(counter.len(), 2);
+        assert_eq!(counter.total(), 7);
+        assert_eq!(counter.get(&'a'), 5);
+        assert_eq!(counter.get(&'c'), 0);
+        assert_eq!(counter.remove(&'a'), 5);
+        assert_eq!(counter.get(&'a'), 0);
+        assert_eq!(counter.len(), 1);
+    }
+
+    #[test]
+    fn test_most_common() {
+        let counter: HashCounter<u32> = (0..100u32)
+            .flat_map(|i| (0..i).map(move |_| i))
+            .collect();
+        assert_eq!(counter.len(), 99);
+
+        let top = counter.most_common(5);
+        assert_eq!(top, [(&99, 99), (&98, 98), (&97, 97), (&96, 96), (&95, 95)]);
+        assert!(counter.most_common(0).is_empty());
+        assert_eq!(counter.most_common(1000).len(), 99);
+    }
+
+    #[test]
+    fn test_ops() {
+        let a: HashCounter<_> = "aaabbc".chars().collect();
+        let b: HashCounter<_> = "abbbd".chars().collect();
+
+        let sum = &a + &b;
+        assert_eq!(sum.get(&'a'), 4);
+        assert_eq!(sum.get(&'b'), 5);
+        assert_eq!(sum.get(&'c'), 1);
+        assert_eq!(sum.get(&'d'), 1);
+
+        let difference = &a - &b;
+        assert_eq!(difference, "aac".chars().collect());
+
+        let intersection = &a & &b;
+        assert_eq!(intersection, "abb".chars().collect());
+
+        let union = &a | &b;
+        assert_eq!(union, "aaabbbcd".chars().collect());
+    }
+}
@@ -0,0 +1,463 @@
+use std::fmt;
+use std::iter::Rev;
+use std::ops::Range;
+
+use super::map::{pop_internal, HashMap};
+use super::table::{Bucket, RawTable};
+use super::table::BucketState::{Empty, Full};
+
+/// Returns the first full bucket of those in `indices`.
+fn find_full<K, V, I>(table: &RawTable<K, V>, mut indices: I) -> Option<usize>
+    where I: Iterator<Item = usize>
+{
+    indices.find(|&index| match Bucket::at_index(table, index).peek() {
+        Full(_) => true,
+        Empty(_) => false,
+    })
+}
+
+/// Returns whether all the buckets in `indices` hold displaced entries,
+/// which removing an entry just before them shifts back by one bucket.
+fn all_displaced<K, V, I>(table: &RawTable<K, V>, mut indices: I) -> bool
+    where I: Iterator<Item = usize>
+{
+    indices.all(|index| match Bucket::at_index(table, index).peek() {
+        Full(bucket) => bucket.displacement() != 0,
+        Empty(_) => false,
+    })
+}
+
+/// The buckets after `index` in order, up to `capacity`, which is also
+/// the ghost position.
+fn after(index: usize, capacity: usize) -> Range<usize> {
+    if index < capacity { index + 1..capacity } else { 0..capacity }
+}
+
+/// The buckets before `index` in reverse order, which for the ghost
+/// position is all of them.
+fn before(index: usize) -> Rev<Range<usize>> {
+    (0..index).rev()
+}
+
+impl<K, V, S> HashMap<K, V, S> {
+    /// Returns the number of buckets in the table, which is the range of
+    /// a cursor's bucket indices.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rayon_hash::HashMap;
+    ///
+    /// let map: HashMap<i32, i32> = HashMap::with_capacity(100);
+    /// assert!(map.bucket_count() >= 100);
+    /// assert!(map.bucket_count().is_power_of_two());
+    /// ```
+    #[inline]
+    pub fn bucket_count(&self) -> usize {
+        self.table.capacity()
+    }
+
+    /// Returns a cursor over the buckets of the table, which starts at the
+    /// "ghost" position before the first bucket and after the last one.
+    ///
+    /// Unlike an iterator, a cursor's position is a bucket index, which
+    /// can be saved and restored with [`seek`], so a scan can be paused and
+    /// resumed later, even by a new cursor.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rayon_hash::HashMap;
+    ///
+    /// let map: HashMap<i32, i32> = (0..10).map(|i| (i, i)).collect();
+    /// let mut cursor = map.cursor();
+    /// let first = cursor.next_occupied().unwrap();
+    /// let saved = cursor.index().unwrap();
+    ///
+    /// let mut cursor = map.cursor();
+    /// cursor.seek(saved);
+    /// assert_eq!(cursor.current(), Some(first));
+    /// let mut rest = 0;
+    /// while let Some(_) = cursor.next_occupied() {
+    ///     rest += 1;
+    /// }
+    /// assert_eq!(rest, 9);
+    /// ```
+    ///
+    /// [`seek`]: struct.Cursor.html#method.seek
+    pub fn cursor(&self) -> Cursor<'_, K, V, S> {
+        Cursor { index: self.bucket_count(), map: self }
+    }
+
+    /// Returns a cursor over the buckets of the table, which can modify
+    /// values and remove entries, starting at the ghost position.  See
+    /// [`cursor`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rayon_hash::HashMap;
+    ///
+    /// let mut map: HashMap<i32, i32> = (0..10).map(|i| (i, i)).collect();
+    /// let mut cursor = map.cursor_mut();
+    /// while let Some((&key, _)) = cursor.next_occupied() {
+    ///     if key % 2 == 0 {
+    ///         cursor.remove_current();
+    ///     } else if let Some(value) = cursor.value_mut() {
+    ///         *value *= 10;
+    ///     }
+    /// }
+    /// assert_eq!(map.len(), 5);
+    /// assert_eq!(map[&3], 30);
+    /// ```
+    ///
+    /// [`cursor`]: #method.cursor
+    pub fn cursor_mut(&mut self) -> CursorMut<'_, K, V, S> {
+        let capacity = self.bucket_count();
+        CursorMut { index: capacity, start: 0, end: capacity, map: self }
+    }
+}
+
+/// A cursor over the buckets of a `HashMap`.
+///
+/// This `struct` is created by the [`cursor`] method on [`HashMap`].
+///
+/// [`cursor`]: struct.HashMap.html#method.cursor
+/// [`HashMap`]: struct.HashMap.html
+pub struct Cursor<'a, K: 'a, V: 'a, S: 'a> {
+    map: &'a HashMap<K, V, S>,
+    // `bucket_count()` is the ghost position.
+    index: usize,
+}
+
+impl<K, V, S> Clone for Cursor<'_, K, V, S> {
+    fn clone(&self) -> Self {
+        Cursor { ..*self }
+    }
+}
+
+impl<'a, K, V, S> Cursor<'a, K, V, S> {
+    /// Returns the index of the current bucket, or `None` at the ghost
+    /// position.
+    #[inline]
+    pub fn index(&self) -> Option<usize> {
+        if self.index < self.map.bucket_count() { Some(self.index) } else { None }
+    }
+
+    /// Moves to the bucket at `index`, which may be empty, or to the ghost
+    /// position if `index` is out of bounds.
+    #[inline]
+    pub fn seek(&mut self, index: usize) {
+        self.index = index.min(self.map.bucket_count());
+    }
+
+    /// Returns the entry in the current bucket, or `None` if it's empty or
+    /// at the ghost position.
+    pub fn current(&self) -> Option<(&'a K, &'a V)> {
+        self.index()?;
+        match Bucket::at_index(&self.map.table, self.index).peek() {
+            Full(bucket) => Some(bucket.into_refs()),
+            Empty(_) => None,
+        }
+    }
+
+    /// Moves to the next full bucket and returns its entry.  At the end of
+    /// the table, moves to the ghost position and returns `None`, and from
+    /// there starts again at the first bucket.
+    pub fn next_occupied(&mut self) -> Option<(&'a K, &'a V)> {
+        let capacity = self.map.bucket_count();
+        self.index = find_full(&self.map.table, after(self.index, capacity)).unwrap_or(capacity);
+        self.current()
+    }
+
+    /// Moves to the previous full bucket and returns its entry.  At the
+    /// start of the table, moves to the ghost position and returns `None`,
+    /// and from there starts again at the last bucket.
+    pub fn prev_occupied(&mut self) -> Option<(&'a K, &'a V)> {
+        let capacity = self.map.bucket_count();
+        self.index = find_full(&self.map.table, before(self.index)).unwrap_or(capacity);
+        self.current()
+    }
+}
+
+impl<K: fmt::Debug, V: fmt::Debug, S> fmt::Debug for Cursor<'_, K, V, S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("Cursor").field(&self.index()).field(&self.current()).finish()
+    }
+}
+
+/// A cursor over the buckets of a `HashMap`, which can modify values and
+/// remove entries.
+///
+/// This `struct` is created by the [`cursor_mut`] method on [`HashMap`].
+///
+/// [`cursor_mut`]: struct.HashMap.html#method.cursor_mut
+/// [`HashMap`]: struct.HashMap.html
+pub struct CursorMut<'a, K: 'a, V: 'a, S: 'a> {
+    map: &'a mut HashMap<K, V, S>,
+    // `bucket_count()` is the ghost position.
+    index: usize,
+    // The bucket where the current forward scan started, which moves back
+    // with its entry when removals shift it.
+    start: usize,
+    // The scan stops before `end`, because the entries from there on have
+    // been shifted around from the start of the table and were visited.
+    end: usize,
+}
+
+impl<'a, K, V, S> CursorMut<'a, K, V, S> {
+    /// Returns the index of the current bucket, or `None` at the ghost
+    /// position.
+    #[inline]
+    pub fn index(&self) -> Option<usize> {
+        if self.index < self.map.bucket_count() { Some(self.index) } else { None }
+    }
+
+    /// Moves to the bucket at `index`, which may be empty, or to the ghost
+    /// position if `index` is out of bounds.
+    #[inline]
+    pub fn seek(&mut self, index: usize) {
+        self.index = index.min(self.map.bucket_count());
+        self.restart();
+    }
+
+    /// Starts a new forward scan at the current bucket.
+    fn restart(&mut self) {
+        let capacity = self.map.bucket_count();
+        self.start = if self.index < capacity { self.index } else { 0 };
+        self.end = capacity;
+    }
+
+    /// Returns the entry in the current bucket, with its value mutable, or
+    /// `None` if it's empty or at the ghost position.
+    pub fn current(&mut self) -> Option<(&K, &mut V)> {
+        self.index()?;
+        match Bucket::at_index(&mut self.map.table, self.index).peek() {
+            Full(bucket) => {
+                let (key, value) = bucket.into_mut_refs();
+                Some((&*key, value))
+            }
+            Empty(_) => None,
+        }
+    }
+
+    /// Returns the value in the current bucket, or `None` if it's empty or
+    /// at the ghost position.
+    ///
+    /// After `remove_current`, the current bucket is the one before the
+    /// removed entry, so this doesn't return the value that moved into its
+    /// place until `next_occupied` moves to it.
+    #[inline]
+    pub fn value_mut(&mut self) -> Option<&mut V> {
+        self.current().map(|(_, value)| value)
+    }
+
+    /// Moves to the next full bucket and returns its entry.  At the end of
+    /// the table, moves to the ghost position and returns `None`, and from
+    /// there starts a new scan at the first bucket.
+    ///
+    /// A scan visits each entry once, even if removing entries shifts some
+    /// from the start of the table around to its end: the scan then ends
+    /// before those it has already visited.
+    pub fn next_occupied(&mut self) -> Option<(&K, &mut V)> {
+        let capacity = self.map.bucket_count();
+        if self.index == capacity {
+            self.restart();
+        }
+        self.index = find_full(&self.map.table, after(self.index, self.end)).unwrap_or(capacity);
+        self.current()
+    }
+
+    /// Moves to the previous full bucket and returns its entry.  At the
+    /// start of the table, moves to the ghost position and returns `None`.
+    pub fn prev_occupied(&mut self) -> Option<(&K, &mut V)> {
+        let capacity = self.map.bucket_count();
+        self.index = find_full(&self.map.table, before(self.index)).unwrap_or(capacity);
+        self.restart();
+        self.current()
+    }
+
+    /// Removes the entry in the current bucket and returns it, or `None` if
+    /// the bucket is empty or at the ghost position.
+    ///
+    /// Removing an entry shifts the entries after it that were displaced
+    /// back by one bucket, so the cursor also moves back one bucket, and
+    /// `next_occupied` then finds the entry that took its place.  Scanning
+    /// forward while removing entries visits every remaining entry once.
+    pub fn remove_current(&mut self) -> Option<(K, V)> {
+        self.index()?;
+        let capacity = self.map.bucket_count();
+        match Bucket::at_index(&mut self.map.table, self.index).peek() {
+            Full(bucket) => {
+                let table = bucket.table();
+                if all_displaced(table, self.index + 1..=capacity) {
+                    // The entry in the first bucket wraps around to the
+                    // last one.  If the scan started there, it was visited,
+                    // otherwise the scan's start may be shifted back too.
+                    if self.start == 0 {
+                        self.end -= 1;
+                    } else if all_displaced(table, 0..=self.start) {
+                        self.start -= 1;
+                    }
+                }
+                let (key, value, _) = pop_internal(bucket);
+                self.index = self.index.checked_sub(1).unwrap_or_else(|| self.map.bucket_count());
+                Some((key, value))
+            }
+            Empty(_) => None,
+        }
+    }
+}
+
+impl<K: fmt::Debug, V: fmt::Debug, S> fmt::Debug for CursorMut<'_, K, V, S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let current = self.index().and_then(|index| match Bucket::at_index(&self.map.table, index).peek() {
+            Full(bucket) => Some(bucket.into_refs()),
+            Empty(_) => None,
+        });
+        f.debug_tuple("CursorMut").field(&self.index()).field(&current).finish()
+    }
+}
+
+#[cfg(test)]
+mod test_cursor {
+    use super::super::map::HashMap;
+    use super::super::set::HashSet;
+    use std::hash::{BuildHasherDefault, Hasher};
+
+    /// Hashes a `u64` key to itself, so keys pick their buckets.
+    #[derive(Default)]
+    struct Identity(u64);
+
+    impl Hasher for Identity {
+        fn finish(&self) -> u64 {
+            self.0
+        }
+
+        fn write(&mut self, bytes: &[u8]) {
+            for &byte in bytes {
+                self.0 = self.0 << 8 | u64::from(byte);
+            }
+        }
+
+        fn write_u64(&mut self, n: u64) {
+            self.0 = n;
+        }
+    }
+
+    #[test]
+    fn test_walk() {
+        let empty: HashMap<i32, i32> = HashMap::new();
+        assert_eq!(empty.bucket_count(), 0);
+        let mut cursor = empty.cursor();
+        assert_eq!(cursor.next_occupied(), None);
+        assert_eq!(cursor.prev_occupied(), None);
+        assert_eq!(cursor.index(), None);
+
+        let map: HashMap<i32, i32> = (0..1000).map(|i| (i, -i)).collect();
+        let mut forward = Vec::new();
+        let mut cursor = map.cursor();
+        while let Some((&k, &v)) = cursor.next_occupied() {
+            assert_eq!(v, -k);
+            assert_eq!(cursor.current(), Some((&k, &v)));
+            forward.push(k);
+        }
+        assert_eq!(cursor.index(), None);
+        assert_eq!(forward, map.keys().cloned().collect::<Vec<_>>());
+
+        let mut backward = Vec::new();
+        while let Some((&k, _)) = cursor.prev_occupied() {
+            backward.push(k);
+        }
+        backward.reverse();
+        assert_eq!(backward, forward);
+
+        // Resuming from a saved index.
+        let mut cursor = map.cursor();
+        for _ in 0..300 {
+            cursor.next_occupied();
+        }
+        let saved = cursor.index().unwrap();
+        let mut cursor = map.cursor();
+        cursor.seek(saved);
+        let mut rest = 0;
+        while cursor.next_occupied().is_some() {
+            rest += 1;
+        }
+        assert_eq!(rest, 700);
+    }
+
+    #[test]
+    fn test_remove() {
+        let mut map: HashMap<i32, i32> = (0..5000).map(|i| (i, i)).collect();
+        let mut seen = HashSet::new();
+        let mut removed = 0;
+        {
+            let mut cursor = map.cursor_mut();
+            assert_eq!(cursor.remove_current(), None);
+            while let Some((&k, _)) = cursor.next_occupied() {
+                seen.insert(k);
+                if k % 3 == 0 {
+                    assert_eq!(cursor.remove_current(), Some((k, k)));
+                    removed += 1;
+                } else {
+                    *cursor.value_mut().unwrap() += 1;
+                }
+            }
+        }
+        assert_eq!(seen.len(), 5000);
+        assert_eq!(removed, 1667);
+        assert_eq!(map.len(), 5000 - 1667);
+        for i in 0..5000 {
+            match map.get(&i) {
+                Some(&v) => assert!(i % 3 != 0 && v == i + 1),
+                None => assert_eq!(i % 3, 0),
+            }
+        }
+    }
+
+    #[test]
+    fn test_remove_wrapped() {
+        let mut map: HashMap<u64, u32, BuildHasherDefault<Identity>> = HashMap::default();
+        map.reserve(16);
+        let capacity = map.bucket_count() as u64;
+        // Four keys for the last bucket, which wrap around to the first
+        // three, and a few others.
+        let keys: Vec<u64> = (0..4).map(|i| capacity - 1 + i * capacity).chain(5..10).collect();
+        for &k in &keys {
+            map.insert(k, 0);
+        }
+
+        // Removing the entry in the last bucket moves the one from the first
+        // bucket there, which a scan from the start has already visited.
+        {
+            let mut cursor = map.cursor_mut();
+            while let Some((&k, value)) = cursor.next_occupied() {
+                *value += 1;
+                if k >= capacity - 1 {
+                    cursor.remove_current();
+                }
+            }
+        }
+        assert_eq!(map.len(), 5);
+        assert!(map.values().all(|&v| v == 1), "{:?}", map);
+
+        // A scan from the second bucket hasn't visited the first one yet.
+        for &k in &keys {
+            map.insert(k, 0);
+        }
+        {
+            let mut cursor = map.cursor_mut();
+            cursor.seek(1);
+            *cursor.value_mut().unwrap() += 1;
+            while let Some((&k, value)) = cursor.next_occupied() {
+                *value += 1;
+                if k >= capacity - 1 {
+                    cursor.remove_current();
+                }
+            }
+        }
+        assert_eq!(map.len(), 6);
+        assert!(map.values().all(|&v| v == 1), "{:?}", map);
+    }
+}
@@ -0,0 +1,316 @@
+use std::hash::{BuildHasher, Hash, Hasher};
+
+use super::map::HashMap;
+use super::set::HashSet;
+use super::snapshot::DeterministicState;
+
+/// A `BuildHasher` with a fixed seed, so maps built with it hash the same
+/// way in every run.
+///
+/// Unlike with `RandomState`, two maps with the same seed that see the same
+/// sequence of inserts and removals have identical tables, and so iterate in
+/// the same order, which makes output reproducible.  The hashers are SipHash
+/// 1-3, like `DefaultHasher`, keyed with the seed and its complement.
+///
+/// Warning: a fixed seed gives up the protection of random keys against
+/// inputs chosen to collide, so it should not be used for untrusted keys.
+///
+/// # Examples
+///
+/// ```
+/// use rayon_hash::hash_map::FixedState;
+/// use rayon_hash::HashMap;
+///
+/// let mut a = HashMap::with_seed(42);
+/// let mut b = HashMap::with_hasher(FixedState(42));
+/// for i in 0..100 {
+///     a.insert(i, i * 2);
+///     b.insert(i, i * 2);
+/// }
+/// assert!(a.iter().eq(b.iter()));
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct FixedState(pub u64);
+
+impl BuildHasher for FixedState {
+    type Hasher = FixedHasher;
+
+    #[inline]
+    fn build_hasher(&self) -> FixedHasher {
+        FixedHasher::with_keys(self.0, !self.0)
+    }
+}
+
+impl DeterministicState for FixedState {}
+
+/// SipHash 1-3 with explicit keys, the hasher of [`FixedState`].
+///
+/// Its output depends only on the keys and the bytes written, though
+/// integers are written in native byte order, as by `DefaultHasher`.
+///
+/// [`FixedState`]: struct.FixedState.html
+#[derive(Clone, Debug)]
+pub struct FixedHasher {
+    v0: u64,
+    v1: u64,
+    v2: u64,
+    v3: u64,
+    // Bytes not yet compressed, in the low `ntail` bytes.
+    tail: u64,
+    ntail: usize,
+    length: usize,
+}
+
+macro_rules! sip_round {
+    ($s:expr) => {{
+        $s.v0 = $s.v0.wrapping_add($s.v1);
+        $s.v1 = $s.v1.rotate_left(13) ^ $s.v0;
+        $s.v0 = $s.v0.rotate_left(32);
+        $s.v2 = $s.v2.wrapping_add($s.v3);
+        $s.v3 = $s.v3.rotate_left(16) ^ $s.v2;
+        $s.v0 = $s.v0.wrapping_add($s.v3);
+        $s.v3 = $s.v3.rotate_left(21) ^ $s.v0;
+        $s.v2 = $s.v2.wrapping_add($s.v1);
+        $s.v1 = $s.v1.rotate_left(17) ^ $s.v2;
+        $s.v2 = $s.v2.rotate_left(32);
+    }};
+}
+
+impl FixedHasher {
+    /// Creates a hasher with the keys `k0` and `k1`.
+    #[inline]
+    pub fn with_keys(k0: u64, k1: u64) -> FixedHasher {
+        FixedHasher {
+            v0: k0 ^ 0x736f_6d65_7073_6575,
+            v1: k1 ^ 0x646f_7261_6e64_6f6d,
+            v2: k0 ^ 0x6c79_6765_6e65_7261,
+            v3: k1 ^ 0x7465_6462_7974_6573,
+            tail: 0,
+            ntail: 0,
+            length: 0,
+        }
+    }
+
+    #[inline]
+    fn compress(&mut self, m: u64) {
+        self.v3 ^= m;
+        sip_round!(self);
+        self.v0 ^= m;
+    }
+}
+
+/// Reads up to eight bytes as a little-endian integer.
+#[inline]
+fn load_le(bytes: &[u8]) -> u64 {
+    bytes.iter().rev().fold(0, |word, &byte| word << 8 | u64::from(byte))
+}
+
+impl Hasher for FixedHasher {
+    fn write(&mut self, mut bytes: &[u8]) {
+        self.length = self.length.wrapping_add(bytes.len());
+        if self.ntail != 0 {
+            let fill = (8 - self.ntail).min(bytes.len());
+            self.tail |= load_le(&bytes[..fill]) << (8 * self.ntail);
+            self.ntail += fill;
+            bytes = &bytes[fill..];
+            if self.ntail < 8 {
+                return;
+            }
+            let m = self.tail;
+            self.compress(m);
+            self.tail = 0;
+            self.ntail = 0;
+        }
+        let mut words = bytes.chunks_exact(8);
+        for word in &mut words {
+            self.compress(load_le(word));
+        }
+        let rest = words.remainder();
+        self.tail = load_le(rest);
+        self.ntail = rest.len();
+    }
+
+    fn finish(&self) -> u64 {
+        let mut state = self.clone();
+        let b = (self.length as u64 & 0xff) << 56 | self.tail;
+        state.compress(b);
+        state.v2 ^= 0xff;
+        for _ in 0..3 {
+            sip_round!(state);
+        }
+        state.v0 ^ state.v1 ^ state.v2 ^ state.v3
+    }
+}
+
+impl<K: Hash + Eq, V> HashMap<K, V, FixedState> {
+    /// Creates an empty `HashMap` hashing with [`FixedState`]`(seed)`, so
+    /// its layout and iteration order only depend on the seed and the
+    /// operations on the map.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rayon_hash::HashMap;
+    ///
+    /// let mut a = HashMap::with_seed(7);
+    /// let mut b = HashMap::with_seed(7);
+    /// a.extend((0..10).map(|i| (i, ())));
+    /// b.extend((0..10).map(|i| (i, ())));
+    /// assert!(a.keys().eq(b.keys()));
+    /// ```
+    ///
+    /// [`FixedState`]: struct.FixedState.html
+    #[inline]
+    pub fn with_seed(seed: u64) -> HashMap<K, V, FixedState> {
+        HashMap::with_hasher(FixedState(seed))
+    }
+}
+
+impl<T: Hash + Eq> HashSet<T, FixedState> {
+    /// Creates an empty `HashSet` hashing with [`FixedState`]`(seed)`, so
+    /// its layout and iteration order only depend on the seed and the
+    /// operations on the set.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rayon_hash::HashSet;
+    ///
+    /// let mut a = HashSet::with_seed(7);
+    /// let mut b = HashSet::with_seed(7);
+    /// a.extend(0..10);
+    /// b.extend(0..10);
+    /// assert!(a.iter().eq(b.iter()));
+    /// ```
+    ///
+    /// [`FixedState`]: ../hash_map/struct.FixedState.html
+    #[inline]
+    pub fn with_seed(seed: u64) -> HashSet<T, FixedState> {
+        HashSet::with_hasher(FixedState(seed))
+    }
+}
+
+#[cfg(test)]
+mod test_fixed {
+    use super::super::map::HashMap;
+    use super::{FixedHasher, FixedState};
+    use std::hash::{BuildHasher, Hash, Hasher};
+
+    fn hash<T: Hash>(hasher: &mut impl Hasher, value: T) -> u64 {
+        value.hash(hasher);
+        hasher.finish()
+    }
+
+    /// The reference vectors for SipHash-1-3, as in the tests of Rust's own
+    /// `SipHasher13`: the hashes of the messages `[]`, `[0]`, `[0, 1]`, and
+    /// so on, with the key `[0, 1, ..., 15]`, as little-endian bytes.
+    const VECTORS: [[u8; 8]; 64] = [
+        [0xdc, 0xc4, 0x0f, 0x05, 0x58, 0x01, 0xac, 0xab],
+        [0x93, 0xca, 0x57, 0x7d, 0xf3, 0x9b, 0xf4, 0xc9],
+        [0x4d, 0xd4, 0xc7, 0x4d, 0x02, 0x9b, 0xcb, 0x82],
+        [0xfb, 0xf7, 0xdd, 0xe7, 0xb8, 0x0a, 0xf8, 0x8b],
+        [0x28, 0x83, 0xd3, 0x88, 0x60, 0x57, 0x75, 0xcf],
+        [0x67, 0x3b, 0x53, 0x49, 0x2f, 0xd5, 0xf9, 0xde],
+        [0xa7, 0x22, 0x9f, 0xc5, 0x50, 0x2b, 0x0d, 0xc5],
+        [0x40, 0x11, 0xb1, 0x9b, 0x98, 0x7d, 0x92, 0xd3],
+        [0x8e, 0x9a, 0x29, 0x8d, 0x11, 0x95, 0x90, 0x36],
+        [0xe4, 0x3d, 0x06, 0x6c, 0xb3, 0x8e, 0xa4, 0x25],
+        [0x7f, 0x09, 0xff, 0x92, 0xee, 0x85, 0xde, 0x79],
+        [0x52, 0xc3, 0x4d, 0xf9, 0xc1, 0x18, 0xc1, 0x70],
+        [0xa2, 0xd9, 0xb4, 0x57, 0xb1, 0x84, 0xa3, 0x78],
+        [0xa7, 0xff, 0x29, 0x12, 0x0c, 0x76, 0x6f, 0x30],
+        [0x34, 0x5d, 0xf9, 0xc0, 0x11, 0xa1, 0x5a, 0x60],
+        [0x56, 0x99, 0x51, 0x2a, 0x6d, 0xd8, 0x20, 0xd3],
+        [0x66, 0x8b, 0x90, 0x7d, 0x1a, 0xdd, 0x4f, 0xcc],
+        [0x0c, 0xd8, 0xdb, 0x63, 0x90, 0x68, 0xf2, 0x9c],
+        [0x3e, 0xe6, 0x73, 0xb4, 0x9c, 0x38, 0xfc, 0x8f],
+        [0x1c, 0x7d, 0x29, 0x8d, 0xe5, 0x9d, 0x1f, 0xf2],
+        [0x40, 0xe0, 0xcc, 0xa6, 0x46, 0x2f, 0xdc, 0xc0],
+        [0x44, 0xf8, 0x45, 0x2b, 0xfe, 0xab, 0x92, 0xb9],
+        [0x2e, 0x87, 0x20, 0xa3, 0x9b, 0x7b, 0xfe, 0x7f],
+        [0x23, 0xc1, 0xe6, 0xda, 0x7f, 0x0e, 0x5a, 0x52],
+        [0x8c, 0x9c, 0x34, 0x67, 0xb2, 0xae, 0x64, 0xf4],
+        [0x79, 0x09, 0x5b, 0x70, 0x28, 0x59, 0xcd, 0x45],
+        [0xa5, 0x13, 0x99, 0xca, 0xe3, 0x35, 0x3e, 0x3a],
+        [0x35, 0x3b, 0xde, 0x4a, 0x4e, 0xc7, 0x1d, 0xa9],
+        [0x0d, 0xd0, 0x6c, 0xef, 0x02, 0xed, 0x0b, 0xfb],
+        [0xf4, 0xe1, 0xb1, 0x4a, 0xb4, 0x3c, 0xd9, 0x88],
+        [0x63, 0xe6, 0xc5, 0x43, 0xd6, 0x11, 0x0f, 0x54],
+        [0xbc, 0xd1, 0x21, 0x8c, 0x1f, 0xdd, 0x70, 0x23],
+        [0x0d, 0xb6, 0xa7, 0x16, 0x6c, 0x7b, 0x15, 0x81],
+        [0xbf, 0xf9, 0x8f, 0x7a, 0xe5, 0xb9, 0x54, 0x4d],
+        [0x3e, 0x75, 0x2a, 0x1f, 0x78, 0x12, 0x9f, 0x75],
+        [0x91, 0x6b, 0x18, 0xbf, 0xbe, 0xa3, 0xa1, 0xce],
+        [0x06, 0x62, 0xa2, 0xad, 0xd3, 0x08, 0xf5, 0x2c],
+        [0x57, 0x30, 0xc3, 0xa3, 0x2d, 0x1c, 0x10, 0xb6],
+        [0xa1, 0x36, 0x3a, 0xae, 0x96, 0x74, 0xf4, 0xb3],
+        [0x92, 0x83, 0x10, 0x7b, 0x54, 0x57, 0x6b, 0x62],
+        [0x31, 0x15, 0xe4, 0x99, 0x32, 0x36, 0xd2, 0xc1],
+        [0x44, 0xd9, 0x1a, 0x3f, 0x92, 0xc1, 0x7c, 0x66],
+        [0x25, 0x88, 0x13, 0xc8, 0xfe, 0x4f, 0x70, 0x65],
+        [0xa6, 0x49, 0x89, 0xc2, 0xd1, 0x80, 0xf2, 0x24],
+        [0x6b, 0x87, 0xf8, 0xfa, 0xed, 0x1c, 0xca, 0xc2],
+        [0x96, 0x21, 0x04, 0x9f, 0xfc, 0x4b, 0x16, 0xc2],
+        [0x23, 0xd6, 0xb1, 0x68, 0x93, 0x9c, 0x6e, 0xa1],
+        [0xfd, 0x14, 0x51, 0x8b, 0x9c, 0x16, 0xfb, 0x49],
+        [0x46, 0x4c, 0x07, 0xdf, 0xf8, 0x43, 0x31, 0x9f],
+        [0xb3, 0x86, 0xcc, 0x12, 0x24, 0xaf, 0xfd, 0xc6],
+        [0x8f, 0x09, 0x52, 0x0a, 0xd1, 0x49, 0xaf, 0x7e],
+        [0x9a, 0x2f, 0x29, 0x9d, 0x55, 0x13, 0xf3, 0x1c],
+        [0x12, 0x1f, 0xf4, 0xa2, 0xdd, 0x30, 0x4a, 0xc4],
+        [0xd0, 0x1e, 0xa7, 0x43, 0x89, 0xe9, 0xfa, 0x36],
+        [0xe6, 0xbc, 0xf0, 0x73, 0x4c, 0xb3, 0x8f, 0x31],
+        [0x80, 0xe9, 0xa7, 0x70, 0x36, 0xbf, 0x7a, 0xa2],
+        [0x75, 0x6d, 0x3c, 0x24, 0xdb, 0xc0, 0xbc, 0xb4],
+        [0x13, 0x15, 0xb7, 0xfd, 0x52, 0xd8, 0xf8, 0x23],
+        [0x08, 0x8a, 0x7d, 0xa6, 0x4d, 0x5f, 0x03, 0x8f],
+        [0x48, 0xf1, 0xe8, 0xb7, 0xe5, 0xd0, 0x9c, 0xd8],
+        [0xee, 0x44, 0xa6, 0xf7, 0xbc, 0xe6, 0xf4, 0xf6],
+        [0xf2, 0x37, 0x18, 0x0f, 0xd8, 0x9a, 0xc5, 0xae],
+        [0xe0, 0x94, 0x66, 0x4b, 0x15, 0xf6, 0xb2, 0xc3],
+        [0xa8, 0xb3, 0xbb, 0xb7, 0x62, 0x90, 0x19, 0x9d],
+    ];
+
+    #[test]
+    fn test_siphash() {
+        for (len, vector) in VECTORS.iter().enumerate() {
+            let bytes: Vec<u8> = (0..len as u8).collect();
+            let mut hasher = FixedHasher::with_keys(0x0706_0504_0302_0100, 0x0f0e_0d0c_0b0a_0908);
+            hasher.write(&bytes);
+            let expected = vector.iter().rev().fold(0, |hash, &b| hash << 8 | u64::from(b));
+            assert_eq!(hasher.finish(), expected, "message of {} bytes", len);
+        }
+
+        // Writes may be split anywhere.
+        let bytes: Vec<u8> = (0..100).collect();
+        let mut whole = FixedHasher::with_keys(1, 2);
+        whole.write(&bytes);
+        for split in 0..20 {
+            let mut parts = FixedHasher::with_keys(1, 2);
+            for chunk in bytes.chunks(split + 1) {
+                parts.write(chunk);
+            }
+            assert_eq!(parts.finish(), whole.finish());
+        }
+    }
+
+    #[test]
+    fn test_seeds() {
+        let s = FixedState(3);
+        assert_eq!(hash(&mut s.build_hasher(), "key"), hash(&mut FixedState(3).build_hasher(), "key"));
+        assert_ne!(hash(&mut s.build_hasher(), "key"), hash(&mut FixedState(4).build_hasher(), "key"));
+
+        let build = |seed| {
+            let mut map = HashMap::with_seed(seed);
+            for i in 0..1000 {
+                map.insert(i.to_string(), i);
+            }
+            for i in 0..100 {
+                map.remove(&(i * 7).to_string());
+            }
+            map.into_iter().collect::<Vec<_>>()
+        };
+        assert_eq!(build(1), build(1));
+        assert_ne!(build(1), build(2));
+    }
+}
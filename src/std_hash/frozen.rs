@@ -0,0 +1,419 @@
+use std::borrow::Borrow;
+use std::fmt;
+use std::hash::{Hash, BuildHasherDefault};
+use std::io;
+use std::iter::FusedIterator;
+use std::marker::PhantomData;
+use std::mem;
+#[cfg(test)]
+use std::ops::Deref;
+use std::ptr::NonNull;
+use std::slice;
+
+use super::codec::invalid_data;
+use super::map::DefaultHasher;
+use super::snapshot::{read_snapshot, DeterministicState};
+use super::table::{make_hash, RawTable};
+
+/// A read-only hash map which borrows a snapshot from
+/// [`HashMap::to_raw_bytes`], such as a memory-mapped file, in place.
+///
+/// The snapshot holds the table's arrays of hashes and pairs exactly as a
+/// `HashMap` lays them out, so lookups probe the borrowed bytes directly.
+/// Opening one only checks the header, and nothing is ever allocated or
+/// copied, however big the table is.
+///
+/// # Examples
+///
+/// ```
+/// use rayon_hash::hash_map::DefaultHasher;
+/// use rayon_hash::{FrozenHashMap, HashMap};
+/// use std::hash::BuildHasherDefault;
+///
+/// type Fixed = BuildHasherDefault<DefaultHasher>;
+///
+/// let map: HashMap<u32, u64, Fixed> = (0..100).map(|i| (i, u64::from(i) * 3)).collect();
+/// // A memory map would be aligned for the table, but a `Vec<u8>` may not
+/// // be, so `aligned` copies the snapshot into `u64`s.
+/// let bytes = aligned(&map.to_raw_bytes());
+/// # fn aligned(bytes: &[u8]) -> &'static [u8] {
+/// #     let words = Box::leak(vec![0u64; bytes.len() / 8 + 1].into_boxed_slice());
+/// #     let aligned = unsafe { std::slice::from_raw_parts_mut(words.as_mut_ptr() as *mut u8, bytes.len()) };
+/// #     aligned.copy_from_slice(bytes);
+/// #     aligned
+/// # }
+///
+/// let frozen = unsafe { FrozenHashMap::<u32, u64, _>::from_raw_bytes(bytes, Fixed::default()) }.unwrap();
+/// assert_eq!(frozen.len(), 100);
+/// assert_eq!(frozen.get(&7), Some(&21));
+/// assert!(!frozen.contains_key(&100));
+/// ```
+///
+/// [`HashMap::to_raw_bytes`]: ../hash_map/struct.HashMap.html#method.to_raw_bytes
+pub struct FrozenHashMap<'a, K: 'a, V: 'a, S = BuildHasherDefault<DefaultHasher>> {
+    hash_builder: S,
+    hashes: &'a [usize],
+    // The pairs of empty buckets may not be valid values, so this can't be
+    // a slice.
+    pairs: *const (K, V),
+    len: usize,
+    marker: PhantomData<&'a [(K, V)]>,
+}
+
+unsafe impl<'a, K: Sync, V: Sync, S: Send> Send for FrozenHashMap<'a, K, V, S> {}
+unsafe impl<'a, K: Sync, V: Sync, S: Sync> Sync for FrozenHashMap<'a, K, V, S> {}
+
+impl<'a, K, V, S> FrozenHashMap<'a, K, V, S>
+    where K: Eq + Hash + Copy,
+          V: Copy,
+          S: DeterministicState
+{
+    /// Opens a snapshot from `HashMap::to_raw_bytes` in place.
+    ///
+    /// The header is padded so that the table is aligned for `usize` and
+    /// for the pairs `(K, V)` if the bytes start at an address aligned for
+    /// them too, as memory maps are.  Returns an error of kind `InvalidData`
+    /// if the table isn't aligned, or for any of the reasons that
+    /// `HashMap::from_raw_bytes` would.  The hashes aren't checked at all.
+    ///
+    /// # Safety
+    ///
+    /// The bytes must be an unmodified snapshot of a `HashMap<K, V, _>` with
+    /// these very types.  Lookups and iteration trust the hashes to tell
+    /// which buckets are full, so a corrupt hash can make them read the pair
+    /// of an empty bucket, which isn't a valid value.
+    pub unsafe fn from_raw_bytes(bytes: &'a [u8], hash_builder: S) -> io::Result<Self> {
+        let (capacity, len, table) = read_snapshot::<K, V, S>(bytes, &hash_builder)?;
+        if capacity == 0 {
+            if len != 0 || !table.is_empty() {
+                return Err(invalid_data("invalid table size"));
+            }
+            return Ok(FrozenHashMap {
+                hash_builder,
+                hashes: &[],
+                pairs: NonNull::dangling().as_ptr(),
+                len,
+                marker: PhantomData,
+            });
+        }
+        if !capacity.is_power_of_two() || len >= capacity {
+            return Err(invalid_data("invalid table size"));
+        }
+        match RawTable::<K, V>::raw_layout(capacity) {
+            Some((size, pairs_offset)) if size == table.len() => {
+                let align = mem::align_of::<usize>().max(mem::align_of::<(K, V)>());
+                if table.as_ptr() as usize & (align - 1) != 0 {
+                    return Err(invalid_data("snapshot is not aligned"));
+                }
+                Ok(FrozenHashMap {
+                    hash_builder,
+                    hashes: slice::from_raw_parts(table.as_ptr() as *const usize, capacity),
+                    pairs: table.as_ptr().add(pairs_offset) as *const (K, V),
+                    len,
+                    marker: PhantomData,
+                })
+            }
+            _ => Err(invalid_data("wrong number of table bytes")),
+        }
+    }
+
+    fn find<Q>(&self, key: &Q) -> Option<&'a (K, V)>
+        where K: Borrow<Q>,
+              Q: ?Sized + Hash + Eq
+    {
+        if self.hashes.is_empty() {
+            return None;
+        }
+        let hash = make_hash(&self.hash_builder, key).inspect();
+        let mask = self.hashes.len() - 1;
+        let mut index = hash & mask;
+        // Stop at an empty bucket, or at one whose entry is closer to its
+        // ideal bucket than we are, like the robin hood search of `HashMap`.
+        // The probe is also bounded, in case the table is corrupt.
+        for displacement in 0..self.hashes.len() {
+            let stored = self.hashes[index];
+            if stored == 0 || index.wrapping_sub(stored) & mask < displacement {
+                return None;
+            }
+            if stored == hash {
+                let pair = unsafe { self.pair(index) };
+                if key.eq(pair.0.borrow()) {
+                    return Some(pair);
+                }
+            }
+            index = (index + 1) & mask;
+        }
+        None
+    }
+
+    /// Returns a reference to the value of a key.
+    ///
+    /// The key may be any borrowed form of the map's key type, but
+    /// [`Hash`] and [`Eq`] on the borrowed form *must* match those for
+    /// the key type.
+    ///
+    /// [`Eq`]: ../../std/cmp/trait.Eq.html
+    /// [`Hash`]: ../../std/hash/trait.Hash.html
+    #[inline]
+    pub fn get<Q>(&self, key: &Q) -> Option<&'a V>
+        where K: Borrow<Q>,
+              Q: ?Sized + Hash + Eq
+    {
+        self.find(key).map(|pair| &pair.1)
+    }
+
+    /// Returns the key-value pair of a key.
+    #[inline]
+    pub fn get_key_value<Q>(&self, key: &Q) -> Option<(&'a K, &'a V)>
+        where K: Borrow<Q>,
+              Q: ?Sized + Hash + Eq
+    {
+        self.find(key).map(|pair| (&pair.0, &pair.1))
+    }
+
+    /// Returns `true` if the map contains the key.
+    #[inline]
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+        where K: Borrow<Q>,
+              Q: ?Sized + Hash + Eq
+    {
+        self.find(key).is_some()
+    }
+}
+
+impl<'a, K, V, S> FrozenHashMap<'a, K, V, S> {
+    /// Returns the number of entries in the map.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if the map contains no entries.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the number of buckets in the table.
+    #[inline]
+    pub fn bucket_count(&self) -> usize {
+        self.hashes.len()
+    }
+
+    /// Returns a reference to the map's `BuildHasher`.
+    #[inline]
+    pub fn hasher(&self) -> &S {
+        &self.hash_builder
+    }
+
+    /// The bucket at `index` must be full.
+    unsafe fn pair(&self, index: usize) -> &'a (K, V) {
+        &*self.pairs.add(index)
+    }
+
+    /// Returns the entries in buckets `start..end`.
+    pub(crate) fn bucket_range(&self, start: usize, end: usize) -> FrozenIter<'a, K, V> {
+        FrozenIter {
+            hashes: &self.hashes[start..end],
+            pairs: unsafe { self.pairs.add(start) },
+            index: 0,
+            marker: PhantomData,
+        }
+    }
+
+    /// An iterator visiting all key-value pairs in the order of the buckets.
+    #[inline]
+    pub fn iter(&self) -> FrozenIter<'a, K, V> {
+        self.bucket_range(0, self.hashes.len())
+    }
+}
+
+impl<'a, K, V, S> fmt::Debug for FrozenHashMap<'a, K, V, S>
+    where K: fmt::Debug,
+          V: fmt::Debug
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<'a, K, V, S> IntoIterator for &FrozenHashMap<'a, K, V, S> {
+    type Item = (&'a K, &'a V);
+    type IntoIter = FrozenIter<'a, K, V>;
+
+    fn into_iter(self) -> FrozenIter<'a, K, V> {
+        self.iter()
+    }
+}
+
+/// An iterator over the entries of a `FrozenHashMap`.
+///
+/// This `struct` is created by the [`iter`] method on [`FrozenHashMap`].
+///
+/// [`iter`]: struct.FrozenHashMap.html#method.iter
+/// [`FrozenHashMap`]: struct.FrozenHashMap.html
+pub struct FrozenIter<'a, K: 'a, V: 'a> {
+    hashes: &'a [usize],
+    pairs: *const (K, V),
+    index: usize,
+    marker: PhantomData<&'a [(K, V)]>,
+}
+
+unsafe impl<'a, K: Sync, V: Sync> Send for FrozenIter<'a, K, V> {}
+unsafe impl<'a, K: Sync, V: Sync> Sync for FrozenIter<'a, K, V> {}
+
+impl<'a, K, V> FrozenIter<'a, K, V> {
+    /// Splits the remaining buckets in two halves.
+    pub(crate) fn split(self) -> (Self, Option<Self>) {
+        let len = self.hashes.len() - self.index;
+        if len > 1 {
+            let mid = self.index + len / 2;
+            let right = FrozenIter {
+                hashes: &self.hashes[mid..],
+                pairs: unsafe { self.pairs.add(mid) },
+                index: 0,
+                marker: PhantomData,
+            };
+            let left = FrozenIter { hashes: &self.hashes[..mid], ..self };
+            (left, Some(right))
+        } else {
+            (self, None)
+        }
+    }
+}
+
+impl<K, V> Clone for FrozenIter<'_, K, V> {
+    fn clone(&self) -> Self {
+        FrozenIter { ..*self }
+    }
+}
+
+impl<'a, K, V> Iterator for FrozenIter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<(&'a K, &'a V)> {
+        while self.index < self.hashes.len() {
+            let index = self.index;
+            self.index += 1;
+            if self.hashes[index] != 0 {
+                let pair = unsafe { &*self.pairs.add(index) };
+                return Some((&pair.0, &pair.1));
+            }
+        }
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.hashes.len() - self.index))
+    }
+}
+
+impl<K, V> FusedIterator for FrozenIter<'_, K, V> {}
+
+impl<K: fmt::Debug, V: fmt::Debug> fmt::Debug for FrozenIter<'_, K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.clone()).finish()
+    }
+}
+
+/// A copy of a snapshot in memory aligned for `T`, as a memory map would
+/// be, which dereferences to the snapshot's bytes.
+#[cfg(test)]
+pub(crate) struct Aligned<T> {
+    items: Vec<T>,
+    len: usize,
+}
+
+#[cfg(test)]
+impl<T: Clone> Aligned<T> {
+    /// Copies the bytes into enough copies of `fill`.
+    pub(crate) fn new(bytes: &[u8], fill: T) -> Aligned<T> {
+        let mut items = vec![fill; bytes.len() / mem::size_of::<T>() + 1];
+        unsafe {
+            slice::from_raw_parts_mut(items.as_mut_ptr() as *mut u8, bytes.len())
+                .copy_from_slice(bytes);
+        }
+        Aligned { items, len: bytes.len() }
+    }
+}
+
+#[cfg(test)]
+impl<T> Deref for Aligned<T> {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        unsafe { slice::from_raw_parts(self.items.as_ptr() as *const u8, self.len) }
+    }
+}
+
+#[cfg(test)]
+mod test_frozen {
+    use super::super::map::{DefaultHasher, HashMap};
+    use super::super::snapshot::{table_offset, NoPadding};
+    use super::{Aligned, FrozenHashMap};
+    use std::hash::BuildHasherDefault;
+    use std::io::ErrorKind;
+
+    type Fixed = BuildHasherDefault<DefaultHasher>;
+
+    #[test]
+    fn test_lookup() {
+        let mut map: HashMap<u64, [u32; 2], Fixed> = HashMap::default();
+        for i in 0..20_000 {
+            map.insert(i * 3, [i as u32, i as u32 + 1]);
+        }
+        for i in 0..2000 {
+            map.remove(&(i * 9));
+        }
+        let bytes = Aligned::new(&map.to_raw_bytes(), 0u64);
+        let frozen = unsafe { FrozenHashMap::<u64, [u32; 2], _>::from_raw_bytes(&bytes, Fixed::default()) }.unwrap();
+
+        assert_eq!(frozen.len(), map.len());
+        for i in 0..60_000 {
+            assert_eq!(frozen.get(&i), map.get(&i));
+        }
+        assert_eq!(frozen.get_key_value(&3), Some((&3, &[1, 2])));
+        assert_eq!(frozen.iter().count(), map.len());
+        assert!(frozen.iter().all(|(k, v)| map.get(k) == Some(v)));
+    }
+
+    #[test]
+    fn test_overaligned_pairs() {
+        #[derive(Clone, Copy, Debug, PartialEq)]
+        #[repr(align(64))]
+        struct Line([u64; 8]);
+        unsafe impl NoPadding for Line {}
+
+        let map: HashMap<u64, Line, Fixed> = (0..1000).map(|i| (i, Line([i; 8]))).collect();
+        assert_eq!(table_offset::<u64, Line>(), 64);
+        let bytes = Aligned::new(&map.to_raw_bytes(), Line([0; 8]));
+        let frozen = unsafe { FrozenHashMap::<u64, Line, _>::from_raw_bytes(&bytes, Fixed::default()) }.unwrap();
+        assert_eq!(frozen.len(), 1000);
+        assert_eq!(frozen.get(&7), Some(&Line([7; 8])));
+    }
+
+    #[test]
+    fn test_empty_and_errors() {
+        let map: HashMap<u32, u32, Fixed> = HashMap::default();
+        let bytes = map.to_raw_bytes();
+        let frozen = unsafe { FrozenHashMap::<u32, u32, _>::from_raw_bytes(&bytes, Fixed::default()) }.unwrap();
+        assert!(frozen.is_empty());
+        assert_eq!(frozen.get(&1), None);
+        assert_eq!(frozen.iter().next(), None);
+
+        let map: HashMap<u32, u32, Fixed> = (0..100).map(|i| (i, i)).collect();
+        let bytes = Aligned::new(&map.to_raw_bytes(), 0u64);
+        let load = |len: usize| unsafe {
+            FrozenHashMap::<u32, u32, _>::from_raw_bytes(&bytes[..len], Fixed::default()).map(|map| map.len())
+        };
+        assert_eq!(load(bytes.len()).unwrap(), 100);
+        assert_eq!(load(bytes.len() - 1).unwrap_err().kind(), ErrorKind::InvalidData);
+
+        // The same bytes, one byte off alignment.
+        let mut shifted = vec![0u8];
+        shifted.extend_from_slice(&bytes);
+        let shifted = Aligned::new(&shifted, 0u64);
+        let err = unsafe { FrozenHashMap::<u32, u32, _>::from_raw_bytes(&shifted[1..], Fixed::default()) }.unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,817 @@
+use std::borrow::Borrow;
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::{Hash, BuildHasher, BuildHasherDefault};
+use std::iter::{FromIterator, FusedIterator};
+use std::mem;
+use std::slice;
+use std::vec;
+
+use super::map::{DefaultHasher, Entry, HashMap, RandomState};
+use super::table::{make_hash, SafeHash};
+
+/// A hash map that remembers the insertion order of its entries.
+///
+/// The entries are stored densely in a `Vec<(K, V)>`, and a hash table of
+/// indices into that `Vec` finds them by key.  Iteration, both serial and
+/// parallel, follows the order of the entries, so it is deterministic and
+/// doesn't depend on the hashes.
+///
+/// Inserting an existing key keeps its position.  [`swap_remove`] moves the
+/// last entry into the removed one's place, and [`sort_by`] reorders the
+/// entries.
+///
+/// # Examples
+///
+/// ```
+/// use rayon_hash::IndexedHashMap;
+///
+/// let mut planets = IndexedHashMap::new();
+/// planets.insert("Mercury", 0.4);
+/// planets.insert("Venus", 0.7);
+/// planets.insert("Earth", 1.0);
+/// planets.insert("Mars", 1.5);
+///
+/// assert_eq!(planets.get_index(2), Some((&"Earth", &1.0)));
+/// assert_eq!(planets.get_index_of("Mars"), Some(3));
+/// assert_eq!(planets["Venus"], 0.7);
+///
+/// let names: Vec<_> = planets.keys().cloned().collect();
+/// assert_eq!(names, ["Mercury", "Venus", "Earth", "Mars"]);
+/// ```
+///
+/// [`swap_remove`]: #method.swap_remove
+/// [`sort_by`]: #method.sort_by
+#[derive(Clone)]
+pub struct IndexedHashMap<K, V, S = RandomState> {
+    hash_builder: S,
+    indices: Indices,
+    pub(crate) entries: Vec<(K, V)>,
+}
+
+/// The index table maps the hashes of the entries' keys to their positions.
+/// It is only used with hashes from the map's hash builder, never its own.
+type Indices = HashMap<usize, (), BuildHasherDefault<DefaultHasher>>;
+
+impl<K: Hash + Eq, V> IndexedHashMap<K, V, RandomState> {
+    /// Creates an empty `IndexedHashMap`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rayon_hash::IndexedHashMap;
+    /// let map: IndexedHashMap<&str, i32> = IndexedHashMap::new();
+    /// ```
+    #[inline]
+    pub fn new() -> IndexedHashMap<K, V, RandomState> {
+        Default::default()
+    }
+
+    /// Creates an empty `IndexedHashMap` with room for at least `capacity`
+    /// entries.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rayon_hash::IndexedHashMap;
+    /// let map: IndexedHashMap<&str, i32> = IndexedHashMap::with_capacity(10);
+    /// assert!(map.capacity() >= 10);
+    /// ```
+    #[inline]
+    pub fn with_capacity(capacity: usize) -> IndexedHashMap<K, V, RandomState> {
+        IndexedHashMap::with_capacity_and_hasher(capacity, Default::default())
+    }
+}
+
+impl<K, V, S> IndexedHashMap<K, V, S> {
+    /// Returns the number of entries the map can hold without reallocating.
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.indices.capacity().min(self.entries.capacity())
+    }
+
+    /// Returns the number of entries in the map.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns `true` if the map contains no entries.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Clears the map, removing all entries.  Keeps the allocated memory for
+    /// reuse.
+    #[inline]
+    pub fn clear(&mut self) {
+        self.indices.clear();
+        self.entries.clear();
+    }
+
+    /// Returns a reference to the map's `BuildHasher`.
+    #[inline]
+    pub fn hasher(&self) -> &S {
+        &self.hash_builder
+    }
+
+    /// An iterator visiting all key-value pairs in order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rayon_hash::IndexedHashMap;
+    ///
+    /// let map: IndexedHashMap<_, _> = vec![("c", 3), ("a", 1), ("b", 2)].into_iter().collect();
+    /// let pairs: Vec<_> = map.iter().collect();
+    /// assert_eq!(pairs, [(&"c", &3), (&"a", &1), (&"b", &2)]);
+    /// ```
+    #[inline]
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        Iter { inner: self.entries.iter() }
+    }
+
+    /// An iterator visiting all key-value pairs in order, with mutable
+    /// references to the values.
+    #[inline]
+    pub fn iter_mut(&mut self) -> IterMut<'_, K, V> {
+        IterMut { inner: self.entries.iter_mut() }
+    }
+
+    /// An iterator visiting all keys in order.
+    #[inline]
+    pub fn keys(&self) -> Keys<'_, K, V> {
+        Keys { inner: self.entries.iter() }
+    }
+
+    /// An iterator visiting all values in order.
+    #[inline]
+    pub fn values(&self) -> Values<'_, K, V> {
+        Values { inner: self.entries.iter() }
+    }
+
+    /// Returns the entry at a position, if it's in bounds.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rayon_hash::IndexedHashMap;
+    ///
+    /// let mut map = IndexedHashMap::new();
+    /// map.insert("x", 1);
+    /// map.insert("y", 2);
+    /// assert_eq!(map.get_index(1), Some((&"y", &2)));
+    /// assert_eq!(map.get_index(2), None);
+    /// ```
+    #[inline]
+    pub fn get_index(&self, index: usize) -> Option<(&K, &V)> {
+        self.entries.get(index).map(|(key, value)| (key, value))
+    }
+
+    /// Returns the entry at a position with a mutable reference to the
+    /// value, if it's in bounds.
+    #[inline]
+    pub fn get_index_mut(&mut self, index: usize) -> Option<(&K, &mut V)> {
+        self.entries.get_mut(index).map(|(key, value)| (&*key, value))
+    }
+
+    /// Returns the entries as a slice, in order.
+    #[inline]
+    pub fn as_slice(&self) -> &[(K, V)] {
+        &self.entries
+    }
+
+    /// Consumes the map, returning its entries in order.
+    #[inline]
+    pub fn into_vec(self) -> Vec<(K, V)> {
+        self.entries
+    }
+}
+
+impl<K, V, S> IndexedHashMap<K, V, S>
+    where K: Eq + Hash,
+          S: BuildHasher
+{
+    /// Creates an empty `IndexedHashMap` which will use the given hash
+    /// builder to hash keys.
+    #[inline]
+    pub fn with_hasher(hash_builder: S) -> IndexedHashMap<K, V, S> {
+        IndexedHashMap::with_capacity_and_hasher(0, hash_builder)
+    }
+
+    /// Creates an empty `IndexedHashMap` with room for at least `capacity`
+    /// entries, using `hash_builder` to hash the keys.
+    #[inline]
+    pub fn with_capacity_and_hasher(capacity: usize, hash_builder: S)
+        -> IndexedHashMap<K, V, S>
+    {
+        IndexedHashMap {
+            hash_builder,
+            indices: Indices::with_capacity_and_hasher(capacity, Default::default()),
+            entries: Vec::with_capacity(capacity),
+        }
+    }
+
+    pub(crate) fn make_hash<X: ?Sized + Hash>(&self, x: &X) -> SafeHash {
+        make_hash(&self.hash_builder, x)
+    }
+
+    /// Reserves capacity for at least `additional` more entries.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the new allocation size overflows `usize`.
+    pub fn reserve(&mut self, additional: usize) {
+        self.indices.reserve(additional);
+        self.entries.reserve(additional);
+    }
+
+    /// Returns the position of a key.
+    ///
+    /// The key may be any borrowed form of the map's key type, but
+    /// [`Hash`] and [`Eq`] on the borrowed form *must* match those for
+    /// the key type.
+    ///
+    /// [`Eq`]: ../../std/cmp/trait.Eq.html
+    /// [`Hash`]: ../../std/hash/trait.Hash.html
+    pub fn get_index_of<Q>(&self, key: &Q) -> Option<usize>
+        where K: Borrow<Q>,
+              Q: ?Sized + Hash + Eq
+    {
+        let entries = &self.entries;
+        self.indices
+            .find_hashed_with(self.make_hash(key), |&i| key.eq(entries[i].0.borrow()))
+            .map(|(&i, _)| i)
+    }
+
+    /// Returns the position, key and value of a key's entry.
+    pub fn get_full<Q>(&self, key: &Q) -> Option<(usize, &K, &V)>
+        where K: Borrow<Q>,
+              Q: ?Sized + Hash + Eq
+    {
+        let i = self.get_index_of(key)?;
+        let (ref key, ref value) = self.entries[i];
+        Some((i, key, value))
+    }
+
+    /// Returns a reference to the value of a key.
+    #[inline]
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+        where K: Borrow<Q>,
+              Q: ?Sized + Hash + Eq
+    {
+        self.get_index_of(key).map(|i| &self.entries[i].1)
+    }
+
+    /// Returns a mutable reference to the value of a key.
+    #[inline]
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+        where K: Borrow<Q>,
+              Q: ?Sized + Hash + Eq
+    {
+        let i = self.get_index_of(key)?;
+        Some(&mut self.entries[i].1)
+    }
+
+    /// Returns `true` if the map contains the key.
+    #[inline]
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+        where K: Borrow<Q>,
+              Q: ?Sized + Hash + Eq
+    {
+        self.get_index_of(key).is_some()
+    }
+
+    /// Inserts a key-value pair.  If the key was already present, its value
+    /// is replaced and returned, and the entry keeps its position.
+    /// Otherwise the entry is added at the end.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rayon_hash::IndexedHashMap;
+    ///
+    /// let mut map = IndexedHashMap::new();
+    /// assert_eq!(map.insert("a", 1), None);
+    /// assert_eq!(map.insert("b", 2), None);
+    /// assert_eq!(map.insert("a", 3), Some(1));
+    /// assert_eq!(map.get_index(0), Some((&"a", &3)));
+    /// ```
+    #[inline]
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        self.insert_full(key, value).1
+    }
+
+    /// Like `insert`, but also returns the position of the entry.
+    pub fn insert_full(&mut self, key: K, value: V) -> (usize, Option<V>) {
+        let hash = self.make_hash(&key);
+        self.insert_hashed(hash, key, value)
+    }
+
+    pub(crate) fn insert_hashed(&mut self, hash: SafeHash, key: K, value: V)
+        -> (usize, Option<V>)
+    {
+        let entries = &mut self.entries;
+        if let Some((&i, _)) = self.indices.find_hashed_with(hash, |&i| entries[i].0 == key) {
+            return (i, Some(mem::replace(&mut entries[i].1, value)));
+        }
+        let i = entries.len();
+        if let Entry::Vacant(entry) = self.indices.entry_hashed(hash, i) {
+            entry.insert(());
+        }
+        entries.push((key, value));
+        (i, None)
+    }
+
+    /// Removes a key, returning its value.  The last entry takes the place
+    /// of the removed one, so this disturbs the order, but only takes O(1)
+    /// time.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rayon_hash::IndexedHashMap;
+    ///
+    /// let mut map: IndexedHashMap<_, _> = (0..4).map(|i| (i, i * 10)).collect();
+    /// assert_eq!(map.swap_remove(&1), Some(10));
+    /// assert_eq!(map.keys().cloned().collect::<Vec<_>>(), [0, 3, 2]);
+    /// ```
+    #[inline]
+    pub fn swap_remove<Q>(&mut self, key: &Q) -> Option<V>
+        where K: Borrow<Q>,
+              Q: ?Sized + Hash + Eq
+    {
+        self.swap_remove_full(key).map(|(_, _, value)| value)
+    }
+
+    /// Like `swap_remove`, but also returns the position and the key of the
+    /// removed entry.
+    pub fn swap_remove_full<Q>(&mut self, key: &Q) -> Option<(usize, K, V)>
+        where K: Borrow<Q>,
+              Q: ?Sized + Hash + Eq
+    {
+        let hash = self.make_hash(key);
+        let entries = &self.entries;
+        let (i, ()) = self.indices
+            .remove_hashed_with(hash, |&i| key.eq(entries[i].0.borrow()))?;
+        let (key, value) = self.swap_remove_entry(i);
+        Some((i, key, value))
+    }
+
+    /// Removes the entry at a position, if it's in bounds.  The last entry
+    /// takes its place.
+    pub fn swap_remove_index(&mut self, index: usize) -> Option<(K, V)> {
+        let hash = self.make_hash(&self.entries.get(index)?.0);
+        self.indices.remove_hashed_with(hash, |&i| i == index);
+        Some(self.swap_remove_entry(index))
+    }
+
+    /// Removes the last entry.
+    pub fn pop(&mut self) -> Option<(K, V)> {
+        self.len().checked_sub(1).and_then(|last| self.swap_remove_index(last))
+    }
+
+    // The caller should already have removed the entry from the indices.
+    fn swap_remove_entry(&mut self, index: usize) -> (K, V) {
+        let last = self.entries.len() - 1;
+        if index != last {
+            let hash = self.make_hash(&self.entries[last].0);
+            if let Some((i, _)) = self.indices.find_hashed_mut_with(hash, |&i| i == last) {
+                *i = index;
+            }
+        }
+        self.entries.swap_remove(index)
+    }
+
+    /// Retains only the entries specified by the predicate, keeping their
+    /// order.
+    pub fn retain<F>(&mut self, mut f: F)
+        where F: FnMut(&K, &mut V) -> bool
+    {
+        // Nothing moves until the predicate and the hashes are done with,
+        // so a panic in either leaves the map as it was.
+        let keep: Vec<bool> = self.entries.iter_mut().map(|entry| f(&entry.0, &mut entry.1)).collect();
+        if keep.iter().all(|&k| k) {
+            return;
+        }
+        let mut hashes = self.hashes();
+
+        // Kept entries are swapped forward, so they stay in order.
+        let mut kept = 0;
+        for (i, keep) in keep.into_iter().enumerate() {
+            if keep {
+                self.entries.swap(kept, i);
+                hashes.swap(kept, i);
+                kept += 1;
+            }
+        }
+        hashes.truncate(kept);
+        self.rebuild_indices_hashed(hashes);
+        self.entries.truncate(kept);
+    }
+
+    /// Sorts the entries with a comparison function of the keys and values.
+    /// The sort is stable.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rayon_hash::IndexedHashMap;
+    ///
+    /// let mut map: IndexedHashMap<_, _> = vec![("b", 2), ("c", 1), ("a", 3)].into_iter().collect();
+    /// map.sort_by(|_, v1, _, v2| v1.cmp(v2));
+    /// assert_eq!(map.keys().cloned().collect::<Vec<_>>(), ["c", "b", "a"]);
+    /// assert_eq!(map.get_index_of("a"), Some(2));
+    /// ```
+    pub fn sort_by<F>(&mut self, mut compare: F)
+        where F: FnMut(&K, &V, &K, &V) -> Ordering
+    {
+        // Sort the positions instead of the entries, so a panicking
+        // comparison leaves the map as it was.
+        let hashes = self.hashes();
+        let mut order: Vec<usize> = (0..self.entries.len()).collect();
+        {
+            let entries = &self.entries;
+            order.sort_by(|&a, &b| {
+                let (a, b) = (&entries[a], &entries[b]);
+                compare(&a.0, &a.1, &b.0, &b.1)
+            });
+        }
+        self.reorder(order, hashes);
+    }
+
+    /// Sorts the entries by their keys.
+    pub fn sort_keys(&mut self)
+        where K: Ord
+    {
+        self.sort_by(|k1, _, k2, _| k1.cmp(k2));
+    }
+
+    /// Returns the hashes of the entries' keys, in order.
+    fn hashes(&self) -> Vec<SafeHash> {
+        self.entries.iter().map(|entry| self.make_hash(&entry.0)).collect()
+    }
+
+    /// Moves the entry at `order[i]` to position `i`, given the hashes of
+    /// the entries' keys in their old order, and rebuilds the index table.
+    pub(crate) fn reorder(&mut self, mut order: Vec<usize>, hashes: Vec<SafeHash>) {
+        let hashes = order.iter().map(|&i| hashes[i]).collect();
+        // Follow each cycle of the permutation, marking its positions done.
+        for start in 0..order.len() {
+            let mut i = start;
+            while order[i] != i {
+                let next = order[i];
+                order[i] = i;
+                if next == start {
+                    break;
+                }
+                self.entries.swap(i, next);
+                i = next;
+            }
+        }
+        self.rebuild_indices_hashed(hashes);
+    }
+
+    /// Rebuilds the index table from the hashes of the entries' keys, in
+    /// order.
+    pub(crate) fn rebuild_indices_hashed(&mut self, hashes: Vec<SafeHash>) {
+        self.indices.clear();
+        self.indices.reserve(hashes.len());
+        for (i, hash) in hashes.into_iter().enumerate() {
+            if let Entry::Vacant(entry) = self.indices.entry_hashed(hash, i) {
+                entry.insert(());
+            }
+        }
+    }
+}
+
+impl<K, V, S> PartialEq for IndexedHashMap<K, V, S>
+    where K: Eq + Hash,
+          V: PartialEq,
+          S: BuildHasher
+{
+    /// Maps are equal if they have the same entries, in any order.
+    fn eq(&self, other: &IndexedHashMap<K, V, S>) -> bool {
+        self.len() == other.len() &&
+            self.iter().all(|(key, value)| other.get(key) == Some(value))
+    }
+}
+
+impl<K, V, S> Eq for IndexedHashMap<K, V, S>
+    where K: Eq + Hash,
+          V: Eq,
+          S: BuildHasher
+{
+}
+
+impl<K, V, S> fmt::Debug for IndexedHashMap<K, V, S>
+    where K: fmt::Debug,
+          V: fmt::Debug
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<K, V, S> Default for IndexedHashMap<K, V, S>
+    where K: Eq + Hash,
+          S: BuildHasher + Default
+{
+    /// Creates an empty `IndexedHashMap<K, V, S>`, with the `Default` value for the hasher.
+    fn default() -> IndexedHashMap<K, V, S> {
+        IndexedHashMap::with_hasher(Default::default())
+    }
+}
+
+impl<K, Q: ?Sized, V, S> std::ops::Index<&Q> for IndexedHashMap<K, V, S>
+    where K: Eq + Hash + Borrow<Q>,
+          Q: Eq + Hash,
+          S: BuildHasher
+{
+    type Output = V;
+
+    /// Returns a reference to the value of the key.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the key is not present in the map.
+    #[inline]
+    fn index(&self, key: &Q) -> &V {
+        self.get(key).expect("no entry found for key")
+    }
+}
+
+impl<K, V, S> FromIterator<(K, V)> for IndexedHashMap<K, V, S>
+    where K: Eq + Hash,
+          S: BuildHasher + Default
+{
+    fn from_iter<T: IntoIterator<Item = (K, V)>>(iter: T) -> IndexedHashMap<K, V, S> {
+        let mut map = IndexedHashMap::default();
+        map.extend(iter);
+        map
+    }
+}
+
+impl<K, V, S> Extend<(K, V)> for IndexedHashMap<K, V, S>
+    where K: Eq + Hash,
+          S: BuildHasher
+{
+    fn extend<T: IntoIterator<Item = (K, V)>>(&mut self, iter: T) {
+        for (key, value) in iter {
+            self.insert(key, value);
+        }
+    }
+}
+
+impl<'a, K, V, S> IntoIterator for &'a IndexedHashMap<K, V, S> {
+    type Item = (&'a K, &'a V);
+    type IntoIter = Iter<'a, K, V>;
+
+    fn into_iter(self) -> Iter<'a, K, V> {
+        self.iter()
+    }
+}
+
+impl<'a, K, V, S> IntoIterator for &'a mut IndexedHashMap<K, V, S> {
+    type Item = (&'a K, &'a mut V);
+    type IntoIter = IterMut<'a, K, V>;
+
+    fn into_iter(self) -> IterMut<'a, K, V> {
+        self.iter_mut()
+    }
+}
+
+impl<K, V, S> IntoIterator for IndexedHashMap<K, V, S> {
+    type Item = (K, V);
+    type IntoIter = vec::IntoIter<(K, V)>;
+
+    /// Creates a consuming iterator over the entries in order.
+    fn into_iter(self) -> vec::IntoIter<(K, V)> {
+        self.entries.into_iter()
+    }
+}
+
+/// An iterator over the entries of an `IndexedHashMap`, in order.
+///
+/// This `struct` is created by the [`iter`] method on [`IndexedHashMap`].
+///
+/// [`iter`]: struct.IndexedHashMap.html#method.iter
+/// [`IndexedHashMap`]: struct.IndexedHashMap.html
+pub struct Iter<'a, K: 'a, V: 'a> {
+    inner: slice::Iter<'a, (K, V)>,
+}
+
+/// A mutable iterator over the entries of an `IndexedHashMap`, in order.
+///
+/// This `struct` is created by the [`iter_mut`] method on [`IndexedHashMap`].
+///
+/// [`iter_mut`]: struct.IndexedHashMap.html#method.iter_mut
+/// [`IndexedHashMap`]: struct.IndexedHashMap.html
+pub struct IterMut<'a, K: 'a, V: 'a> {
+    inner: slice::IterMut<'a, (K, V)>,
+}
+
+/// An iterator over the keys of an `IndexedHashMap`, in order.
+///
+/// This `struct` is created by the [`keys`] method on [`IndexedHashMap`].
+///
+/// [`keys`]: struct.IndexedHashMap.html#method.keys
+/// [`IndexedHashMap`]: struct.IndexedHashMap.html
+pub struct Keys<'a, K: 'a, V: 'a> {
+    inner: slice::Iter<'a, (K, V)>,
+}
+
+/// An iterator over the values of an `IndexedHashMap`, in order.
+///
+/// This `struct` is created by the [`values`] method on [`IndexedHashMap`].
+///
+/// [`values`]: struct.IndexedHashMap.html#method.values
+/// [`IndexedHashMap`]: struct.IndexedHashMap.html
+pub struct Values<'a, K: 'a, V: 'a> {
+    inner: slice::Iter<'a, (K, V)>,
+}
+
+macro_rules! entry_iterator {
+    ($name:ident, $item:ty, |$entry:pat| $map:expr) => {
+        impl<'a, K, V> Iterator for $name<'a, K, V> {
+            type Item = $item;
+
+            #[inline]
+            fn next(&mut self) -> Option<$item> {
+                self.inner.next().map(|$entry| $map)
+            }
+
+            #[inline]
+            fn size_hint(&self) -> (usize, Option<usize>) {
+                self.inner.size_hint()
+            }
+        }
+
+        impl<'a, K, V> DoubleEndedIterator for $name<'a, K, V> {
+            #[inline]
+            fn next_back(&mut self) -> Option<$item> {
+                self.inner.next_back().map(|$entry| $map)
+            }
+        }
+
+        impl<K, V> ExactSizeIterator for $name<'_, K, V> {
+            #[inline]
+            fn len(&self) -> usize {
+                self.inner.len()
+            }
+        }
+
+        impl<K, V> FusedIterator for $name<'_, K, V> {}
+    };
+}
+
+entry_iterator!(Iter, (&'a K, &'a V), |(key, value)| (key, value));
+entry_iterator!(IterMut, (&'a K, &'a mut V), |(key, value)| (&*key, value));
+entry_iterator!(Keys, &'a K, |(key, _)| key);
+entry_iterator!(Values, &'a V, |(_, value)| value);
+
+impl<K, V> Clone for Iter<'_, K, V> {
+    fn clone(&self) -> Self {
+        Iter { inner: self.inner.clone() }
+    }
+}
+
+impl<K, V> Clone for Keys<'_, K, V> {
+    fn clone(&self) -> Self {
+        Keys { inner: self.inner.clone() }
+    }
+}
+
+impl<K, V> Clone for Values<'_, K, V> {
+    fn clone(&self) -> Self {
+        Values { inner: self.inner.clone() }
+    }
+}
+
+impl<K: fmt::Debug, V: fmt::Debug> fmt::Debug for Iter<'_, K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.clone()).finish()
+    }
+}
+
+impl<K: fmt::Debug, V: fmt::Debug> fmt::Debug for IterMut<'_, K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.inner.as_slice().iter()).finish()
+    }
+}
+
+impl<K: fmt::Debug, V> fmt::Debug for Keys<'_, K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.clone()).finish()
+    }
+}
+
+impl<K, V: fmt::Debug> fmt::Debug for Values<'_, K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.clone()).finish()
+    }
+}
+
+#[cfg(test)]
+mod test_indexed {
+    use super::IndexedHashMap;
+
+    #[test]
+    fn test_insertion_order() {
+        let mut map = IndexedHashMap::new();
+        for i in (0..1000).rev() {
+            assert_eq!(map.insert(i, i * 2), None);
+        }
+        assert_eq!(map.insert(500, 0), Some(1000));
+        assert_eq!(map.len(), 1000);
+        let keys: Vec<_> = map.keys().cloned().collect();
+        assert_eq!(keys, (0..1000).rev().collect::<Vec<_>>());
+        assert_eq!(map.get_full(&500), Some((499, &500, &0)));
+        assert_eq!(map.get_index(999), Some((&0, &0)));
+        assert_eq!(map.values().rev().nth(1), Some(&2));
+    }
+
+    #[test]
+    fn test_swap_remove() {
+        let mut map: IndexedHashMap<_, _> = (0..100).map(|i| (i, i)).collect();
+        assert_eq!(map.swap_remove(&10), Some(10));
+        assert_eq!(map.swap_remove(&10), None);
+        assert_eq!(map.get_index(10), Some((&99, &99)));
+        assert_eq!(map.get_index_of(&99), Some(10));
+        assert_eq!(map.swap_remove_full(&98), Some((98, 98, 98)));
+        assert_eq!(map.swap_remove_index(0), Some((0, 0)));
+        assert_eq!(map.get_index_of(&97), Some(0));
+        assert_eq!(map.pop(), Some((96, 96)));
+        assert_eq!(map.swap_remove_index(96), None);
+        assert_eq!(map.len(), 96);
+
+        // Every remaining key is still found at its position.
+        for (i, (key, _)) in map.iter().enumerate() {
+            assert_eq!(map.get_index_of(key), Some(i));
+        }
+        while map.pop().is_some() {}
+        assert!(map.is_empty());
+        assert_eq!(map.get(&1), None);
+    }
+
+    #[test]
+    fn test_sort_and_retain() {
+        let mut map: IndexedHashMap<_, _> = (0..100).map(|i| ((i * 37) % 100, i)).collect();
+        map.sort_keys();
+        for (i, (&key, _)) in map.iter().enumerate() {
+            assert_eq!(key, i);
+            assert_eq!(map.get_index_of(&key), Some(i));
+        }
+
+        map.retain(|&key, value| {
+            *value += 1;
+            key % 3 == 0
+        });
+        assert_eq!(map.len(), 34);
+        for (i, (&key, &value)) in map.iter().enumerate() {
+            assert_eq!(key, i * 3);
+            assert_eq!(map.get_index_of(&key), Some(i));
+            assert_eq!((value - 1) * 37 % 100, key);
+        }
+        assert!(!map.contains_key(&1));
+    }
+
+    #[test]
+    fn test_panic() {
+        use std::panic::{self, AssertUnwindSafe};
+
+        let check = |map: &IndexedHashMap<i32, i32>| {
+            assert_eq!(map.len(), 100);
+            for (i, (key, _)) in map.iter().enumerate() {
+                assert_eq!(map.get_index_of(key), Some(i));
+            }
+        };
+        let mut map: IndexedHashMap<_, _> = (0..100).map(|i| (i, i)).collect();
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            map.retain(|&key, _| {
+                if key == 50 {
+                    panic!("retain");
+                }
+                key % 2 == 0
+            })
+        }));
+        assert!(result.is_err());
+        check(&map);
+        assert_eq!(map.get(&51), Some(&51));
+
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            map.sort_by(|&k1, _, &k2, _| {
+                if k1 == 50 || k2 == 50 {
+                    panic!("sort_by");
+                }
+                k2.cmp(&k1)
+            })
+        }));
+        assert!(result.is_err());
+        check(&map);
+        assert_eq!(map.keys().cloned().collect::<Vec<_>>(), (0..100).collect::<Vec<_>>());
+        map.insert(51, 0);
+        assert_eq!(map.len(), 100);
+    }
+}
@@ -0,0 +1,363 @@
+use std::borrow::Borrow;
+use std::fmt;
+use std::hash::{BuildHasher, BuildHasherDefault};
+use std::iter::FusedIterator;
+use std::ops::Range;
+use std::str;
+
+use super::map::{DefaultHasher, Entry, RandomState};
+use super::set::HashSet;
+use super::table::{make_hash, SafeHash};
+
+/// A string interner, which gives every distinct string a small, stable
+/// [`Symbol`].
+///
+/// The bytes of all interned strings are stored back to back in one arena,
+/// and a `HashSet` of symbols finds them by content.  Looking up a string
+/// that is already interned doesn't allocate.  Strings and byte slices share
+/// the same symbols, so `"abc"` and `b"abc"` are interned as one.
+///
+/// Symbols are numbered from zero in the order that their strings were first
+/// interned, and stay valid for the life of the interner.
+///
+/// # Examples
+///
+/// ```
+/// use rayon_hash::hash_set::Interner;
+///
+/// let mut interner = Interner::new();
+/// let foo = interner.intern("foo");
+/// let bar = interner.intern("bar");
+///
+/// assert_ne!(foo, bar);
+/// assert_eq!(interner.intern("foo"), foo);
+/// assert_eq!(interner.intern_bytes(b"bar"), bar);
+/// assert_eq!(interner.resolve(foo), Some("foo"));
+/// assert_eq!(interner.len(), 2);
+/// ```
+///
+/// [`Symbol`]: struct.Symbol.html
+#[derive(Clone)]
+pub struct Interner<S = RandomState> {
+    hash_builder: S,
+    symbols: HashSet<Symbol, BuildHasherDefault<DefaultHasher>>,
+    bytes: Vec<u8>,
+    spans: Vec<Span>,
+}
+
+/// A handle for a string in an [`Interner`].
+///
+/// [`Interner`]: struct.Interner.html
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Symbol(u32);
+
+impl Symbol {
+    /// Returns the symbol's number, which counts up from zero in the order
+    /// that strings were first interned.
+    #[inline]
+    pub fn as_u32(self) -> u32 {
+        self.0
+    }
+
+    #[inline]
+    fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+/// Where a symbol's bytes end in the arena, and whether they are valid
+/// UTF-8, which is checked once when they are first interned.
+#[derive(Clone, Copy)]
+struct Span {
+    end: usize,
+    utf8: bool,
+}
+
+impl Interner<RandomState> {
+    /// Creates an empty `Interner`.
+    #[inline]
+    pub fn new() -> Interner<RandomState> {
+        Default::default()
+    }
+
+    /// Creates an empty `Interner` with room for at least `symbols` strings
+    /// of `bytes` total length.
+    #[inline]
+    pub fn with_capacity(symbols: usize, bytes: usize) -> Interner<RandomState> {
+        Interner::with_capacity_and_hasher(symbols, bytes, Default::default())
+    }
+}
+
+impl<S> Interner<S> {
+    /// Returns the number of interned strings.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.spans.len()
+    }
+
+    /// Returns `true` if nothing has been interned.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.spans.is_empty()
+    }
+
+    /// Returns a reference to the interner's `BuildHasher`.
+    #[inline]
+    pub fn hasher(&self) -> &S {
+        &self.hash_builder
+    }
+
+    fn range(&self, index: usize) -> Range<usize> {
+        let start = if index == 0 { 0 } else { self.spans[index - 1].end };
+        start..self.spans[index].end
+    }
+
+    /// Returns the bytes of a symbol, or `None` if it doesn't belong to this
+    /// interner.
+    #[inline]
+    pub fn resolve_bytes(&self, symbol: Symbol) -> Option<&[u8]> {
+        if symbol.index() < self.spans.len() {
+            Some(&self.bytes[self.range(symbol.index())])
+        } else {
+            None
+        }
+    }
+
+    /// Returns the string of a symbol, or `None` if it doesn't belong to
+    /// this interner or was interned from bytes that aren't UTF-8.
+    #[inline]
+    pub fn resolve(&self, symbol: Symbol) -> Option<&str> {
+        let bytes = self.resolve_bytes(symbol)?;
+        if self.spans[symbol.index()].utf8 {
+            // The bytes were validated when they were interned.
+            Some(unsafe { str::from_utf8_unchecked(bytes) })
+        } else {
+            None
+        }
+    }
+
+    /// An iterator visiting all symbols and their bytes, in the order that
+    /// they were interned.
+    #[inline]
+    pub fn iter(&self) -> Symbols<'_, S> {
+        Symbols { interner: self, next: 0 }
+    }
+}
+
+impl<S: BuildHasher> Interner<S> {
+    /// Creates an empty `Interner` which will use the given hash builder to
+    /// hash strings.
+    #[inline]
+    pub fn with_hasher(hash_builder: S) -> Interner<S> {
+        Interner::with_capacity_and_hasher(0, 0, hash_builder)
+    }
+
+    /// Creates an empty `Interner` with room for at least `symbols` strings
+    /// of `bytes` total length, using `hash_builder` to hash them.
+    pub fn with_capacity_and_hasher(symbols: usize, bytes: usize, hash_builder: S)
+        -> Interner<S>
+    {
+        Interner {
+            hash_builder,
+            symbols: HashSet::with_capacity_and_hasher(symbols, Default::default()),
+            bytes: Vec::with_capacity(bytes),
+            spans: Vec::with_capacity(symbols),
+        }
+    }
+
+    pub(crate) fn make_hash(&self, bytes: &[u8]) -> SafeHash {
+        make_hash(&self.hash_builder, bytes)
+    }
+
+    /// Interns a string, returning its symbol.
+    #[inline]
+    pub fn intern<Q>(&mut self, string: &Q) -> Symbol
+        where Q: ?Sized + Borrow<str>
+    {
+        let bytes = string.borrow().as_bytes();
+        let hash = self.make_hash(bytes);
+        self.intern_hashed(hash, bytes, Some(true))
+    }
+
+    /// Interns a byte slice, returning its symbol.
+    #[inline]
+    pub fn intern_bytes(&mut self, bytes: &[u8]) -> Symbol {
+        let hash = self.make_hash(bytes);
+        self.intern_hashed(hash, bytes, None)
+    }
+
+    /// Returns the symbol of a string, if it's interned.
+    #[inline]
+    pub fn get<Q>(&self, string: &Q) -> Option<Symbol>
+        where Q: ?Sized + Borrow<str>
+    {
+        self.get_bytes(string.borrow().as_bytes())
+    }
+
+    /// Returns the symbol of a byte slice, if it's interned.
+    #[inline]
+    pub fn get_bytes(&self, bytes: &[u8]) -> Option<Symbol> {
+        self.get_hashed(self.make_hash(bytes), bytes)
+    }
+
+    pub(crate) fn get_hashed(&self, hash: SafeHash, bytes: &[u8]) -> Option<Symbol> {
+        self.symbols
+            .map
+            .find_hashed_with(hash, |&symbol| self.resolve_bytes(symbol) == Some(bytes))
+            .map(|(&symbol, _)| symbol)
+    }
+
+    /// Interns bytes that were already hashed, where `utf8` tells if they
+    /// are known to be valid UTF-8 or not, or `None` if they must be
+    /// checked.
+    pub(crate) fn intern_hashed(&mut self, hash: SafeHash, bytes: &[u8], utf8: Option<bool>)
+        -> Symbol
+    {
+        if let Some(symbol) = self.get_hashed(hash, bytes) {
+            return symbol;
+        }
+
+        let index = self.spans.len();
+        assert_eq!(index as u32 as usize, index, "too many symbols");
+        let symbol = Symbol(index as u32);
+        if let Entry::Vacant(entry) = self.symbols.map.entry_hashed(hash, symbol) {
+            entry.insert(());
+        }
+        self.bytes.extend_from_slice(bytes);
+        self.spans.push(Span {
+            end: self.bytes.len(),
+            utf8: utf8.unwrap_or_else(|| str::from_utf8(bytes).is_ok()),
+        });
+        symbol
+    }
+}
+
+impl<S> fmt::Debug for Interner<S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_map()
+            .entries(self.iter().map(|(symbol, bytes)| {
+                (symbol.as_u32(), String::from_utf8_lossy(bytes))
+            }))
+            .finish()
+    }
+}
+
+impl<S: BuildHasher + Default> Default for Interner<S> {
+    /// Creates an empty `Interner<S>`, with the `Default` value for the hasher.
+    fn default() -> Interner<S> {
+        Interner::with_hasher(Default::default())
+    }
+}
+
+impl<'a, S> IntoIterator for &'a Interner<S> {
+    type Item = (Symbol, &'a [u8]);
+    type IntoIter = Symbols<'a, S>;
+
+    fn into_iter(self) -> Symbols<'a, S> {
+        self.iter()
+    }
+}
+
+/// An iterator over the symbols of an `Interner` and their bytes.
+///
+/// This `struct` is created by the [`iter`] method on [`Interner`].
+///
+/// [`iter`]: struct.Interner.html#method.iter
+/// [`Interner`]: struct.Interner.html
+pub struct Symbols<'a, S: 'a> {
+    interner: &'a Interner<S>,
+    next: usize,
+}
+
+impl<S> Clone for Symbols<'_, S> {
+    fn clone(&self) -> Self {
+        Symbols { interner: self.interner, next: self.next }
+    }
+}
+
+impl<'a, S> Iterator for Symbols<'a, S> {
+    type Item = (Symbol, &'a [u8]);
+
+    fn next(&mut self) -> Option<(Symbol, &'a [u8])> {
+        let interner = self.interner;
+        if self.next < interner.len() {
+            let index = self.next;
+            self.next += 1;
+            Some((Symbol(index as u32), &interner.bytes[interner.range(index)]))
+        } else {
+            None
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.interner.len() - self.next;
+        (len, Some(len))
+    }
+}
+
+impl<S> ExactSizeIterator for Symbols<'_, S> {
+    fn len(&self) -> usize {
+        self.interner.len() - self.next
+    }
+}
+
+impl<S> FusedIterator for Symbols<'_, S> {}
+
+#[cfg(test)]
+mod test_interner {
+    use super::Interner;
+
+    #[test]
+    fn test_intern() {
+        let mut interner = Interner::new();
+        let words = ["alpha", "beta", "", "gamma", "beta", "alpha"];
+        let symbols: Vec<_> = words.iter().map(|w| interner.intern(w)).collect();
+        assert_eq!(symbols[0], symbols[5]);
+        assert_eq!(symbols[1], symbols[4]);
+        assert_eq!(interner.len(), 4);
+        let numbers: Vec<_> = symbols.iter().map(|s| s.as_u32()).collect();
+        assert_eq!(numbers, [0, 1, 2, 3, 1, 0]);
+
+        for (word, &symbol) in words.iter().zip(&symbols) {
+            assert_eq!(interner.resolve(symbol), Some(*word));
+            assert_eq!(interner.get(word), Some(symbol));
+        }
+        assert_eq!(interner.get("delta"), None);
+
+        let owned = String::from("gamma");
+        assert_eq!(interner.intern(&owned), symbols[3]);
+        assert_eq!(interner.len(), 4);
+    }
+
+    #[test]
+    fn test_bytes() {
+        let mut interner = Interner::new();
+        let text = interner.intern("text");
+        assert_eq!(interner.intern_bytes(b"text"), text);
+
+        let binary = interner.intern_bytes(&[0xff, 0xfe]);
+        assert_eq!(interner.resolve(binary), None);
+        assert_eq!(interner.resolve_bytes(binary), Some(&[0xff, 0xfe][..]));
+
+        // Validity is a property of the bytes, however they came in first.
+        let late = interner.intern_bytes("späť".as_bytes());
+        assert_eq!(interner.intern("späť"), late);
+        assert_eq!(interner.resolve(late), Some("späť"));
+
+        let other = Interner::new().intern("x");
+        let empty = Interner::new();
+        assert_eq!(empty.resolve(other), None);
+    }
+
+    #[test]
+    fn test_many() {
+        let mut interner = Interner::new();
+        for i in 0..10_000 {
+            assert_eq!(interner.intern(&i.to_string()).as_u32(), i);
+        }
+        for (symbol, bytes) in &interner {
+            assert_eq!(bytes, symbol.as_u32().to_string().as_bytes());
+        }
+        assert_eq!(interner.iter().len(), 10_000);
+    }
+}
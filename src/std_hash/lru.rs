@@ -0,0 +1,642 @@
+use std::borrow::Borrow;
+use std::fmt;
+use std::hash::{Hash, BuildHasher, BuildHasherDefault};
+use std::iter::FusedIterator;
+use std::mem;
+
+use super::map::{DefaultHasher, Entry, HashMap, RandomState};
+use super::table::{make_hash, SafeHash};
+
+/// A cache which holds at most a fixed number of entries, and evicts the
+/// least recently used one to make room for new ones.
+///
+/// The entries are stored densely in a `Vec`, each with links to the entries
+/// used just before and after it, so the recency list needs no allocations
+/// of its own.  A hash table of indices into that `Vec` finds them by key.
+/// [`get`], [`put`] and [`pop_lru`] all take O(1) time.
+///
+/// Looking up a key with [`get`] or [`get_mut`] marks it as the most
+/// recently used, while [`peek`] and [`contains_key`] leave the order alone.
+///
+/// # Examples
+///
+/// ```
+/// use rayon_hash::hash_map::LruCache;
+///
+/// let mut cache = LruCache::new(2);
+/// cache.put("apple", 3);
+/// cache.put("banana", 5);
+/// assert_eq!(cache.get("apple"), Some(&3));
+///
+/// // "banana" is now the least recently used, so it is evicted.
+/// cache.put("cherry", 7);
+/// assert!(!cache.contains_key("banana"));
+/// assert_eq!(cache.len(), 2);
+///
+/// assert_eq!(cache.pop_lru(), Some(("apple", 3)));
+/// ```
+///
+/// [`get`]: #method.get
+/// [`get_mut`]: #method.get_mut
+/// [`put`]: #method.put
+/// [`peek`]: #method.peek
+/// [`contains_key`]: #method.contains_key
+/// [`pop_lru`]: #method.pop_lru
+#[derive(Clone)]
+pub struct LruCache<K, V, S = RandomState> {
+    hash_builder: S,
+    slots: Slots,
+    pub(crate) nodes: Vec<Node<K, V>>,
+    head: usize,
+    tail: usize,
+    capacity: usize,
+}
+
+/// The slot table maps the hashes of the entries' keys to their positions.
+/// It is only used with hashes from the cache's hash builder, never its own.
+type Slots = HashMap<usize, (), BuildHasherDefault<DefaultHasher>>;
+
+/// The end of the recency list.
+const NIL: usize = !0;
+
+/// An entry, with its position in the recency list.  `prev` is the entry
+/// used more recently, and `next` the one used less recently.
+#[derive(Clone)]
+pub(crate) struct Node<K, V> {
+    pub(crate) key: K,
+    pub(crate) value: V,
+    hash: SafeHash,
+    prev: usize,
+    next: usize,
+}
+
+impl<K: Hash + Eq, V> LruCache<K, V, RandomState> {
+    /// Creates an empty `LruCache` which holds at most `capacity` entries.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rayon_hash::hash_map::LruCache;
+    /// let cache: LruCache<&str, i32> = LruCache::new(10);
+    /// assert_eq!(cache.capacity(), 10);
+    /// ```
+    #[inline]
+    pub fn new(capacity: usize) -> LruCache<K, V, RandomState> {
+        LruCache::with_hasher(capacity, Default::default())
+    }
+}
+
+impl<K, V, S> LruCache<K, V, S> {
+    /// Returns the most entries the cache will hold.
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Returns the number of entries in the cache.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Returns `true` if the cache contains no entries.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Returns a reference to the cache's `BuildHasher`.
+    #[inline]
+    pub fn hasher(&self) -> &S {
+        &self.hash_builder
+    }
+
+    /// Clears the cache, removing all entries.  Keeps the allocated memory
+    /// for reuse.
+    #[inline]
+    pub fn clear(&mut self) {
+        self.slots.clear();
+        self.nodes.clear();
+        self.head = NIL;
+        self.tail = NIL;
+    }
+
+    /// An iterator visiting all key-value pairs, from the most recently used
+    /// to the least.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rayon_hash::hash_map::LruCache;
+    ///
+    /// let mut cache = LruCache::new(3);
+    /// cache.put("a", 1);
+    /// cache.put("b", 2);
+    /// cache.put("c", 3);
+    /// cache.get("a");
+    ///
+    /// let keys: Vec<_> = cache.iter().map(|(&k, _)| k).collect();
+    /// assert_eq!(keys, ["a", "c", "b"]);
+    /// ```
+    #[inline]
+    pub fn iter(&self) -> LruIter<'_, K, V> {
+        LruIter {
+            nodes: &self.nodes,
+            front: self.head,
+            back: self.tail,
+            len: self.nodes.len(),
+        }
+    }
+
+    /// Returns the least recently used entry, without marking it as used.
+    #[inline]
+    pub fn peek_lru(&self) -> Option<(&K, &V)> {
+        self.nodes.get(self.tail).map(|node| (&node.key, &node.value))
+    }
+
+    fn unlink(&mut self, i: usize) {
+        let (prev, next) = (self.nodes[i].prev, self.nodes[i].next);
+        match prev {
+            NIL => self.head = next,
+            prev => self.nodes[prev].next = next,
+        }
+        match next {
+            NIL => self.tail = prev,
+            next => self.nodes[next].prev = prev,
+        }
+    }
+
+    fn push_front(&mut self, i: usize) {
+        let head = self.head;
+        self.nodes[i].prev = NIL;
+        self.nodes[i].next = head;
+        match head {
+            NIL => self.tail = i,
+            head => self.nodes[head].prev = i,
+        }
+        self.head = i;
+    }
+
+    fn touch(&mut self, i: usize) {
+        if self.head != i {
+            self.unlink(i);
+            self.push_front(i);
+        }
+    }
+
+    /// Removes an entry from the list, the slots and the `Vec`.  The last
+    /// entry takes its place.
+    fn remove_node(&mut self, i: usize) -> (K, V) {
+        self.unlink(i);
+        self.slots.remove_hashed_with(self.nodes[i].hash, |&j| j == i);
+
+        let last = self.nodes.len() - 1;
+        if i != last {
+            let hash = self.nodes[last].hash;
+            if let Some((j, _)) = self.slots.find_hashed_mut_with(hash, |&j| j == last) {
+                *j = i;
+            }
+        }
+        let node = self.nodes.swap_remove(i);
+
+        if i != last {
+            let (prev, next) = (self.nodes[i].prev, self.nodes[i].next);
+            match prev {
+                NIL => self.head = i,
+                prev => self.nodes[prev].next = i,
+            }
+            match next {
+                NIL => self.tail = i,
+                next => self.nodes[next].prev = i,
+            }
+        }
+        (node.key, node.value)
+    }
+
+    /// Removes the least recently used entry, and returns it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rayon_hash::hash_map::LruCache;
+    ///
+    /// let mut cache = LruCache::new(2);
+    /// cache.put(1, "one");
+    /// cache.put(2, "two");
+    /// assert_eq!(cache.pop_lru(), Some((1, "one")));
+    /// assert_eq!(cache.pop_lru(), Some((2, "two")));
+    /// assert_eq!(cache.pop_lru(), None);
+    /// ```
+    pub fn pop_lru(&mut self) -> Option<(K, V)> {
+        match self.tail {
+            NIL => None,
+            tail => Some(self.remove_node(tail)),
+        }
+    }
+
+    /// Changes the capacity of the cache, evicting the least recently used
+    /// entries if there are more than the new capacity.
+    pub fn set_capacity(&mut self, capacity: usize) {
+        while self.nodes.len() > capacity {
+            self.pop_lru();
+        }
+        self.capacity = capacity;
+    }
+
+    /// Retains only the entries specified by the predicate, keeping their
+    /// order of use.
+    pub fn retain<F>(&mut self, mut f: F)
+        where F: FnMut(&K, &mut V) -> bool
+    {
+        let keep: Vec<bool> = self.nodes
+            .iter_mut()
+            .map(|node| f(&node.key, &mut node.value))
+            .collect();
+        self.retain_nodes(&keep);
+    }
+
+    /// Keeps the entries whose positions are `true` in `keep`, and rebuilds
+    /// the list and slots for those that are left.
+    pub(crate) fn retain_nodes(&mut self, keep: &[bool]) {
+        if keep.iter().all(|&k| k) {
+            return;
+        }
+
+        // Move the kept entries back into the `Vec` in order of use, so each
+        // one's neighbours in the list are also its neighbours in the `Vec`.
+        let mut old: Vec<_> = self.nodes.drain(..).map(Some).collect();
+        let mut i = self.head;
+        while i != NIL {
+            let node = old[i].take().expect("entry in the list twice");
+            let kept = keep[i];
+            i = node.next;
+            if kept {
+                self.nodes.push(node);
+            }
+        }
+        self.relink();
+    }
+
+    fn relink(&mut self) {
+        let len = self.nodes.len();
+        self.slots.clear();
+        self.slots.reserve(len);
+        for (i, node) in self.nodes.iter_mut().enumerate() {
+            node.prev = if i == 0 { NIL } else { i - 1 };
+            node.next = if i + 1 == len { NIL } else { i + 1 };
+            if let Entry::Vacant(entry) = self.slots.entry_hashed(node.hash, i) {
+                entry.insert(());
+            }
+        }
+        self.head = if len == 0 { NIL } else { 0 };
+        self.tail = if len == 0 { NIL } else { len - 1 };
+    }
+}
+
+impl<K, V, S> LruCache<K, V, S>
+    where K: Eq + Hash,
+          S: BuildHasher
+{
+    /// Creates an empty `LruCache` which holds at most `capacity` entries,
+    /// using `hash_builder` to hash the keys.
+    ///
+    /// Memory is allocated as entries are added, not all up front.
+    #[inline]
+    pub fn with_hasher(capacity: usize, hash_builder: S) -> LruCache<K, V, S> {
+        LruCache {
+            hash_builder,
+            slots: Slots::default(),
+            nodes: Vec::new(),
+            head: NIL,
+            tail: NIL,
+            capacity,
+        }
+    }
+
+    fn find<Q>(&self, key: &Q) -> Option<usize>
+        where K: Borrow<Q>,
+              Q: ?Sized + Hash + Eq
+    {
+        let nodes = &self.nodes;
+        self.slots
+            .find_hashed_with(make_hash(&self.hash_builder, key),
+                              |&i| key.eq(nodes[i].key.borrow()))
+            .map(|(&i, _)| i)
+    }
+
+    /// Returns a reference to the value of a key, and marks it as the most
+    /// recently used.
+    ///
+    /// The key may be any borrowed form of the cache's key type, but
+    /// [`Hash`] and [`Eq`] on the borrowed form *must* match those for
+    /// the key type.
+    ///
+    /// [`Eq`]: ../../std/cmp/trait.Eq.html
+    /// [`Hash`]: ../../std/hash/trait.Hash.html
+    #[inline]
+    pub fn get<Q>(&mut self, key: &Q) -> Option<&V>
+        where K: Borrow<Q>,
+              Q: ?Sized + Hash + Eq
+    {
+        let i = self.find(key)?;
+        self.touch(i);
+        Some(&self.nodes[i].value)
+    }
+
+    /// Returns a mutable reference to the value of a key, and marks it as
+    /// the most recently used.
+    #[inline]
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+        where K: Borrow<Q>,
+              Q: ?Sized + Hash + Eq
+    {
+        let i = self.find(key)?;
+        self.touch(i);
+        Some(&mut self.nodes[i].value)
+    }
+
+    /// Returns a reference to the value of a key, without marking it as
+    /// used.
+    #[inline]
+    pub fn peek<Q>(&self, key: &Q) -> Option<&V>
+        where K: Borrow<Q>,
+              Q: ?Sized + Hash + Eq
+    {
+        self.find(key).map(|i| &self.nodes[i].value)
+    }
+
+    /// Returns `true` if the cache contains the key.  This doesn't mark it
+    /// as used.
+    #[inline]
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+        where K: Borrow<Q>,
+              Q: ?Sized + Hash + Eq
+    {
+        self.find(key).is_some()
+    }
+
+    /// Inserts a key-value pair as the most recently used entry, evicting
+    /// the least recently used one if the cache is full.
+    ///
+    /// If the key was already present, its value is replaced and returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rayon_hash::hash_map::LruCache;
+    ///
+    /// let mut cache = LruCache::new(2);
+    /// assert_eq!(cache.put("a", 1), None);
+    /// assert_eq!(cache.put("a", 2), Some(1));
+    /// assert_eq!(cache.peek("a"), Some(&2));
+    /// ```
+    pub fn put(&mut self, key: K, value: V) -> Option<V> {
+        match self.find(&key) {
+            Some(i) => {
+                self.touch(i);
+                Some(mem::replace(&mut self.nodes[i].value, value))
+            }
+            None => {
+                self.push_new(key, value);
+                None
+            }
+        }
+    }
+
+    /// Like `put`, but returns the entry that was pushed out: either the
+    /// key with its old value, or the evicted least recently used entry.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rayon_hash::hash_map::LruCache;
+    ///
+    /// let mut cache = LruCache::new(2);
+    /// assert_eq!(cache.push("a", 1), None);
+    /// assert_eq!(cache.push("b", 2), None);
+    /// assert_eq!(cache.push("a", 3), Some(("a", 1)));
+    /// assert_eq!(cache.push("c", 4), Some(("b", 2)));
+    /// ```
+    pub fn push(&mut self, key: K, value: V) -> Option<(K, V)> {
+        match self.find(&key) {
+            Some(i) => {
+                self.touch(i);
+                Some((key, mem::replace(&mut self.nodes[i].value, value)))
+            }
+            None => self.push_new(key, value),
+        }
+    }
+
+    /// Adds a key that isn't in the cache, and returns the evicted entry, if
+    /// any.  With a capacity of zero, the new entry is itself evicted.
+    fn push_new(&mut self, key: K, value: V) -> Option<(K, V)> {
+        if self.capacity == 0 {
+            return Some((key, value));
+        }
+        let evicted = if self.nodes.len() >= self.capacity {
+            self.pop_lru()
+        } else {
+            None
+        };
+
+        let hash = make_hash(&self.hash_builder, &key);
+        let i = self.nodes.len();
+        if let Entry::Vacant(entry) = self.slots.entry_hashed(hash, i) {
+            entry.insert(());
+        }
+        self.nodes.push(Node { key, value, hash, prev: NIL, next: NIL });
+        self.push_front(i);
+        evicted
+    }
+
+    /// Removes a key from the cache, returning its value.
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+        where K: Borrow<Q>,
+              Q: ?Sized + Hash + Eq
+    {
+        let i = self.find(key)?;
+        Some(self.remove_node(i).1)
+    }
+}
+
+impl<K, V, S> fmt::Debug for LruCache<K, V, S>
+    where K: fmt::Debug,
+          V: fmt::Debug
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<K, V, S> Extend<(K, V)> for LruCache<K, V, S>
+    where K: Eq + Hash,
+          S: BuildHasher
+{
+    /// Puts each pair in turn, so the last ones are the most recently used.
+    fn extend<T: IntoIterator<Item = (K, V)>>(&mut self, iter: T) {
+        for (key, value) in iter {
+            self.put(key, value);
+        }
+    }
+}
+
+impl<'a, K, V, S> IntoIterator for &'a LruCache<K, V, S> {
+    type Item = (&'a K, &'a V);
+    type IntoIter = LruIter<'a, K, V>;
+
+    fn into_iter(self) -> LruIter<'a, K, V> {
+        self.iter()
+    }
+}
+
+/// An iterator over the entries of an `LruCache`, from the most recently
+/// used to the least.
+///
+/// This `struct` is created by the [`iter`] method on [`LruCache`].
+///
+/// [`iter`]: struct.LruCache.html#method.iter
+/// [`LruCache`]: struct.LruCache.html
+pub struct LruIter<'a, K: 'a, V: 'a> {
+    nodes: &'a [Node<K, V>],
+    front: usize,
+    back: usize,
+    len: usize,
+}
+
+impl<K, V> Clone for LruIter<'_, K, V> {
+    fn clone(&self) -> Self {
+        LruIter { ..*self }
+    }
+}
+
+impl<'a, K, V> Iterator for LruIter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<(&'a K, &'a V)> {
+        if self.len == 0 {
+            return None;
+        }
+        let node = &self.nodes[self.front];
+        self.front = node.next;
+        self.len -= 1;
+        Some((&node.key, &node.value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<'a, K, V> DoubleEndedIterator for LruIter<'a, K, V> {
+    fn next_back(&mut self) -> Option<(&'a K, &'a V)> {
+        if self.len == 0 {
+            return None;
+        }
+        let node = &self.nodes[self.back];
+        self.back = node.prev;
+        self.len -= 1;
+        Some((&node.key, &node.value))
+    }
+}
+
+impl<K, V> ExactSizeIterator for LruIter<'_, K, V> {
+    fn len(&self) -> usize {
+        self.len
+    }
+}
+
+impl<K, V> FusedIterator for LruIter<'_, K, V> {}
+
+impl<K: fmt::Debug, V: fmt::Debug> fmt::Debug for LruIter<'_, K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.clone()).finish()
+    }
+}
+
+#[cfg(test)]
+mod test_lru {
+    use super::LruCache;
+
+    fn keys(cache: &LruCache<u32, u32>) -> Vec<u32> {
+        cache.iter().map(|(&k, _)| k).collect()
+    }
+
+    #[test]
+    fn test_recency() {
+        let mut cache = LruCache::new(3);
+        for i in 0..3 {
+            assert_eq!(cache.push(i, i * 10), None);
+        }
+        assert_eq!(keys(&cache), [2, 1, 0]);
+
+        assert_eq!(cache.get(&0), Some(&0));
+        assert_eq!(keys(&cache), [0, 2, 1]);
+        assert_eq!(cache.peek(&1), Some(&10));
+        assert_eq!(keys(&cache), [0, 2, 1]);
+
+        assert_eq!(cache.push(3, 30), Some((1, 10)));
+        assert_eq!(keys(&cache), [3, 0, 2]);
+        assert_eq!(cache.put(2, 21), Some(20));
+        assert_eq!(keys(&cache), [2, 3, 0]);
+        assert_eq!(cache.iter().rev().map(|(&k, _)| k).collect::<Vec<_>>(), [0, 3, 2]);
+
+        assert_eq!(cache.remove(&3), Some(30));
+        assert_eq!(keys(&cache), [2, 0]);
+        assert_eq!(cache.peek_lru(), Some((&0, &0)));
+        *cache.get_mut(&0).unwrap() += 1;
+        assert_eq!(cache.pop_lru(), Some((2, 21)));
+        assert_eq!(cache.pop_lru(), Some((0, 1)));
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn test_churn() {
+        let mut cache = LruCache::new(100);
+        for i in 0..10_000u32 {
+            cache.put(i, i);
+            if i % 3 == 0 {
+                cache.get(&(i / 2));
+            }
+            if i % 7 == 0 {
+                cache.remove(&(i - i / 50));
+            }
+            assert!(cache.len() <= 100);
+        }
+        for (&k, &v) in &cache {
+            assert_eq!(k, v);
+            assert_eq!(cache.peek(&k), Some(&v));
+        }
+        assert_eq!(cache.iter().count(), cache.len());
+        assert_eq!(cache.iter().rev().count(), cache.len());
+
+        cache.set_capacity(10);
+        assert_eq!(cache.len(), 10);
+        assert_eq!(keys(&cache), (9990..10_000).rev().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_retain() {
+        let mut cache = LruCache::new(10);
+        cache.extend((0..10).map(|i| (i, i)));
+        cache.get(&4);
+        cache.retain(|&k, v| {
+            *v += 1;
+            k % 2 == 0
+        });
+        assert_eq!(keys(&cache), [4, 8, 6, 2, 0]);
+        assert!(cache.iter().all(|(&k, &v)| v == k + 1));
+        assert!(!cache.contains_key(&3));
+
+        cache.put(11, 0);
+        assert_eq!(cache.pop_lru(), Some((0, 1)));
+        assert_eq!(keys(&cache), [11, 4, 8, 6, 2]);
+    }
+
+    #[test]
+    fn test_zero_capacity() {
+        let mut cache = LruCache::new(0);
+        assert_eq!(cache.push("a", 1), Some(("a", 1)));
+        assert_eq!(cache.put("b", 2), None);
+        assert!(cache.is_empty());
+    }
+}
@@ -7,9 +7,7 @@ use crate::intrinsics::unlikely;
 use std::borrow::Borrow;
 use std::cmp::max;
 use std::fmt::{self, Debug};
-use std::hash::{Hash, BuildHasher};
-#[cfg(rayon_hash_unstable)]
-use std::hash::Hasher;
+use std::hash::{Hash, BuildHasher, Hasher};
 use std::iter::{FromIterator, FusedIterator};
 use std::mem::{self, replace};
 use std::ops::{Deref, Index};
@@ -539,7 +537,7 @@ fn search_hashed_nonempty_mut<K, V, M, F>(table: M, hash: SafeHash, mut is_match
     }
 }
 
-fn pop_internal<K, V>(starting_bucket: FullBucketMut<'_, K, V>)
+pub(crate) fn pop_internal<K, V>(starting_bucket: FullBucketMut<'_, K, V>)
     -> (K, V, &mut RawTable<K, V>)
 {
     let (empty, retkey, retval) = starting_bucket.take();
@@ -746,6 +744,21 @@ impl<K, V, S> HashMap<K, V, S> {
         self.table.capacity()
     }
 
+    /// Splits the map into its hash builder and raw table.
+    pub(crate) fn into_raw_parts(self) -> (S, RawTable<K, V>) {
+        (self.hash_builder, self.table)
+    }
+
+    /// Reassembles a map from a hash builder and a raw table whose entries
+    /// were all placed using hashes from that same builder.
+    pub(crate) fn from_raw_parts(hash_builder: S, table: RawTable<K, V>) -> Self {
+        HashMap {
+            hash_builder,
+            table,
+            resize_policy: DefaultResizePolicy::new(),
+        }
+    }
+
     /// An iterator visiting all keys in arbitrary order.
     /// The iterator element type is `&'a K`.
     ///
@@ -1281,6 +1294,102 @@ impl<K, V, S> HashMap<K, V, S>
             .into_entry(key).expect("unreachable")
     }
 
+    /// Gets the entry for a key that was already hashed with this map's
+    /// hash builder.
+    pub(crate) fn entry_hashed(&mut self, hash: SafeHash, key: K) -> Entry<'_, K, V> {
+        self.reserve(1);
+        search_hashed(&mut self.table, hash, |q| q.eq(&key))
+            .into_entry(key).expect("unreachable")
+    }
+
+    /// Like `entry_hashed(hash, key).or_insert_with(default)`, but also
+    /// returns a reference to the key in the map.
+    pub(crate) fn get_or_insert_hashed_with<F>(&mut self, hash: SafeHash, key: K, default: F)
+        -> (&K, &mut V)
+        where F: FnOnce() -> V
+    {
+        match self.entry_hashed(hash, key) {
+            Occupied(entry) => entry.into_refs_mut(),
+            Vacant(entry) => {
+                let (key, value) = entry.insert_refs(default());
+                (key, value)
+            }
+        }
+    }
+
+    /// Looks up a key that was already hashed with this map's hash builder.
+    pub(crate) fn get_hashed<Q>(&self, hash: SafeHash, q: &Q) -> Option<(&K, &V)>
+        where K: Borrow<Q>,
+              Q: ?Sized + Eq
+    {
+        self.find_hashed_with(hash, |k| q.eq(k.borrow()))
+    }
+
+    /// Returns the index of the bucket holding a key that was already hashed
+    /// with this map's hash builder.
+    pub(crate) fn bucket_index_hashed<Q>(&self, hash: SafeHash, q: &Q) -> Option<usize>
+        where K: Borrow<Q>,
+              Q: ?Sized + Eq
+    {
+        if self.is_empty() {
+            return None;
+        }
+        search_hashed_nonempty(&self.table, hash, |k| q.eq(k.borrow()), true)
+            .into_occupied_bucket()
+            .map(|bucket| bucket.index())
+    }
+
+    /// Removes a key that was already hashed with this map's hash builder.
+    pub(crate) fn remove_hashed<Q>(&mut self, hash: SafeHash, q: &Q) -> Option<(K, V)>
+        where K: Borrow<Q>,
+              Q: ?Sized + Eq
+    {
+        self.remove_hashed_with(hash, |k| q.eq(k.borrow()))
+    }
+
+    /// Looks up an entry by its hash, using `is_match` to compare the keys
+    /// with the same hash.
+    pub(crate) fn find_hashed_with<F>(&self, hash: SafeHash, is_match: F) -> Option<(&K, &V)>
+        where F: FnMut(&K) -> bool
+    {
+        if self.is_empty() {
+            return None;
+        }
+        search_hashed_nonempty(&self.table, hash, is_match, true)
+            .into_occupied_bucket()
+            .map(|bucket| bucket.into_refs())
+    }
+
+    /// Like `find_hashed_with`, but with mutable access to the key too.  The
+    /// key must not be changed in a way that affects its hash.
+    pub(crate) fn find_hashed_mut_with<F>(&mut self, hash: SafeHash, is_match: F)
+        -> Option<(&mut K, &mut V)>
+        where F: FnMut(&K) -> bool
+    {
+        if self.is_empty() {
+            return None;
+        }
+        search_hashed_nonempty(&mut self.table, hash, is_match, true)
+            .into_occupied_bucket()
+            .map(|bucket| bucket.into_mut_refs())
+    }
+
+    /// Removes an entry by its hash, using `is_match` to compare the keys
+    /// with the same hash.
+    pub(crate) fn remove_hashed_with<F>(&mut self, hash: SafeHash, is_match: F) -> Option<(K, V)>
+        where F: FnMut(&K) -> bool
+    {
+        if self.is_empty() {
+            return None;
+        }
+        search_hashed_nonempty(&mut self.table, hash, is_match, true)
+            .into_occupied_bucket()
+            .map(|bucket| {
+                let (k, v, _) = pop_internal(bucket);
+                (k, v)
+            })
+    }
+
     /// Returns a reference to the value corresponding to the key.
     ///
     /// The key may be any borrowed form of the map's key type, but
@@ -1534,6 +1643,41 @@ impl<K, V, S> HashMap<K, V, S>
             debug_assert!(elems_left == 0 || bucket.index() != start_index);
         }
     }
+
+    /// Removes the entries in the buckets at `indices`, dropping their keys
+    /// but not their values, which must have been moved out already.
+    ///
+    /// Like `retain`, this walks the buckets backwards from one that isn't
+    /// displaced, so a removal only shifts entries that were already
+    /// visited, and the other indices still find their buckets.
+    pub(crate) unsafe fn remove_moved_out(&mut self, indices: &[usize]) {
+        let mut left = indices.len();
+        if left == 0 {
+            return;
+        }
+        let mut bucket = Bucket::head_bucket(&mut self.table);
+        bucket.prev();
+        while left != 0 {
+            bucket = match bucket.peek() {
+                Full(full) => {
+                    if indices.contains(&full.index()) {
+                        left -= 1;
+                        let prev_raw = full.raw();
+                        let (key, value, t) = pop_internal(full);
+                        drop(key);
+                        mem::forget(value);
+                        Bucket::new_from(prev_raw, t)
+                    } else {
+                        full.into_bucket()
+                    }
+                },
+                Empty(b) => {
+                    b.into_bucket()
+                }
+            };
+            bucket.prev();  // reverse iteration
+        }
+    }
 }
 
 impl<K, V, S> HashMap<K, V, S>
@@ -1622,6 +1766,30 @@ impl<K, V, S> Eq for HashMap<K, V, S>
 {
 }
 
+/// Hashes the map's entries independently of their order in the table, so
+/// that maps which compare equal also hash equally.
+///
+/// Each entry is hashed separately with a fixed-key `DefaultHasher`, and
+/// these are combined with a commutative wrapping sum.  The state is then
+/// fed the length and that sum.
+impl<K, V, S> Hash for HashMap<K, V, S>
+    where K: Hash,
+          V: Hash
+{
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        let sum = self.iter().map(entry_hash).fold(0, u64::wrapping_add);
+        state.write_usize(self.len());
+        state.write_u64(sum);
+    }
+}
+
+/// Hashes one entry for the order-independent `Hash` of a map.
+pub(crate) fn entry_hash<T: Hash>(entry: T) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    entry.hash(&mut hasher);
+    hasher.finish()
+}
+
 // #[stable(feature = "rust1", since = "1.0.0")]
 impl<K, V, S> Debug for HashMap<K, V, S>
     where K: Eq + Hash + Debug,
@@ -2895,6 +3063,13 @@ impl<'a, K, V> OccupiedEntry<'a, K, V> {
         self.elem.into_mut_refs().1
     }
 
+    /// Converts the entry into references to its key and value, with a
+    /// lifetime bound to the map itself.
+    pub(crate) fn into_refs_mut(self) -> (&'a K, &'a mut V) {
+        let (key, value) = self.elem.into_mut_refs();
+        (key, value)
+    }
+
     /// Sets the value of the entry, and returns the entry's old value.
     ///
     /// # Examples
@@ -3063,6 +3238,10 @@ impl<'a, K: 'a, V: 'a> VacantEntry<'a, K, V> {
     /// ```
     // #[stable(feature = "rust1", since = "1.0.0")]
     pub fn insert(self, value: V) -> &'a mut V {
+        self.insert_refs(value).1
+    }
+
+    fn insert_refs(self, value: V) -> (&'a mut K, &'a mut V) {
         let b = match self.elem {
             NeqElem(mut bucket, disp) => {
                 if disp >= DISPLACEMENT_THRESHOLD {
@@ -3077,7 +3256,7 @@ impl<'a, K: 'a, V: 'a> VacantEntry<'a, K, V> {
                 bucket.put(self.hash, self.key, value)
             },
         };
-        b.into_mut_refs().1
+        b.into_mut_refs()
     }
 }
 
@@ -3200,10 +3379,11 @@ fn assert_covariance() {
 
 #[cfg(test)]
 mod test_map {
-    use super::HashMap;
+    use super::{DefaultHasher, HashMap};
     use super::Entry::{Occupied, Vacant};
     use super::RandomState;
     use std::cell::RefCell;
+    use std::hash::{Hash, Hasher};
     use rand::{thread_rng, Rng};
     #[cfg(rayon_hash_unstable)] use crate::alloc::CollectionAllocErr::*;
     #[cfg(rayon_hash_unstable)] use std::mem::size_of;
@@ -3685,6 +3865,36 @@ mod test_map {
         assert_eq!(m1, m2);
     }
 
+    #[test]
+    fn test_hash() {
+        fn hash<T: Hash>(t: &T) -> u64 {
+            let mut hasher = DefaultHasher::new();
+            t.hash(&mut hasher);
+            hasher.finish()
+        }
+
+        let mut m1 = HashMap::new();
+        for i in 0..100 {
+            m1.insert(i, i * 2);
+        }
+
+        let mut m2 = HashMap::with_capacity(1000);
+        for i in (0..100).rev() {
+            m2.insert(i, i * 2);
+        }
+
+        assert_eq!(m1, m2);
+        assert_eq!(hash(&m1), hash(&m2));
+
+        m2.insert(0, 1);
+        assert!(hash(&m1) != hash(&m2));
+
+        let mut outer = HashMap::new();
+        outer.insert(m1.clone(), "m1");
+        assert_eq!(outer.get(&m1), Some(&"m1"));
+        assert_eq!(outer.get(&m2), None);
+    }
+
     #[test]
     fn test_show() {
         let mut map = HashMap::new();
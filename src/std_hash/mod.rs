@@ -1,8 +1,24 @@
 //! Unordered containers, implemented as hash-tables
 
 pub(crate) mod table;
+pub mod bimap;
+pub mod codec;
+pub mod counter;
+pub mod cursor;
+pub mod fixed;
+pub mod frozen;
+pub mod indexed;
+pub mod interner;
+pub mod lru;
 pub mod map;
+pub mod multimap;
+pub mod sample;
+pub mod scan;
 pub mod set;
+pub mod snapshot;
+pub mod split;
+pub(crate) mod top_k;
+pub mod ttl;
 
 trait Recover<Q: ?Sized> {
     type Key;
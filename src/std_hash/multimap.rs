@@ -0,0 +1,682 @@
+use std::borrow::Borrow;
+use std::fmt;
+use std::hash::{Hash, BuildHasher};
+use std::iter::{FromIterator, FusedIterator};
+use std::mem;
+use std::ops::{Deref, DerefMut};
+use std::slice;
+use std::vec;
+
+use super::map::{self, HashMap, RandomState};
+
+/// A hash multimap implemented as a `HashMap` from each key to the [`Group`]
+/// of its values.
+///
+/// The values of a key are kept in insertion order, and a key with a single
+/// value doesn't allocate anything besides its bucket.  Keys without values
+/// are never stored.
+///
+/// # Examples
+///
+/// ```
+/// use rayon_hash::MultiMap;
+///
+/// let mut authors = MultiMap::new();
+/// authors.insert("Rust", "Graydon");
+/// authors.insert("Go", "Rob");
+/// authors.insert("Go", "Ken");
+///
+/// assert_eq!(authors.get_all("Go"), ["Rob", "Ken"]);
+/// assert_eq!(authors.get_all("C"), [] as [&str; 0]);
+/// assert_eq!(authors.len(), 3);
+/// assert_eq!(authors.keys_len(), 2);
+/// ```
+///
+/// [`Group`]: struct.Group.html
+#[derive(Clone)]
+pub struct MultiMap<K, V, S = RandomState> {
+    pub(crate) map: HashMap<K, Group<V>, S>,
+    pub(crate) len: usize,
+}
+
+/// The values of one key in a [`MultiMap`], in insertion order.  It
+/// dereferences to a slice of the values.
+///
+/// A single value is stored inline, and only a second value moves the group
+/// into a `Vec`.
+///
+/// [`MultiMap`]: struct.MultiMap.html
+#[derive(Clone)]
+pub struct Group<V> {
+    repr: GroupRepr<V>,
+}
+
+#[derive(Clone)]
+enum GroupRepr<V> {
+    One(V),
+    // Only empty while a group is being built.
+    Many(Vec<V>),
+}
+
+impl<V> Group<V> {
+    pub(crate) fn new() -> Group<V> {
+        Group { repr: GroupRepr::Many(Vec::new()) }
+    }
+
+    pub(crate) fn one(value: V) -> Group<V> {
+        Group { repr: GroupRepr::One(value) }
+    }
+
+    pub(crate) fn push(&mut self, value: V) {
+        match self.repr {
+            GroupRepr::Many(ref mut values) if !values.is_empty() => values.push(value),
+            GroupRepr::Many(_) => self.repr = GroupRepr::One(value),
+            GroupRepr::One(_) => {
+                let first = self.take_vec();
+                let mut values = Vec::with_capacity(2);
+                values.extend(first);
+                values.push(value);
+                self.repr = GroupRepr::Many(values);
+            }
+        }
+    }
+
+    /// Moves all values of `other` to the end of this group.
+    pub(crate) fn append(&mut self, other: Group<V>) {
+        if self.is_empty() {
+            *self = other;
+        } else {
+            let mut values = self.take_vec();
+            values.extend(other);
+            self.repr = GroupRepr::Many(values);
+        }
+    }
+
+    pub(crate) fn retain<F>(&mut self, mut f: F)
+        where F: FnMut(&mut V) -> bool
+    {
+        // Like `Vec::retain`, but lending out mutable references.  Kept
+        // values are swapped forward, so they stay in order, and nothing is
+        // removed until all calls to `f` are done, in case one panics.
+        let kept = match self.repr {
+            GroupRepr::One(ref mut value) => f(value) as usize,
+            GroupRepr::Many(ref mut values) => {
+                let mut kept = 0;
+                for i in 0..values.len() {
+                    if f(&mut values[i]) {
+                        values.swap(kept, i);
+                        kept += 1;
+                    }
+                }
+                kept
+            }
+        };
+        if kept < self.len() {
+            let mut values = self.take_vec();
+            values.truncate(kept);
+            if values.len() == 1 {
+                self.repr = GroupRepr::One(values.pop().unwrap());
+            } else {
+                self.repr = GroupRepr::Many(values);
+            }
+        }
+    }
+
+    fn take_vec(&mut self) -> Vec<V> {
+        match mem::replace(&mut self.repr, GroupRepr::Many(Vec::new())) {
+            GroupRepr::One(value) => vec![value],
+            GroupRepr::Many(values) => values,
+        }
+    }
+
+    /// Returns the values as a slice.
+    pub fn as_slice(&self) -> &[V] {
+        match self.repr {
+            GroupRepr::One(ref value) => slice::from_ref(value),
+            GroupRepr::Many(ref values) => values,
+        }
+    }
+
+    /// Returns the values as a mutable slice.
+    pub fn as_mut_slice(&mut self) -> &mut [V] {
+        match self.repr {
+            GroupRepr::One(ref mut value) => slice::from_mut(value),
+            GroupRepr::Many(ref mut values) => values,
+        }
+    }
+
+    /// Consumes the group, returning its values in a `Vec`.
+    pub fn into_vec(self) -> Vec<V> {
+        match self.repr {
+            GroupRepr::One(value) => vec![value],
+            GroupRepr::Many(values) => values,
+        }
+    }
+}
+
+impl<V> Deref for Group<V> {
+    type Target = [V];
+
+    fn deref(&self) -> &[V] {
+        self.as_slice()
+    }
+}
+
+impl<V> DerefMut for Group<V> {
+    fn deref_mut(&mut self) -> &mut [V] {
+        self.as_mut_slice()
+    }
+}
+
+impl<V: fmt::Debug> fmt::Debug for Group<V> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.as_slice().fmt(f)
+    }
+}
+
+impl<V: PartialEq> PartialEq for Group<V> {
+    fn eq(&self, other: &Group<V>) -> bool {
+        self.as_slice() == other.as_slice()
+    }
+}
+
+impl<V: Eq> Eq for Group<V> {}
+
+impl<'a, V> IntoIterator for &'a Group<V> {
+    type Item = &'a V;
+    type IntoIter = slice::Iter<'a, V>;
+
+    fn into_iter(self) -> slice::Iter<'a, V> {
+        self.as_slice().iter()
+    }
+}
+
+impl<V> IntoIterator for Group<V> {
+    type Item = V;
+    type IntoIter = vec::IntoIter<V>;
+
+    fn into_iter(self) -> vec::IntoIter<V> {
+        self.into_vec().into_iter()
+    }
+}
+
+impl<K: Hash + Eq, V> MultiMap<K, V, RandomState> {
+    /// Creates an empty `MultiMap`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rayon_hash::MultiMap;
+    /// let map: MultiMap<&str, i32> = MultiMap::new();
+    /// ```
+    #[inline]
+    pub fn new() -> MultiMap<K, V, RandomState> {
+        Default::default()
+    }
+
+    /// Creates an empty `MultiMap` with room for at least `capacity` keys.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rayon_hash::MultiMap;
+    /// let map: MultiMap<&str, i32> = MultiMap::with_capacity(10);
+    /// assert!(map.capacity() >= 10);
+    /// ```
+    #[inline]
+    pub fn with_capacity(capacity: usize) -> MultiMap<K, V, RandomState> {
+        MultiMap::with_capacity_and_hasher(capacity, Default::default())
+    }
+}
+
+impl<K, V, S> MultiMap<K, V, S> {
+    /// Returns the number of keys the map can hold without reallocating.
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.map.capacity()
+    }
+
+    /// Returns the number of values in the map, counting every value of
+    /// every key.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns the number of distinct keys in the map.
+    #[inline]
+    pub fn keys_len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Returns `true` if the map contains no values.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Clears the map, removing all keys and values.  Keeps the allocated
+    /// memory for reuse.
+    #[inline]
+    pub fn clear(&mut self) {
+        self.map.clear();
+        self.len = 0;
+    }
+
+    /// An iterator visiting all key-value pairs in arbitrary order, with
+    /// the values of each key together in insertion order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rayon_hash::MultiMap;
+    ///
+    /// let map: MultiMap<_, _> = vec![("a", 1), ("b", 2), ("a", 3)].into_iter().collect();
+    /// let mut pairs: Vec<_> = map.iter().collect();
+    /// pairs.sort();
+    /// assert_eq!(pairs, [(&"a", &1), (&"a", &3), (&"b", &2)]);
+    /// ```
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        Iter {
+            groups: self.map.iter(),
+            current: None,
+            remaining: self.len,
+        }
+    }
+
+    /// An iterator visiting all keys and the groups of their values in
+    /// arbitrary order.
+    #[inline]
+    pub fn iter_groups(&self) -> map::Iter<'_, K, Group<V>> {
+        self.map.iter()
+    }
+
+    /// An iterator visiting all distinct keys in arbitrary order.
+    #[inline]
+    pub fn keys(&self) -> map::Keys<'_, K, Group<V>> {
+        self.map.keys()
+    }
+
+    /// Returns a reference to the underlying map of groups.
+    #[inline]
+    pub fn as_map(&self) -> &HashMap<K, Group<V>, S> {
+        &self.map
+    }
+}
+
+impl<K, V, S> MultiMap<K, V, S>
+    where K: Eq + Hash,
+          S: BuildHasher
+{
+    /// Creates an empty `MultiMap` which will use the given hash builder to
+    /// hash keys.
+    #[inline]
+    pub fn with_hasher(hash_builder: S) -> MultiMap<K, V, S> {
+        MultiMap { map: HashMap::with_hasher(hash_builder), len: 0 }
+    }
+
+    /// Creates an empty `MultiMap` with room for at least `capacity` keys,
+    /// using `hash_builder` to hash them.
+    #[inline]
+    pub fn with_capacity_and_hasher(capacity: usize, hash_builder: S) -> MultiMap<K, V, S> {
+        MultiMap { map: HashMap::with_capacity_and_hasher(capacity, hash_builder), len: 0 }
+    }
+
+    /// Returns a reference to the map's `BuildHasher`.
+    #[inline]
+    pub fn hasher(&self) -> &S {
+        self.map.hasher()
+    }
+
+    /// Adds a value to the end of the key's values.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rayon_hash::MultiMap;
+    ///
+    /// let mut map = MultiMap::new();
+    /// map.insert(1, "a");
+    /// map.insert(1, "b");
+    /// assert_eq!(map.get_all(&1), ["a", "b"]);
+    /// ```
+    pub fn insert(&mut self, key: K, value: V) {
+        self.map.entry(key).or_insert_with(Group::new).push(value);
+        self.len += 1;
+    }
+
+    /// Returns a reference to the first value of the key.
+    ///
+    /// The key may be any borrowed form of the map's key type, but
+    /// [`Hash`] and [`Eq`] on the borrowed form *must* match those for
+    /// the key type.
+    ///
+    /// [`Eq`]: ../../std/cmp/trait.Eq.html
+    /// [`Hash`]: ../../std/hash/trait.Hash.html
+    #[inline]
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+        where K: Borrow<Q>,
+              Q: ?Sized + Hash + Eq
+    {
+        self.map.get(key).map(|group| &group[0])
+    }
+
+    /// Returns all values of the key in insertion order, or an empty slice
+    /// if the key isn't present.
+    #[inline]
+    pub fn get_all<Q>(&self, key: &Q) -> &[V]
+        where K: Borrow<Q>,
+              Q: ?Sized + Hash + Eq
+    {
+        self.map.get(key).map_or(&[], Group::as_slice)
+    }
+
+    /// Returns all values of the key mutably, or an empty slice if the key
+    /// isn't present.
+    #[inline]
+    pub fn get_all_mut<Q>(&mut self, key: &Q) -> &mut [V]
+        where K: Borrow<Q>,
+              Q: ?Sized + Hash + Eq
+    {
+        match self.map.get_mut(key) {
+            Some(group) => group.as_mut_slice(),
+            None => &mut [],
+        }
+    }
+
+    /// Returns `true` if the map contains any value for the key.
+    #[inline]
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+        where K: Borrow<Q>,
+              Q: ?Sized + Hash + Eq
+    {
+        self.map.contains_key(key)
+    }
+
+    /// Removes a key from the map, returning all of its values in insertion
+    /// order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rayon_hash::MultiMap;
+    ///
+    /// let mut map = MultiMap::new();
+    /// map.insert(1, "a");
+    /// map.insert(1, "b");
+    /// assert_eq!(map.remove_all(&1), ["a", "b"]);
+    /// assert!(map.remove_all(&1).is_empty());
+    /// assert!(map.is_empty());
+    /// ```
+    pub fn remove_all<Q>(&mut self, key: &Q) -> Vec<V>
+        where K: Borrow<Q>,
+              Q: ?Sized + Hash + Eq
+    {
+        match self.map.remove(key) {
+            Some(group) => {
+                self.len -= group.len();
+                group.into_vec()
+            }
+            None => Vec::new(),
+        }
+    }
+
+    /// Retains only the key-value pairs specified by the predicate, and
+    /// removes the keys that have no values left.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rayon_hash::MultiMap;
+    ///
+    /// let mut map: MultiMap<_, _> = (0..8).map(|i| (i % 3, i)).collect();
+    /// map.retain(|_, &mut v| v % 2 == 0);
+    /// assert_eq!(map.get_all(&0), [0, 6]);
+    /// assert_eq!(map.get_all(&1), [4]);
+    /// assert_eq!(map.get_all(&2), [2]);
+    /// assert_eq!(map.len(), 4);
+    /// ```
+    pub fn retain<F>(&mut self, mut f: F)
+        where F: FnMut(&K, &mut V) -> bool
+    {
+        // The length is kept up to date group by group, so it stays right
+        // if `f` panics.
+        let len = &mut self.len;
+        self.map.retain(|key, group| {
+            let before = group.len();
+            group.retain(|value| f(key, value));
+            *len -= before - group.len();
+            !group.is_empty()
+        });
+    }
+
+    /// Reserves capacity for at least `additional` more keys.
+    #[inline]
+    pub fn reserve(&mut self, additional: usize) {
+        self.map.reserve(additional)
+    }
+
+    /// Shrinks the capacity of the map as much as possible.
+    #[inline]
+    pub fn shrink_to_fit(&mut self) {
+        self.map.shrink_to_fit()
+    }
+}
+
+impl<K, V, S> PartialEq for MultiMap<K, V, S>
+    where K: Eq + Hash,
+          V: PartialEq,
+          S: BuildHasher
+{
+    fn eq(&self, other: &MultiMap<K, V, S>) -> bool {
+        self.len == other.len && self.map == other.map
+    }
+}
+
+impl<K, V, S> Eq for MultiMap<K, V, S>
+    where K: Eq + Hash,
+          V: Eq,
+          S: BuildHasher
+{
+}
+
+impl<K, V, S> fmt::Debug for MultiMap<K, V, S>
+    where K: fmt::Debug,
+          V: fmt::Debug
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_map().entries(self.iter_groups()).finish()
+    }
+}
+
+impl<K, V, S> Default for MultiMap<K, V, S>
+    where K: Eq + Hash,
+          S: BuildHasher + Default
+{
+    /// Creates an empty `MultiMap<K, V, S>`, with the `Default` value for the hasher.
+    fn default() -> MultiMap<K, V, S> {
+        MultiMap::with_hasher(Default::default())
+    }
+}
+
+impl<K, V, S> FromIterator<(K, V)> for MultiMap<K, V, S>
+    where K: Eq + Hash,
+          S: BuildHasher + Default
+{
+    fn from_iter<T: IntoIterator<Item = (K, V)>>(iter: T) -> MultiMap<K, V, S> {
+        let mut map = MultiMap::default();
+        map.extend(iter);
+        map
+    }
+}
+
+impl<K, V, S> Extend<(K, V)> for MultiMap<K, V, S>
+    where K: Eq + Hash,
+          S: BuildHasher
+{
+    fn extend<T: IntoIterator<Item = (K, V)>>(&mut self, iter: T) {
+        for (key, value) in iter {
+            self.insert(key, value);
+        }
+    }
+}
+
+impl<'a, K, V, S> IntoIterator for &'a MultiMap<K, V, S> {
+    type Item = (&'a K, &'a V);
+    type IntoIter = Iter<'a, K, V>;
+
+    fn into_iter(self) -> Iter<'a, K, V> {
+        self.iter()
+    }
+}
+
+/// An iterator over the key-value pairs of a `MultiMap`.
+///
+/// This `struct` is created by the [`iter`] method on [`MultiMap`]. See its
+/// documentation for more.
+///
+/// [`iter`]: struct.MultiMap.html#method.iter
+/// [`MultiMap`]: struct.MultiMap.html
+pub struct Iter<'a, K: 'a, V: 'a> {
+    groups: map::Iter<'a, K, Group<V>>,
+    current: Option<(&'a K, slice::Iter<'a, V>)>,
+    remaining: usize,
+}
+
+impl<K, V> Clone for Iter<'_, K, V> {
+    fn clone(&self) -> Self {
+        Iter {
+            groups: self.groups.clone(),
+            current: self.current.clone(),
+            remaining: self.remaining,
+        }
+    }
+}
+
+impl<'a, K, V> Iterator for Iter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<(&'a K, &'a V)> {
+        loop {
+            if let Some((key, ref mut values)) = self.current {
+                if let Some(value) = values.next() {
+                    self.remaining -= 1;
+                    return Some((key, value));
+                }
+            }
+            let (key, group) = self.groups.next()?;
+            self.current = Some((key, group.iter()));
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<K, V> ExactSizeIterator for Iter<'_, K, V> {
+    fn len(&self) -> usize {
+        self.remaining
+    }
+}
+
+impl<K, V> FusedIterator for Iter<'_, K, V> {}
+
+impl<K: fmt::Debug, V: fmt::Debug> fmt::Debug for Iter<'_, K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.clone()).finish()
+    }
+}
+
+#[cfg(test)]
+mod test_multimap {
+    use super::MultiMap;
+
+    #[test]
+    fn test_insert_get() {
+        let mut map = MultiMap::new();
+        assert!(map.is_empty());
+        map.insert("a", 1);
+        assert_eq!(map.get("a"), Some(&1));
+        assert_eq!(map.get_all("a"), [1]);
+        map.insert("a", 2);
+        map.insert("b", 3);
+        map.insert("a", 4);
+        assert_eq!(map.get("a"), Some(&1));
+        assert_eq!(map.get_all("a"), [1, 2, 4]);
+        assert_eq!(map.get_all("b"), [3]);
+        assert!(map.get_all("c").is_empty());
+        assert_eq!(map.len(), 4);
+        assert_eq!(map.keys_len(), 2);
+
+        for value in map.get_all_mut("a") {
+            *value *= 10;
+        }
+        assert_eq!(map.get_all("a"), [10, 20, 40]);
+        assert!(map.get_all_mut("c").is_empty());
+    }
+
+    #[test]
+    fn test_remove_all() {
+        let mut map: MultiMap<_, _> = (0..10).map(|i| (i % 2, i)).collect();
+        assert_eq!(map.remove_all(&1), [1, 3, 5, 7, 9]);
+        assert!(map.remove_all(&1).is_empty());
+        assert!(!map.contains_key(&1));
+        assert_eq!(map.len(), 5);
+        assert_eq!(map.keys_len(), 1);
+    }
+
+    #[test]
+    fn test_retain() {
+        let mut map: MultiMap<_, _> = (0..100).map(|i| (i % 10, i)).collect();
+        map.retain(|&k, v| {
+            *v += 1;
+            k != 3 && *v % 3 != 0
+        });
+        assert!(!map.contains_key(&3));
+        assert_eq!(map.get_all(&0), [1, 11, 31, 41, 61, 71, 91]);
+        assert_eq!(map.len(), map.iter().count());
+        assert_eq!(map.len(), map.iter_groups().map(|(_, g)| g.len()).sum());
+
+        // A key whose last value is removed goes away.
+        map.retain(|&k, _| k != 0);
+        assert!(!map.contains_key(&0));
+    }
+
+    #[test]
+    fn test_retain_panic() {
+        use std::panic::{self, AssertUnwindSafe};
+
+        let mut map: MultiMap<_, _> = (0..100).map(|i| (i % 10, i)).collect();
+        map.insert(10, 10);
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            map.retain(|_, &mut v| {
+                if v == 55 || v == 10 {
+                    panic!("retain");
+                }
+                v % 2 == 0
+            })
+        }));
+        assert!(result.is_err());
+        assert_eq!(map.len(), map.iter().count());
+        for (_, group) in map.iter_groups() {
+            assert!(!group.is_empty());
+        }
+        assert_eq!(map.get(&10), Some(&10));
+        assert_eq!(map.get_all(&5).len(), 10);
+    }
+
+    #[test]
+    fn test_iter() {
+        let map: MultiMap<_, _> = (0..1000).map(|i| (i % 7, i)).collect();
+        let iter = map.iter();
+        assert_eq!(iter.len(), 1000);
+        let mut seen = vec![false; 1000];
+        for (&k, &v) in iter {
+            assert_eq!(k, v % 7);
+            assert!(!seen[v]);
+            seen[v] = true;
+        }
+        assert!(seen.iter().all(|&s| s));
+    }
+}
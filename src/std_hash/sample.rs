@@ -0,0 +1,171 @@
+#[cfg(feature = "rand_core")]
+use rand_core::RngCore;
+
+#[cfg(feature = "rand_core")]
+use super::map::HashMap;
+use super::table::{Bucket, RawTable};
+use super::table::BucketState::{Empty, Full};
+
+/// Rejection sampling is used while at least one in `DENSE` buckets is
+/// full, so a sample takes `DENSE` tries at most on average.  Sparser
+/// tables, e.g. after many removals, are sampled by the ranks of their
+/// entries instead.
+pub(crate) const DENSE: usize = 4;
+
+/// A small, fast generator for the samples of parallel jobs, each seeded
+/// from the caller's seed and the job's index.
+pub(crate) struct SplitMix64(u64);
+
+const GAMMA: u64 = 0x9e37_79b9_7f4a_7c15;
+
+impl SplitMix64 {
+    /// Returns the generator of the `stream`th job with this seed.
+    pub(crate) fn stream(seed: u64, stream: u64) -> SplitMix64 {
+        let mut seeder = SplitMix64(seed.wrapping_add(stream.wrapping_mul(GAMMA)));
+        SplitMix64(seeder.next_u64())
+    }
+
+    pub(crate) fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(GAMMA);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+}
+
+/// Returns a uniformly random number below `n`, which must not be zero,
+/// by Lemire's multiply-and-reject method.
+pub(crate) fn below<F: FnMut() -> u64>(next: &mut F, n: usize) -> usize {
+    let n = n as u64;
+    let mut m = u128::from(next()) * u128::from(n);
+    if (m as u64) < n {
+        let threshold = n.wrapping_neg() % n;
+        while (m as u64) < threshold {
+            m = u128::from(next()) * u128::from(n);
+        }
+    }
+    (m >> 64) as usize
+}
+
+impl<K, V> RawTable<K, V> {
+    /// Returns a uniformly random entry by picking buckets until one is
+    /// full.  The table must not be empty.
+    pub(crate) fn sample_bucket<F: FnMut() -> u64>(&self, next: &mut F) -> (&K, &V) {
+        let mask = self.capacity() - 1;
+        loop {
+            let index = next() as usize & mask;
+            match Bucket::at_index(self, index).peek() {
+                Full(bucket) => return bucket.into_refs(),
+                Empty(_) => continue,
+            }
+        }
+    }
+
+    /// Returns whether rejection sampling is fast enough for this table.
+    pub(crate) fn is_dense(&self) -> bool {
+        self.size().saturating_mul(DENSE) >= self.capacity()
+    }
+}
+
+#[cfg(feature = "rand_core")]
+impl<K, V, S> HashMap<K, V, S> {
+    /// Returns `n` entries chosen uniformly at random with replacement, so
+    /// the same entry may be returned more than once.  Returns an empty
+    /// vector if the map is empty.
+    ///
+    /// This requires the `rand_core` feature.  `par_sample` is always
+    /// available, and takes a seed instead of a generator.
+    ///
+    /// Entries are found by picking random buckets of the table until a
+    /// full one turns up, without iterating the map.  If the table is
+    /// mostly empty, e.g. after many removals, random ranks are picked
+    /// instead, and found in a single pass over the table.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rayon_hash::HashMap;
+    ///
+    /// let map: HashMap<u32, u32> = (0..1000).map(|i| (i, i * 2)).collect();
+    /// let sample = map.sample(&mut rand::thread_rng(), 10);
+    /// assert_eq!(sample.len(), 10);
+    /// assert!(sample.iter().all(|&(k, v)| *v == k * 2));
+    /// ```
+    pub fn sample<R: RngCore + ?Sized>(&self, rng: &mut R, n: usize) -> Vec<(&K, &V)> {
+        let mut next = || rng.next_u64();
+        if self.is_empty() {
+            return Vec::new();
+        }
+        if self.table.is_dense() {
+            return (0..n).map(|_| self.table.sample_bucket(&mut next)).collect();
+        }
+
+        // Pick the ranks of the entries, and visit them in order, keeping
+        // track of where each one was drawn.
+        let mut ranks: Vec<(usize, usize)> = (0..n).map(|i| (below(&mut next, self.len()), i)).collect();
+        ranks.sort_unstable();
+        let mut sample = vec![None; n];
+        let mut entries = self.iter().enumerate();
+        let mut last = None;
+        for (rank, i) in ranks {
+            // Repeated ranks get the same entry.
+            if last.map(|(r, _)| r) != Some(rank) {
+                last = entries.by_ref().find(|&(j, _)| j == rank);
+            }
+            sample[i] = last.map(|(_, entry)| entry);
+        }
+        sample.into_iter().map(Option::unwrap).collect()
+    }
+}
+
+#[cfg(test)]
+mod test_sample {
+    use super::{below, SplitMix64};
+
+    #[test]
+    fn test_below() {
+        let mut rng = SplitMix64::stream(1, 0);
+        let mut next = || rng.next_u64();
+        let mut counts = [0usize; 10];
+        for _ in 0..100_000 {
+            counts[below(&mut next, 10)] += 1;
+        }
+        assert!(counts.iter().all(|&c| c > 9000 && c < 11_000), "{:?}", counts);
+        assert_eq!(below(&mut next, 1), 0);
+        assert_ne!(SplitMix64::stream(1, 0).next_u64(), SplitMix64::stream(1, 1).next_u64());
+    }
+
+    #[test]
+    #[cfg(feature = "rand_core")]
+    fn test_sample() {
+        use rand::SeedableRng;
+        use rand_xorshift::XorShiftRng;
+        use super::super::map::HashMap;
+
+        let mut rng = XorShiftRng::from_seed([7; 16]);
+        let empty: HashMap<u32, u32> = HashMap::new();
+        assert!(empty.sample(&mut rng, 10).is_empty());
+
+        let mut map: HashMap<u32, u32> = (0..1000).map(|i| (i, i)).collect();
+        let sample = map.sample(&mut rng, 20_000);
+        assert_eq!(sample.len(), 20_000);
+        let mut hits = vec![0; 1000];
+        for (&k, &v) in sample {
+            assert_eq!(k, v);
+            hits[k as usize] += 1;
+        }
+        assert!(hits.iter().all(|&h| h > 0));
+
+        // A sparse table, with a few entries left in a big one.
+        map.retain(|&k, _| k % 100 == 0);
+        assert!(!map.table.is_dense());
+        let sample = map.sample(&mut rng, 1000);
+        let mut hits = vec![0; 10];
+        for (&k, _) in sample {
+            assert_eq!(k % 100, 0);
+            hits[k as usize / 100] += 1;
+        }
+        assert!(hits.iter().all(|&h| h > 50), "{:?}", hits);
+    }
+}
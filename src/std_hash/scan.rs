@@ -0,0 +1,185 @@
+use std::cmp;
+
+use super::map::HashMap;
+use super::table::{Bucket, RawTable};
+use super::table::BucketState::{Empty, Full};
+
+/// How many empty buckets a call to `scan` may visit for each entry it was
+/// asked for, before it returns early.
+const EMPTY_VISITS: usize = 10;
+
+/// Reverses the bits of a cursor.
+fn reverse_bits(mut v: u64) -> u64 {
+    v = (v >> 1) & 0x5555_5555_5555_5555 | (v & 0x5555_5555_5555_5555) << 1;
+    v = (v >> 2) & 0x3333_3333_3333_3333 | (v & 0x3333_3333_3333_3333) << 2;
+    v = (v >> 4) & 0x0f0f_0f0f_0f0f_0f0f | (v & 0x0f0f_0f0f_0f0f_0f0f) << 4;
+    v = (v >> 8) & 0x00ff_00ff_00ff_00ff | (v & 0x00ff_00ff_00ff_00ff) << 8;
+    v = (v >> 16) & 0x0000_ffff_0000_ffff | (v & 0x0000_ffff_0000_ffff) << 16;
+    v.rotate_left(32)
+}
+
+impl<K, V> RawTable<K, V> {
+    /// Pushes the entries whose ideal bucket is `ideal`, and returns whether
+    /// there were any.
+    ///
+    /// Robin hood hashing keeps each run of full buckets sorted by ideal
+    /// bucket, so these entries are together in the run from `ideal`, after
+    /// any entries displaced from earlier buckets.
+    fn scan_ideal<'a>(&'a self, ideal: usize, out: &mut Vec<(&'a K, &'a V)>) -> bool {
+        let mask = self.capacity() - 1;
+        let len = out.len();
+        for distance in 0..self.capacity() {
+            match Bucket::at_index(self, ideal + distance).peek() {
+                Full(bucket) => {
+                    let displacement = bucket.displacement();
+                    if displacement < distance {
+                        break;
+                    } else if displacement == distance {
+                        debug_assert_eq!(bucket.hash().inspect() & mask, ideal);
+                        out.push(bucket.into_refs());
+                    }
+                }
+                Empty(_) => break,
+            }
+        }
+        out.len() > len
+    }
+}
+
+impl<K, V, S> HashMap<K, V, S> {
+    /// Iterates over the map in steps, like the `SCAN` command of Redis.
+    /// Start with a `cursor` of 0, and pass the returned cursor to the next
+    /// call, until it's 0 again.  Each call returns about `count` entries,
+    /// but possibly more or fewer, or none.
+    ///
+    /// Every entry that is in the map for the whole scan is returned at
+    /// least once, even if the map is modified or resized between calls.
+    /// Entries added or removed during the scan may or may not be returned,
+    /// and entries may be returned more than once if the table shrinks.
+    ///
+    /// The cursor walks the buckets where the entries ideally belong, which
+    /// for a table of `2^n` buckets are the lowest `n` bits of their hashes.
+    /// It counts up in the bits reversed, so that a bucket of a resized
+    /// table corresponds to buckets that are scanned together, before or
+    /// after the cursor.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rayon_hash::HashMap;
+    ///
+    /// let mut map: HashMap<u32, u32> = (0..100).map(|i| (i, i)).collect();
+    /// let mut seen = Vec::new();
+    /// let mut cursor = 0;
+    /// loop {
+    ///     let (page, next) = map.scan(cursor, 10);
+    ///     seen.extend(page.into_iter().map(|(&k, _)| k));
+    ///     // The map can be modified between pages.
+    ///     map.insert(1000 + next as u32 % 1000, 0);
+    ///     cursor = next;
+    ///     if cursor == 0 {
+    ///         break;
+    ///     }
+    /// }
+    /// seen.sort();
+    /// seen.dedup();
+    /// assert!((0..100).all(|i| seen.binary_search(&i).is_ok()));
+    /// ```
+    pub fn scan(&self, cursor: u64, count: usize) -> (Vec<(&K, &V)>, u64) {
+        let mut entries = Vec::with_capacity(cmp::min(count, self.len()));
+        let capacity = self.table.capacity();
+        if capacity == 0 {
+            return (entries, 0);
+        }
+        let mask = capacity as u64 - 1;
+        let mut cursor = cursor;
+        let mut empty_visits = count.saturating_mul(EMPTY_VISITS);
+        loop {
+            if !self.table.scan_ideal((cursor & mask) as usize, &mut entries) {
+                empty_visits = empty_visits.saturating_sub(1);
+            }
+            // Increment the cursor's bits above the mask, reversed.
+            cursor = reverse_bits(reverse_bits(cursor | !mask).wrapping_add(1));
+            if cursor == 0 || entries.len() >= count || empty_visits == 0 {
+                return (entries, cursor);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_scan {
+    use super::super::map::HashMap;
+    use super::super::set::HashSet;
+    use super::reverse_bits;
+
+    #[test]
+    fn test_reverse_bits() {
+        assert_eq!(reverse_bits(1), 1 << 63);
+        assert_eq!(reverse_bits(0b1011), 0b1101 << 60);
+        assert_eq!(reverse_bits(reverse_bits(0x1234_5678_9abc_def0)), 0x1234_5678_9abc_def0);
+    }
+
+    fn scan_all(map: &HashMap<u32, u32>, count: usize) -> Vec<u32> {
+        let mut keys = Vec::new();
+        let mut cursor = 0;
+        loop {
+            let (page, next) = map.scan(cursor, count);
+            keys.extend(page.into_iter().map(|(&k, _)| k));
+            cursor = next;
+            if cursor == 0 {
+                return keys;
+            }
+        }
+    }
+
+    #[test]
+    fn test_scan() {
+        let empty: HashMap<u32, u32> = HashMap::new();
+        assert_eq!(empty.scan(0, 10), (vec![], 0));
+
+        let map: HashMap<u32, u32> = (0..10_000).map(|i| (i, i)).collect();
+        for &count in &[1, 7, 100, 100_000] {
+            let mut keys = scan_all(&map, count);
+            keys.sort();
+            assert_eq!(keys, (0..10_000).collect::<Vec<_>>());
+        }
+        assert_eq!(map.scan(0, 100_000).1, 0);
+        assert_eq!(map.scan(0, !0).0.len(), 10_000);
+    }
+
+    #[test]
+    fn test_resize_between_calls() {
+        for &grow in &[true, false] {
+            let mut map: HashMap<u32, u32> = (0..5000).map(|i| (i, i)).collect();
+            if !grow {
+                map.reserve(100_000);
+            }
+            let mut seen = HashSet::new();
+            let mut cursor = 0;
+            let mut step = 0;
+            loop {
+                let (page, next) = map.scan(cursor, 50);
+                seen.extend(page.into_iter().map(|(&k, _)| k));
+                cursor = next;
+                if cursor == 0 {
+                    break;
+                }
+                step += 1;
+                if grow {
+                    // Add entries, growing the table several times.
+                    map.extend((0..200).map(|i| (100_000 + step * 200 + i, 0)));
+                } else if step % 10 == 0 {
+                    // Remove some entries from 1000 up, and shrink.
+                    map.retain(|&k, _| k < 1000 || k % 7 != step / 10 % 7);
+                    map.shrink_to_fit();
+                }
+            }
+            for i in 0..5000 {
+                if map.contains_key(&i) {
+                    assert!(seen.contains(&i), "missed {} (grow: {})", i, grow);
+                }
+            }
+        }
+    }
+}
@@ -1,6 +1,6 @@
 use std::borrow::Borrow;
 use std::fmt;
-use std::hash::{Hash, BuildHasher};
+use std::hash::{Hash, BuildHasher, Hasher};
 use std::iter::{Chain, FromIterator, FusedIterator};
 use std::ops::{BitOr, BitAnd, BitXor, Sub};
 
@@ -799,6 +799,14 @@ impl<T, S> Eq for HashSet<T, S>
 {
 }
 
+/// Hashes the set's values independently of their order in the table, in
+/// the same way as the `HashMap` implementation.
+impl<T: Hash, S> Hash for HashSet<T, S> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.map.hash(state)
+    }
+}
+
 // #[stable(feature = "rust1", since = "1.0.0")]
 impl<T, S> fmt::Debug for HashSet<T, S>
     where T: Eq + Hash + fmt::Debug,
@@ -1699,6 +1707,20 @@ mod test_set {
         assert_eq!(s1, s2);
     }
 
+    #[test]
+    fn test_hash() {
+        let a: HashSet<_> = (0..10).collect();
+        let mut b = HashSet::with_capacity(100);
+        b.extend((0..10).rev());
+        let c: HashSet<_> = (1..11).collect();
+
+        let mut sets = HashSet::new();
+        assert!(sets.insert(a));
+        assert!(!sets.insert(b));
+        assert!(sets.insert(c));
+        assert_eq!(sets.len(), 2);
+    }
+
     #[test]
     fn test_show() {
         let mut set = HashSet::new();
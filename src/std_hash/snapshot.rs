@@ -0,0 +1,301 @@
+use std::cmp;
+use std::hash::{BuildHasher, BuildHasherDefault, Hash, Hasher};
+use std::io;
+use std::mem;
+
+use super::codec::{hasher_id, invalid_data, BinaryCodec, Decode, Encode};
+use super::map::HashMap;
+use super::table::RawTable;
+
+/// A `BuildHasher` that hashes the same way every time it's created with the
+/// same settings, in any process, unlike `RandomState`.
+///
+/// This is required by [`HashMap::from_raw_bytes`], since a snapshot keeps
+/// the stored hashes of its entries, and they are only of use if the
+/// reloaded map hashes keys the same way.
+///
+/// [`HashMap::from_raw_bytes`]: struct.HashMap.html#method.from_raw_bytes
+pub trait DeterministicState: BuildHasher {}
+
+/// `BuildHasherDefault` is deterministic as long as `H::default()` is,
+/// which holds for hashers with fixed keys like `DefaultHasher`.
+impl<H: Default + Hasher> DeterministicState for BuildHasherDefault<H> {}
+
+/// Types with no padding, so that every byte of their values is initialized,
+/// and they can be copied into a snapshot byte for byte.
+///
+/// This is implemented for the primitive number types, `bool`, `char`, and
+/// arrays of them.  Tuples and most structs have padding, unless all their
+/// fields happen to have the same alignment.
+///
+/// # Safety
+///
+/// The type must not have any padding, not even in some of its values, as
+/// enums may, since reading uninitialized bytes is undefined behavior.
+pub unsafe trait NoPadding: Copy {}
+
+macro_rules! impl_no_padding {
+    ($($t:ty)*) => {
+        $(unsafe impl NoPadding for $t {})*
+    };
+}
+
+impl_no_padding! {
+    () bool char f32 f64
+    u8 u16 u32 u64 u128 usize
+    i8 i16 i32 i64 i128 isize
+}
+
+macro_rules! impl_no_padding_arrays {
+    ($($n:expr)*) => {
+        $(unsafe impl<T: NoPadding> NoPadding for [T; $n] {})*
+    };
+}
+
+impl_no_padding_arrays! {
+    0 1 2 3 4 5 6 7 8 9 10 11 12 13 14 15 16
+    17 18 19 20 21 22 23 24 25 26 27 28 29 30 31 32
+}
+
+/// The start of every snapshot.
+const MAGIC: [u8; 4] = *b"RHRW";
+
+/// The current version of the snapshot format.
+const VERSION: u32 = 1;
+
+/// The size of a snapshot's header.
+pub(crate) const HEADER_LEN: usize = 56;
+
+/// The offset of the table in a snapshot, after the header and then enough
+/// padding to align the table like a `HashMap`'s own allocation, provided
+/// that the snapshot itself is aligned at least as much, e.g. to a page.
+pub(crate) fn table_offset<K, V>() -> usize {
+    let align = cmp::max(mem::align_of::<usize>(), mem::align_of::<(K, V)>());
+    (HEADER_LEN + align - 1) & !(align - 1)
+}
+
+/// The header of a snapshot, which must match the map that reloads it.
+struct RawHeader {
+    hasher_id: u64,
+    usize_bytes: u32,
+    little_endian: u32,
+    pair_size: u64,
+    pair_align: u64,
+    capacity: u64,
+    size: u64,
+}
+
+impl RawHeader {
+    fn new<K, V>(hasher_id: u64, capacity: usize, size: usize) -> RawHeader {
+        RawHeader {
+            hasher_id,
+            usize_bytes: mem::size_of::<usize>() as u32,
+            little_endian: cfg!(target_endian = "little") as u32,
+            pair_size: mem::size_of::<(K, V)>() as u64,
+            pair_align: mem::align_of::<(K, V)>() as u64,
+            capacity: capacity as u64,
+            size: size as u64,
+        }
+    }
+
+    fn write(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&MAGIC);
+        BinaryCodec.encode(&VERSION, out);
+        BinaryCodec.encode(&self.hasher_id, out);
+        BinaryCodec.encode(&self.usize_bytes, out);
+        BinaryCodec.encode(&self.little_endian, out);
+        BinaryCodec.encode(&self.pair_size, out);
+        BinaryCodec.encode(&self.pair_align, out);
+        BinaryCodec.encode(&self.capacity, out);
+        BinaryCodec.encode(&self.size, out);
+    }
+
+    fn read(input: &mut &[u8]) -> io::Result<RawHeader> {
+        if input.len() < HEADER_LEN || input[..4] != MAGIC {
+            return Err(invalid_data("not a map snapshot"));
+        }
+        *input = &input[4..];
+        let version: u32 = BinaryCodec.decode(input)?;
+        if version != VERSION {
+            return Err(invalid_data("unknown snapshot version"));
+        }
+        Ok(RawHeader {
+            hasher_id: BinaryCodec.decode(input)?,
+            usize_bytes: BinaryCodec.decode(input)?,
+            little_endian: BinaryCodec.decode(input)?,
+            pair_size: BinaryCodec.decode(input)?,
+            pair_align: BinaryCodec.decode(input)?,
+            capacity: BinaryCodec.decode(input)?,
+            size: BinaryCodec.decode(input)?,
+        })
+    }
+}
+
+impl<K, V, S> HashMap<K, V, S>
+    where K: Eq + Hash + Copy,
+          V: Copy,
+          S: DeterministicState
+{
+    /// Returns a snapshot of the map: a header, padded to the table's
+    /// alignment, and then the table's memory exactly as it's laid out, with
+    /// the hashes, keys and values of every bucket.  Reloading it with
+    /// [`from_raw_bytes`] is little more than a copy, with no rehashing.
+    ///
+    /// The snapshot is only meant for the same program, or at least the same
+    /// key and value types on the same platform.  The keys and values are
+    /// copied byte for byte, so they must not have any padding, while the
+    /// padding between them is written as zeros.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rayon_hash::hash_map::DefaultHasher;
+    /// use rayon_hash::HashMap;
+    /// use std::hash::BuildHasherDefault;
+    ///
+    /// type Fixed = BuildHasherDefault<DefaultHasher>;
+    ///
+    /// let map: HashMap<u32, u64, Fixed> = (0..100).map(|i| (i, u64::from(i) * 3)).collect();
+    /// let bytes = map.to_raw_bytes();
+    ///
+    /// let copy = unsafe { HashMap::<u32, u64, _>::from_raw_bytes(&bytes, Fixed::default()) };
+    /// assert_eq!(copy.unwrap(), map);
+    /// ```
+    ///
+    /// [`from_raw_bytes`]: #method.from_raw_bytes
+    pub fn to_raw_bytes(&self) -> Vec<u8>
+        where K: NoPadding,
+              V: NoPadding
+    {
+        let capacity = self.table.capacity();
+        let header = RawHeader::new::<K, V>(hasher_id(self.hasher()), capacity, self.len());
+        let (len, _) = RawTable::<K, V>::raw_layout(capacity).expect("table size overflow");
+        let offset = table_offset::<K, V>();
+        let mut out = Vec::with_capacity(offset + len);
+        header.write(&mut out);
+        out.resize(offset, 0);
+        self.table.write_raw_bytes(&mut out);
+        out
+    }
+
+    /// Reloads a snapshot from [`to_raw_bytes`], with the same table layout,
+    /// so the map can be probed right away.
+    ///
+    /// Returns an error of kind `InvalidData` if the snapshot was taken with
+    /// different key and value layouts or on a different platform, or if
+    /// `hash_builder` doesn't hash the same way as the original map's.
+    /// Beyond the header, only the top bit of each hash and the number of
+    /// full buckets are checked, so most corruption isn't detected.
+    ///
+    /// # Safety
+    ///
+    /// The keys and values are copied without any checks, so the snapshot
+    /// must have been taken of a `HashMap<K, V, _>` with these very types,
+    /// and must not have been modified since.  Otherwise they may not be
+    /// valid values, e.g. a `bool` that isn't 0 or 1, or a dangling
+    /// reference.
+    ///
+    /// [`to_raw_bytes`]: #method.to_raw_bytes
+    pub unsafe fn from_raw_bytes(bytes: &[u8], hash_builder: S) -> io::Result<HashMap<K, V, S>> {
+        let (capacity, size, input) = read_snapshot::<K, V, S>(bytes, &hash_builder)?;
+        let table = RawTable::from_raw_bytes(capacity, size, input).map_err(invalid_data)?;
+        Ok(HashMap::from_raw_parts(hash_builder, table))
+    }
+}
+
+/// Checks a snapshot's header against the key and value types and the hash
+/// builder, and returns the table's capacity and size, and then its bytes.
+pub(crate) fn read_snapshot<'a, K, V, S>(bytes: &'a [u8], hash_builder: &S)
+    -> io::Result<(usize, usize, &'a [u8])>
+    where S: BuildHasher
+{
+    let mut input = bytes;
+    let header = RawHeader::read(&mut input)?;
+    let expected = RawHeader::new::<K, V>(hasher_id(hash_builder), 0, 0);
+    if header.usize_bytes != expected.usize_bytes
+        || header.little_endian != expected.little_endian
+        || header.pair_size != expected.pair_size
+        || header.pair_align != expected.pair_align
+    {
+        return Err(invalid_data("snapshot of a different layout"));
+    }
+    if header.hasher_id != expected.hasher_id {
+        return Err(invalid_data("snapshot from a different hasher"));
+    }
+    let padding = table_offset::<K, V>() - HEADER_LEN;
+    if input.len() < padding {
+        return Err(invalid_data("wrong number of table bytes"));
+    }
+    Ok((header.capacity as usize, header.size as usize, &input[padding..]))
+}
+
+#[cfg(test)]
+mod test_snapshot {
+    use super::super::map::{DefaultHasher, HashMap};
+    use std::hash::BuildHasherDefault;
+    use std::io::ErrorKind;
+    use std::mem;
+
+    type Fixed = BuildHasherDefault<DefaultHasher>;
+
+    #[test]
+    fn test_round_trip() {
+        let mut map: HashMap<u64, [u32; 2], Fixed> = HashMap::default();
+        let empty = unsafe { HashMap::<u64, [u32; 2], _>::from_raw_bytes(&map.to_raw_bytes(), Fixed::default()) };
+        assert!(empty.unwrap().is_empty());
+
+        for i in 0..10_000 {
+            map.insert(i * 7, [i as u32, !i as u32]);
+        }
+        for i in 0..1000 {
+            map.remove(&(i * 14));
+        }
+        let bytes = map.to_raw_bytes();
+        let mut copy = unsafe { HashMap::<u64, [u32; 2], _>::from_raw_bytes(&bytes, Fixed::default()) }.unwrap();
+        assert_eq!(copy, map);
+        assert_eq!(copy.capacity(), map.capacity());
+        assert_eq!(copy.to_raw_bytes(), bytes);
+
+        // The reloaded table is fully usable.
+        copy.insert(1, [1, 1]);
+        assert_eq!(copy.remove(&(7 * 3)), Some([3, !3]));
+        assert_eq!(copy.get(&14), None);
+        assert_eq!(copy.len(), map.len());
+    }
+
+    #[test]
+    fn test_padded_pairs() {
+        // The pairs have padding between the keys and values.
+        let map: HashMap<u8, u64, Fixed> = (0..200).map(|i| (i, u64::from(i) << 40)).collect();
+        let bytes = map.to_raw_bytes();
+        let copy = unsafe { HashMap::<u8, u64, _>::from_raw_bytes(&bytes, Fixed::default()) }.unwrap();
+        assert_eq!(copy, map);
+        assert_eq!(copy.to_raw_bytes(), bytes);
+    }
+
+    #[test]
+    fn test_mismatch() {
+        let map: HashMap<u32, u32, Fixed> = (0..100).map(|i| (i, i)).collect();
+        let bytes = map.to_raw_bytes();
+        let load = |bytes: &[u8]| unsafe { HashMap::<u32, u32, _>::from_raw_bytes(bytes, Fixed::default()) };
+        assert!(load(&bytes).is_ok());
+
+        let wrong_type = unsafe { HashMap::<u64, u32, _>::from_raw_bytes(&bytes, Fixed::default()) };
+        assert_eq!(wrong_type.unwrap_err().kind(), ErrorKind::InvalidData);
+
+        assert_eq!(load(&bytes[..bytes.len() - 1]).unwrap_err().kind(), ErrorKind::InvalidData);
+        assert_eq!(load(&bytes[..10]).unwrap_err().kind(), ErrorKind::InvalidData);
+
+        // Filling in an empty bucket's hash is caught.
+        let mut corrupt = bytes.clone();
+        let n = mem::size_of::<usize>();
+        let capacity = map.table.capacity();
+        let offset = super::table_offset::<u32, u32>();
+        let hashes = &mut corrupt[offset..offset + n * capacity];
+        let empty = hashes.chunks_mut(n).find(|hash| hash.iter().all(|&b| b == 0)).unwrap();
+        for b in empty {
+            *b = 0xff;
+        }
+        assert_eq!(load(&corrupt).unwrap_err().kind(), ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,285 @@
+use std::fmt;
+use std::iter::FusedIterator;
+use std::marker::PhantomData;
+use std::ops::Range;
+
+use super::map::HashMap;
+use super::table::RawBucket;
+
+impl<K, V, S> HashMap<K, V, S> {
+    /// Splits the table into `n` views of consecutive, disjoint ranges of
+    /// buckets, of nearly equal sizes, which give mutable access to the
+    /// values in their ranges.  The keys can't be modified, as that could
+    /// break the table.
+    ///
+    /// The views can be sent to other threads, e.g. with `rayon::join` or
+    /// `rayon::scope`, for algorithms that don't fit a parallel iterator,
+    /// and split further with [`TableSliceMut::split_at`].  The number of
+    /// entries in each range depends on the hashes, and isn't even.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rayon_hash::HashMap;
+    ///
+    /// let mut map: HashMap<u32, u32> = (0..1000).map(|i| (i, i)).collect();
+    /// let mut slices = map.split_mut(2);
+    /// let right = slices.pop().unwrap();
+    /// let left = slices.pop().unwrap();
+    ///
+    /// let (a, b) = rayon::join(
+    ///     || left.into_iter().map(|(_, v)| { *v += 1; 1 }).sum::<usize>(),
+    ///     || right.into_iter().map(|(_, v)| { *v += 1; 1 }).sum::<usize>(),
+    /// );
+    /// assert_eq!(a + b, 1000);
+    /// assert!(map.iter().all(|(k, v)| *v == k + 1));
+    /// ```
+    ///
+    /// [`TableSliceMut::split_at`]: struct.TableSliceMut.html#method.split_at
+    pub fn split_mut(&mut self, n: usize) -> Vec<TableSliceMut<'_, K, V>> {
+        let capacity = self.table.capacity();
+        let (len, extra) = match n {
+            0 => (0, 0),
+            _ => (capacity / n, capacity % n),
+        };
+        let mut start = 0;
+        (0..n)
+            .map(|i| {
+                let end = start + len + (i < extra) as usize;
+                let slice = TableSliceMut {
+                    bucket: self.table.raw_bucket_at(start),
+                    end,
+                    marker: PhantomData,
+                };
+                start = end;
+                slice
+            }).collect()
+    }
+}
+
+/// A view of a range of a `HashMap`'s buckets, with mutable access to the
+/// values.
+///
+/// This `struct` is created by the [`split_mut`] method on [`HashMap`].
+///
+/// [`split_mut`]: struct.HashMap.html#method.split_mut
+/// [`HashMap`]: struct.HashMap.html
+pub struct TableSliceMut<'a, K: 'a, V: 'a> {
+    bucket: RawBucket<K, V>,
+    end: usize,
+    marker: PhantomData<(&'a K, &'a mut V)>,
+}
+
+unsafe impl<K: Sync, V: Send> Send for TableSliceMut<'_, K, V> {}
+unsafe impl<K: Sync, V: Sync> Sync for TableSliceMut<'_, K, V> {}
+
+impl<'a, K, V> TableSliceMut<'a, K, V> {
+    /// Returns the range of bucket indices in the view.
+    #[inline]
+    pub fn buckets(&self) -> Range<usize> {
+        self.bucket.index()..self.end
+    }
+
+    /// Splits the view in two at the bucket index `mid`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `mid` is not within `buckets()`, or its end.
+    pub fn split_at(self, mid: usize) -> (Self, Self) {
+        let buckets = self.buckets();
+        assert!(buckets.start <= mid && mid <= buckets.end, "bucket index out of range");
+        let mut right = TableSliceMut { ..self };
+        right.bucket.index_add(mid - buckets.start);
+        let left = TableSliceMut { end: mid, ..self };
+        (left, right)
+    }
+
+    /// Returns an iterator over the entries in the view.
+    pub fn iter(&self) -> TableSliceIter<'_, K, V> {
+        TableSliceIter {
+            bucket: self.bucket,
+            end: self.end,
+            marker: PhantomData,
+        }
+    }
+
+    /// Returns an iterator over the entries in the view, with mutable
+    /// references to the values.
+    pub fn iter_mut(&mut self) -> TableSliceIterMut<'_, K, V> {
+        TableSliceIterMut {
+            bucket: self.bucket,
+            end: self.end,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<'a, K, V> IntoIterator for TableSliceMut<'a, K, V> {
+    type Item = (&'a K, &'a mut V);
+    type IntoIter = TableSliceIterMut<'a, K, V>;
+
+    fn into_iter(self) -> TableSliceIterMut<'a, K, V> {
+        TableSliceIterMut {
+            bucket: self.bucket,
+            end: self.end,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<K: fmt::Debug, V: fmt::Debug> fmt::Debug for TableSliceMut<'_, K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+/// Moves `bucket` to the next full one before `end`, and returns it.
+fn next_full<K, V>(bucket: &mut RawBucket<K, V>, end: usize) -> Option<RawBucket<K, V>> {
+    while bucket.index() < end {
+        let item = *bucket;
+        bucket.index_add(1);
+        if unsafe { !item.is_empty() } {
+            return Some(item);
+        }
+    }
+    None
+}
+
+/// An iterator over the entries of a `TableSliceMut`.
+///
+/// This `struct` is created by the [`iter`] method on [`TableSliceMut`].
+///
+/// [`iter`]: struct.TableSliceMut.html#method.iter
+/// [`TableSliceMut`]: struct.TableSliceMut.html
+pub struct TableSliceIter<'a, K: 'a, V: 'a> {
+    bucket: RawBucket<K, V>,
+    end: usize,
+    marker: PhantomData<(&'a K, &'a V)>,
+}
+
+unsafe impl<K: Sync, V: Sync> Send for TableSliceIter<'_, K, V> {}
+unsafe impl<K: Sync, V: Sync> Sync for TableSliceIter<'_, K, V> {}
+
+impl<K, V> Clone for TableSliceIter<'_, K, V> {
+    fn clone(&self) -> Self {
+        TableSliceIter { ..*self }
+    }
+}
+
+impl<'a, K, V> Iterator for TableSliceIter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<(&'a K, &'a V)> {
+        next_full(&mut self.bucket, self.end).map(|bucket| unsafe {
+            let pair_ptr = bucket.pair();
+            (&(*pair_ptr).0, &(*pair_ptr).1)
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.end - self.bucket.index()))
+    }
+}
+
+impl<K, V> FusedIterator for TableSliceIter<'_, K, V> {}
+
+impl<K: fmt::Debug, V: fmt::Debug> fmt::Debug for TableSliceIter<'_, K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.clone()).finish()
+    }
+}
+
+/// A mutable iterator over the entries of a `TableSliceMut`.
+///
+/// This `struct` is created by the [`iter_mut`] method on [`TableSliceMut`],
+/// or by consuming it.
+///
+/// [`iter_mut`]: struct.TableSliceMut.html#method.iter_mut
+/// [`TableSliceMut`]: struct.TableSliceMut.html
+pub struct TableSliceIterMut<'a, K: 'a, V: 'a> {
+    bucket: RawBucket<K, V>,
+    end: usize,
+    marker: PhantomData<(&'a K, &'a mut V)>,
+}
+
+unsafe impl<K: Sync, V: Send> Send for TableSliceIterMut<'_, K, V> {}
+unsafe impl<K: Sync, V: Sync> Sync for TableSliceIterMut<'_, K, V> {}
+
+impl<'a, K, V> Iterator for TableSliceIterMut<'a, K, V> {
+    type Item = (&'a K, &'a mut V);
+
+    fn next(&mut self) -> Option<(&'a K, &'a mut V)> {
+        next_full(&mut self.bucket, self.end).map(|bucket| unsafe {
+            let pair_ptr = bucket.pair();
+            (&(*pair_ptr).0, &mut (*pair_ptr).1)
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.end - self.bucket.index()))
+    }
+}
+
+impl<K, V> FusedIterator for TableSliceIterMut<'_, K, V> {}
+
+impl<K: fmt::Debug, V: fmt::Debug> fmt::Debug for TableSliceIterMut<'_, K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let iter = TableSliceIter {
+            bucket: self.bucket,
+            end: self.end,
+            marker: PhantomData,
+        };
+        f.debug_list().entries(iter).finish()
+    }
+}
+
+#[cfg(test)]
+mod test_split {
+    use super::super::map::HashMap;
+    use super::TableSliceMut;
+
+    fn process(slice: TableSliceMut<'_, u32, u32>) -> usize {
+        let buckets = slice.buckets();
+        if buckets.len() > 64 {
+            let (left, right) = slice.split_at(buckets.start + buckets.len() / 2);
+            let (a, b) = rayon::join(|| process(left), || process(right));
+            a + b
+        } else {
+            // Per-range state, e.g. a local buffer.
+            let mut local = Vec::new();
+            for (&k, v) in slice {
+                *v += k;
+                local.push(k);
+            }
+            local.len()
+        }
+    }
+
+    #[test]
+    fn test_split_mut() {
+        let mut empty: HashMap<u32, u32> = HashMap::new();
+        let slices = empty.split_mut(3);
+        assert_eq!(slices.len(), 3);
+        assert!(slices.iter().all(|s| s.buckets().is_empty() && s.iter().next().is_none()));
+        assert!(empty.split_mut(0).is_empty());
+
+        let mut map: HashMap<u32, u32> = (0..10_000).map(|i| (i, 1)).collect();
+        let capacity = map.bucket_count();
+        for &n in &[1, 3, 7, capacity + 5] {
+            let mut slices = map.split_mut(n);
+            assert_eq!(slices.len(), n);
+            let mut next = 0;
+            for slice in &slices {
+                assert_eq!(slice.buckets().start, next);
+                next = slice.buckets().end;
+            }
+            assert_eq!(next, capacity);
+            let count: usize = slices.iter_mut().map(|s| s.iter_mut().count()).sum();
+            assert_eq!(count, 10_000);
+        }
+
+        let mut slices = map.split_mut(1);
+        assert_eq!(process(slices.pop().unwrap()), 10_000);
+        assert!(map.iter().all(|(&k, &v)| v == k + 1));
+    }
+}
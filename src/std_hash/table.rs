@@ -9,6 +9,8 @@ use std::ops::{Deref, DerefMut};
 use std::ptr::{self, NonNull};
 use std::hint;
 
+use super::snapshot::NoPadding;
+
 use self::BucketState::*;
 
 /// Integer type used for stored hash values.
@@ -245,6 +247,15 @@ impl<K, V> RawBucket<K, V> {
     pub(crate) unsafe fn set_empty(&self) {
         *self.hash() = EMPTY_BUCKET;
     }
+    pub(crate) unsafe fn safe_hash(&self) -> SafeHash {
+        SafeHash { hash: *self.hash() }
+    }
+    /// Fills this bucket without touching the table's size, which the
+    /// caller must then fix up.
+    pub(crate) unsafe fn write(&self, hash: SafeHash, pair: (K, V)) {
+        *self.hash() = hash.inspect();
+        ptr::write(self.pair(), pair);
+    }
 }
 
 // Buckets hold references to the table.
@@ -867,6 +878,101 @@ impl<K, V> RawTable<K, V> {
     }
 }
 
+impl<K: Copy, V: Copy> RawTable<K, V> {
+    /// The number of bytes in the allocation of a table with `capacity`
+    /// buckets, and the offset of its pairs, or `None` if that overflows.
+    pub(crate) fn raw_layout(capacity: usize) -> Option<(usize, usize)> {
+        calculate_layout::<K, V>(capacity).ok().map(|(layout, offset)| (layout.size(), offset))
+    }
+
+    /// Appends the table's allocation to `out` byte for byte, as laid out by
+    /// `calculate_layout`.  The padding and the pairs of empty buckets are
+    /// undefined in the table, so they are written as zeros.
+    ///
+    /// The keys and values are copied separately, so any padding between
+    /// them is skipped, but they must not have padding of their own.
+    pub(crate) fn write_raw_bytes(&self, out: &mut Vec<u8>)
+        where K: NoPadding,
+              V: NoPadding
+    {
+        let capacity = self.capacity();
+        if capacity == 0 {
+            return;
+        }
+        let (layout, pairs_offset) = calculate_layout::<K, V>(capacity)
+            .unwrap_or_else(|_| unsafe { hint::unreachable_unchecked() });
+        let start = out.len();
+        out.resize(start + layout.size(), 0);
+
+        let pair_size = size_of::<(K, V)>();
+        unsafe {
+            let dst = out.as_mut_ptr().add(start);
+            ptr::copy_nonoverlapping(self.hashes.ptr() as *const u8, dst,
+                                     capacity * size_of::<HashUint>());
+            let mut bucket = self.raw_bucket_at(0);
+            for i in 0..capacity {
+                if *bucket.hash() != EMPTY_BUCKET {
+                    let pair = bucket.pair();
+                    let pair_dst = dst.add(pairs_offset + i * pair_size);
+                    let key = &(*pair).0 as *const K as *const u8;
+                    let value = &(*pair).1 as *const V as *const u8;
+                    ptr::copy_nonoverlapping(key,
+                                             pair_dst.add(key as usize - pair as usize),
+                                             size_of::<K>());
+                    ptr::copy_nonoverlapping(value,
+                                             pair_dst.add(value as usize - pair as usize),
+                                             size_of::<V>());
+                }
+                bucket.idx += 1;
+            }
+        }
+    }
+
+    /// Rebuilds a table from the bytes of `write_raw_bytes`.  The hashes are
+    /// checked, so that there are `size` full buckets, but the pairs are
+    /// copied as they are.
+    ///
+    /// This is unsafe because the pairs must be valid values of `(K, V)`.
+    pub(crate) unsafe fn from_raw_bytes(capacity: usize, size: usize, bytes: &[u8])
+        -> Result<RawTable<K, V>, &'static str>
+    {
+        if capacity == 0 {
+            return if size == 0 && bytes.is_empty() {
+                Ok(RawTable::new(0))
+            } else {
+                Err("invalid table size")
+            };
+        }
+        if !capacity.is_power_of_two() || size >= capacity {
+            return Err("invalid table size");
+        }
+        if Self::raw_layout(capacity).map(|(len, _)| len) != Some(bytes.len()) {
+            return Err("wrong number of table bytes");
+        }
+
+        let mut table = RawTable::new_uninitialized(capacity);
+        ptr::copy_nonoverlapping(bytes.as_ptr(), table.hashes.ptr() as *mut u8, bytes.len());
+
+        // Every full bucket must have a `SafeHash`, with its top bit set.
+        let hashes = ::std::slice::from_raw_parts(table.hashes.ptr(), capacity);
+        let safe_bit: HashUint = !(!0 >> 1);
+        let mut full = 0;
+        for &hash in hashes {
+            if hash != EMPTY_BUCKET {
+                if hash & safe_bit == 0 {
+                    return Err("invalid hash");
+                }
+                full += 1;
+            }
+        }
+        if full != size {
+            return Err("wrong number of entries");
+        }
+        table.size = size;
+        Ok(table)
+    }
+}
+
 /// A raw iterator. The basis for some other iterators in this module. Although
 /// this interface is safe, it's not used outside this module.
 struct RawBuckets<'a, K, V> {
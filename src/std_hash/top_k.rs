@@ -0,0 +1,66 @@
+use std::cmp::{Ordering, Reverse};
+use std::collections::BinaryHeap;
+
+/// Keeps the `k` greatest items pushed to it according to `cmp`, in a
+/// min-heap of at most `k` items.  Partial results can be merged, so each
+/// parallel job can keep its own.
+pub(crate) struct TopK<'f, T, F: 'f> {
+    k: usize,
+    cmp: &'f F,
+    heap: BinaryHeap<Reverse<ByCmp<'f, T, F>>>,
+}
+
+struct ByCmp<'f, T, F: 'f>(T, &'f F);
+
+impl<T, F: Fn(&T, &T) -> Ordering> PartialEq for ByCmp<'_, T, F> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<T, F: Fn(&T, &T) -> Ordering> Eq for ByCmp<'_, T, F> {}
+
+impl<T, F: Fn(&T, &T) -> Ordering> PartialOrd for ByCmp<'_, T, F> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T, F: Fn(&T, &T) -> Ordering> Ord for ByCmp<'_, T, F> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.1)(&self.0, &other.0)
+    }
+}
+
+impl<'f, T, F: Fn(&T, &T) -> Ordering> TopK<'f, T, F> {
+    pub(crate) fn new(k: usize, cmp: &'f F) -> Self {
+        TopK { k, cmp, heap: BinaryHeap::new() }
+    }
+
+    pub(crate) fn push(&mut self, item: T) {
+        if self.heap.len() < self.k {
+            self.heap.push(Reverse(ByCmp(item, self.cmp)));
+        } else if let Some(mut least) = self.heap.peek_mut() {
+            if (self.cmp)(&(least.0).0, &item) == Ordering::Less {
+                *least = Reverse(ByCmp(item, self.cmp));
+            }
+        }
+    }
+
+    pub(crate) fn merge(mut self, other: Self) -> Self {
+        for Reverse(ByCmp(item, _)) in other.heap {
+            self.push(item);
+        }
+        self
+    }
+
+    /// Returns the items from the greatest.
+    pub(crate) fn into_vec(self) -> Vec<T> {
+        // Ascending order of `Reverse` is descending order of the items.
+        self.heap
+            .into_sorted_vec()
+            .into_iter()
+            .map(|Reverse(ByCmp(item, _))| item)
+            .collect()
+    }
+}
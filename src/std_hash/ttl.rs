@@ -0,0 +1,458 @@
+use std::borrow::Borrow;
+use std::cmp;
+use std::fmt;
+use std::hash::{Hash, BuildHasher};
+use std::iter::FusedIterator;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use super::map::{self, HashMap, RandomState};
+
+/// A source of the current time for a [`TtlHashMap`].
+///
+/// [`SystemClock`] reads the real time, while [`ManualClock`] is a logical
+/// clock that only moves when told to, which is handy for tests.
+///
+/// [`TtlHashMap`]: struct.TtlHashMap.html
+/// [`SystemClock`]: struct.SystemClock.html
+/// [`ManualClock`]: struct.ManualClock.html
+pub trait Clock {
+    /// A point in time.
+    type Instant: Copy + Ord;
+
+    /// A span of time, added to the current time to get an expiry.
+    type Duration;
+
+    /// Returns the current time.
+    fn now(&self) -> Self::Instant;
+
+    /// Returns the time `ttl` from now.
+    fn after(&self, ttl: Self::Duration) -> Self::Instant;
+}
+
+/// The real, monotonic time, from `std::time::Instant`.
+///
+/// Times after now saturate at about a century from now, so a huge TTL
+/// can stand for "never expires".
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SystemClock;
+
+/// About a hundred years, which an `Instant` can always be moved forward
+/// by.  (`Instant::checked_add` needs a newer Rust.)
+const MAX_TTL_SECS: u64 = 100 * 365 * 24 * 60 * 60;
+
+impl Clock for SystemClock {
+    type Instant = Instant;
+    type Duration = Duration;
+
+    #[inline]
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    #[inline]
+    fn after(&self, ttl: Duration) -> Instant {
+        Instant::now() + cmp::min(ttl, Duration::from_secs(MAX_TTL_SECS))
+    }
+}
+
+/// A logical clock counting ticks, which only moves when it is set or
+/// advanced.
+///
+/// Clones share the same time, so a map can own one clone while the caller
+/// drives another.
+///
+/// # Examples
+///
+/// ```
+/// use rayon_hash::hash_ttl::{Clock, ManualClock};
+///
+/// let clock = ManualClock::new();
+/// let other = clock.clone();
+/// clock.advance(5);
+/// assert_eq!(other.now(), 5);
+/// assert_eq!(other.after(10), 15);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct ManualClock {
+    now: Arc<AtomicUsize>,
+}
+
+impl ManualClock {
+    /// Creates a clock at tick zero.
+    #[inline]
+    pub fn new() -> ManualClock {
+        Default::default()
+    }
+
+    /// Sets the current tick.
+    #[inline]
+    pub fn set(&self, now: usize) {
+        self.now.store(now, Ordering::SeqCst);
+    }
+
+    /// Moves the clock forward by `ticks`.
+    #[inline]
+    pub fn advance(&self, ticks: usize) {
+        self.now.fetch_add(ticks, Ordering::SeqCst);
+    }
+}
+
+impl Clock for ManualClock {
+    type Instant = usize;
+    type Duration = usize;
+
+    #[inline]
+    fn now(&self) -> usize {
+        self.now.load(Ordering::SeqCst)
+    }
+
+    #[inline]
+    fn after(&self, ttl: usize) -> usize {
+        self.now().saturating_add(ttl)
+    }
+}
+
+/// A hash map whose entries expire at a given time.
+///
+/// Each entry records when it expires, as an instant of the map's [`Clock`].
+/// An entry is live until then, and expired from then on.  Lookups treat
+/// expired entries as absent, but they still hold their memory until they
+/// are swept with [`sweep`] or [`par_sweep`], or overwritten.
+///
+/// # Examples
+///
+/// ```
+/// use rayon_hash::hash_ttl::{Clock, ManualClock, TtlHashMap};
+///
+/// let clock = ManualClock::new();
+/// let mut sessions = TtlHashMap::with_clock(clock.clone());
+/// sessions.insert("alice", 1, 10);
+/// sessions.insert("bob", 2, 20);
+///
+/// clock.set(15);
+/// assert_eq!(sessions.get("alice"), None);
+/// assert_eq!(sessions.get("bob"), Some(&2));
+///
+/// clock.set(25);
+/// assert_eq!(sessions.len(), 2);
+/// assert_eq!(sessions.sweep(clock.now()), 2);
+/// assert!(sessions.is_empty());
+/// ```
+///
+/// [`Clock`]: trait.Clock.html
+/// [`sweep`]: #method.sweep
+/// [`par_sweep`]: #method.par_sweep
+#[derive(Clone)]
+pub struct TtlHashMap<K, V, C: Clock = SystemClock, S = RandomState> {
+    clock: C,
+    pub(crate) map: HashMap<K, Timed<V, C::Instant>, S>,
+}
+
+/// A value with its expiry.
+#[derive(Clone)]
+pub(crate) struct Timed<V, T> {
+    pub(crate) value: V,
+    pub(crate) expires: T,
+}
+
+impl<V, T: Ord> Timed<V, T> {
+    #[inline]
+    pub(crate) fn is_live(&self, now: T) -> bool {
+        now < self.expires
+    }
+}
+
+impl<K: Hash + Eq, V> TtlHashMap<K, V, SystemClock, RandomState> {
+    /// Creates an empty `TtlHashMap` using the system clock.
+    #[inline]
+    pub fn new() -> TtlHashMap<K, V, SystemClock, RandomState> {
+        Default::default()
+    }
+}
+
+impl<K: Hash + Eq, V, C: Clock> TtlHashMap<K, V, C, RandomState> {
+    /// Creates an empty `TtlHashMap` using the given clock.
+    #[inline]
+    pub fn with_clock(clock: C) -> TtlHashMap<K, V, C, RandomState> {
+        TtlHashMap::with_clock_and_hasher(clock, Default::default())
+    }
+}
+
+impl<K, V, C: Clock, S> TtlHashMap<K, V, C, S> {
+    /// Returns a reference to the map's clock.
+    #[inline]
+    pub fn clock(&self) -> &C {
+        &self.clock
+    }
+
+    /// Returns the number of entries the map can hold without reallocating.
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.map.capacity()
+    }
+
+    /// Returns the number of entries in the map, including expired ones
+    /// that haven't been swept yet.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Returns `true` if the map contains no entries, live or expired.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Clears the map, removing all entries.  Keeps the allocated memory for
+    /// reuse.
+    #[inline]
+    pub fn clear(&mut self) {
+        self.map.clear();
+    }
+
+    /// An iterator visiting the live entries in arbitrary order, as of the
+    /// clock's current time.
+    #[inline]
+    pub fn iter(&self) -> TtlIter<'_, K, V, C::Instant> {
+        TtlIter { inner: self.map.iter(), now: self.clock.now() }
+    }
+}
+
+impl<K, V, C, S> TtlHashMap<K, V, C, S>
+    where K: Eq + Hash,
+          C: Clock,
+          S: BuildHasher
+{
+    /// Creates an empty `TtlHashMap` which will use the given clock, and the
+    /// given hash builder to hash keys.
+    #[inline]
+    pub fn with_clock_and_hasher(clock: C, hash_builder: S) -> TtlHashMap<K, V, C, S> {
+        TtlHashMap { clock, map: HashMap::with_hasher(hash_builder) }
+    }
+
+    /// Returns a reference to the map's `BuildHasher`.
+    #[inline]
+    pub fn hasher(&self) -> &S {
+        self.map.hasher()
+    }
+
+    /// Reserves capacity for at least `additional` more entries.
+    #[inline]
+    pub fn reserve(&mut self, additional: usize) {
+        self.map.reserve(additional);
+    }
+
+    fn get_timed<Q>(&self, key: &Q) -> Option<&Timed<V, C::Instant>>
+        where K: Borrow<Q>,
+              Q: ?Sized + Hash + Eq
+    {
+        let now = self.clock.now();
+        self.map.get(key).filter(|timed| timed.is_live(now))
+    }
+
+    /// Returns a reference to the value of a key, if it hasn't expired.
+    ///
+    /// The key may be any borrowed form of the map's key type, but
+    /// [`Hash`] and [`Eq`] on the borrowed form *must* match those for
+    /// the key type.
+    ///
+    /// [`Eq`]: ../../std/cmp/trait.Eq.html
+    /// [`Hash`]: ../../std/hash/trait.Hash.html
+    #[inline]
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+        where K: Borrow<Q>,
+              Q: ?Sized + Hash + Eq
+    {
+        self.get_timed(key).map(|timed| &timed.value)
+    }
+
+    /// Returns a mutable reference to the value of a key, if it hasn't
+    /// expired.
+    #[inline]
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+        where K: Borrow<Q>,
+              Q: ?Sized + Hash + Eq
+    {
+        let now = self.clock.now();
+        self.map.get_mut(key).filter(|timed| timed.is_live(now)).map(|timed| &mut timed.value)
+    }
+
+    /// Returns `true` if the map contains the key, and it hasn't expired.
+    #[inline]
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+        where K: Borrow<Q>,
+              Q: ?Sized + Hash + Eq
+    {
+        self.get_timed(key).is_some()
+    }
+
+    /// Returns when a key expires, if it hasn't already.
+    #[inline]
+    pub fn expires_at<Q>(&self, key: &Q) -> Option<C::Instant>
+        where K: Borrow<Q>,
+              Q: ?Sized + Hash + Eq
+    {
+        self.get_timed(key).map(|timed| timed.expires)
+    }
+
+    /// Inserts a key-value pair which expires `ttl` from now.
+    ///
+    /// If the key was present and hadn't expired, its old value is returned.
+    #[inline]
+    pub fn insert(&mut self, key: K, value: V, ttl: C::Duration) -> Option<V> {
+        let expires = self.clock.after(ttl);
+        self.insert_until(key, value, expires)
+    }
+
+    /// Inserts a key-value pair which expires at `expires`.
+    ///
+    /// If the key was present and hadn't expired, its old value is returned.
+    pub fn insert_until(&mut self, key: K, value: V, expires: C::Instant) -> Option<V> {
+        let now = self.clock.now();
+        self.map
+            .insert(key, Timed { value, expires })
+            .filter(|timed| timed.is_live(now))
+            .map(|timed| timed.value)
+    }
+
+    /// Removes the entries that have expired by `now`, and returns how many
+    /// were removed.  Pass `clock().now()` to sweep up to the current time.
+    pub fn sweep(&mut self, now: C::Instant) -> usize {
+        let len = self.map.len();
+        self.map.retain(|_, timed| timed.is_live(now));
+        len - self.map.len()
+    }
+
+    /// Removes a key from the map, returning its value if it hadn't
+    /// expired.
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+        where K: Borrow<Q>,
+              Q: ?Sized + Hash + Eq
+    {
+        let now = self.clock.now();
+        self.map.remove(key).filter(|timed| timed.is_live(now)).map(|timed| timed.value)
+    }
+}
+
+impl<K, V, C, S> fmt::Debug for TtlHashMap<K, V, C, S>
+    where K: fmt::Debug,
+          V: fmt::Debug,
+          C: Clock
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<K, V, C, S> Default for TtlHashMap<K, V, C, S>
+    where K: Eq + Hash,
+          C: Clock + Default,
+          S: BuildHasher + Default
+{
+    /// Creates an empty `TtlHashMap<K, V, C, S>`, with the `Default` values
+    /// for the clock and the hasher.
+    fn default() -> TtlHashMap<K, V, C, S> {
+        TtlHashMap::with_clock_and_hasher(Default::default(), Default::default())
+    }
+}
+
+impl<'a, K, V, C: Clock, S> IntoIterator for &'a TtlHashMap<K, V, C, S> {
+    type Item = (&'a K, &'a V);
+    type IntoIter = TtlIter<'a, K, V, C::Instant>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// An iterator over the live entries of a `TtlHashMap`.
+///
+/// This `struct` is created by the [`iter`] method on [`TtlHashMap`].
+///
+/// [`iter`]: struct.TtlHashMap.html#method.iter
+/// [`TtlHashMap`]: struct.TtlHashMap.html
+pub struct TtlIter<'a, K: 'a, V: 'a, T: 'a> {
+    inner: map::Iter<'a, K, Timed<V, T>>,
+    now: T,
+}
+
+impl<K, V, T: Copy> Clone for TtlIter<'_, K, V, T> {
+    fn clone(&self) -> Self {
+        TtlIter { inner: self.inner.clone(), now: self.now }
+    }
+}
+
+impl<'a, K, V, T: Copy + Ord> Iterator for TtlIter<'a, K, V, T> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<(&'a K, &'a V)> {
+        let now = self.now;
+        self.inner
+            .by_ref()
+            .find(|&(_, timed)| timed.is_live(now))
+            .map(|(key, timed)| (key, &timed.value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, self.inner.size_hint().1)
+    }
+}
+
+impl<K, V, T: Copy + Ord> FusedIterator for TtlIter<'_, K, V, T> {}
+
+#[cfg(test)]
+mod test_ttl {
+    use super::{Clock, ManualClock, TtlHashMap};
+    use std::time::Duration;
+
+    #[test]
+    fn test_expiry() {
+        let clock = ManualClock::new();
+        let mut map = TtlHashMap::with_clock(clock.clone());
+        for i in 0..100 {
+            assert_eq!(map.insert(i, i * 10, i), None);
+        }
+        assert_eq!(map.get(&0), None);
+        assert_eq!(map.get(&1), Some(&10));
+        assert_eq!(map.expires_at(&7), Some(7));
+
+        clock.set(50);
+        assert_eq!(map.iter().count(), 49);
+        assert!(map.iter().all(|(&k, &v)| k > 50 && v == k * 10));
+        assert_eq!(map.len(), 100);
+        assert!(!map.contains_key(&50));
+        assert_eq!(map.get_mut(&50), None);
+        *map.get_mut(&51).unwrap() += 1;
+        assert_eq!(map.get(&51), Some(&511));
+
+        // Overwriting or removing an expired entry doesn't return it.
+        assert_eq!(map.insert(10, 0, 100), None);
+        assert_eq!(map.insert(10, 1, 100), Some(0));
+        assert_eq!(map.remove(&20), None);
+        assert_eq!(map.remove(&60), Some(600));
+        assert_eq!(map.len(), 98);
+
+        let now = clock.now();
+        assert_eq!(map.sweep(now), 49);
+        assert_eq!(map.len(), 49);
+        assert_eq!(map.sweep(now), 0);
+        assert_eq!(map.get(&10), Some(&1));
+    }
+
+    #[test]
+    fn test_system_clock() {
+        let mut map = TtlHashMap::new();
+        map.insert("long", 1, Duration::from_secs(3600));
+        map.insert("gone", 2, Duration::from_secs(0));
+        map.insert("never", 3, Duration::from_secs(!0));
+        assert_eq!(map.get("long"), Some(&1));
+        assert_eq!(map.get("gone"), None);
+        let now = map.clock().now();
+        assert_eq!(map.sweep(now), 1);
+        assert_eq!(map.len(), 2);
+        assert_eq!(map.get("never"), Some(&3));
+    }
+}